@@ -8,6 +8,12 @@ pub fn list_env_variables(storage: State<Storage>, server_id: i64) -> Vec<EnvVar
     storage.get_env_variables(server_id)
 }
 
+/// 获取所有全局环境变量
+#[tauri::command]
+pub fn list_global_env_variables(storage: State<Storage>) -> Vec<EnvVariable> {
+    storage.get_global_env_variables()
+}
+
 /// 获取单个环境变量
 #[tauri::command]
 pub fn get_env_variable(storage: State<Storage>, id: i64) -> Option<EnvVariable> {
@@ -0,0 +1,20 @@
+use crate::db::models::MessageQuery;
+use crate::db::Storage;
+use crate::export::{self, ExportFormat, ExportSummary};
+use std::path::PathBuf;
+use tauri::State;
+
+/// 导出历史消息到文件，返回导出的条数
+///
+/// format: "csv" | "ndjson" | "replay"；redact 为 true 时替换凭据和敏感变量的值
+#[tauri::command]
+pub async fn export_message_history(
+    storage: State<'_, Storage>,
+    query: MessageQuery,
+    format: String,
+    path: String,
+    redact: bool,
+) -> Result<ExportSummary, String> {
+    let format = ExportFormat::parse(&format)?;
+    export::export_messages(&storage, &query, format, &PathBuf::from(path), redact)
+}
@@ -1,9 +1,15 @@
 pub mod env;
+pub mod export;
 pub mod log;
 pub mod mqtt;
 pub mod publish;
+pub mod replay;
+pub mod retention;
+pub mod schedule;
 pub mod script;
 pub mod server;
 pub mod settings;
 pub mod subscription;
 pub mod template;
+pub mod vault;
+pub mod workspace;
@@ -1,23 +1,33 @@
 use crate::db::Storage;
+use crate::mqtt::tracker::TopicGrant;
 use crate::mqtt::MqttManager;
+use crate::scheduler::SchedulerManager;
 use tauri::State;
 
 #[tauri::command]
 pub async fn mqtt_connect(
     storage: State<'_, Storage>,
     mqtt: State<'_, MqttManager>,
+    scheduler: State<'_, SchedulerManager>,
     server_id: i64,
 ) -> Result<(), String> {
     // 从存储获取 server 配置
-    let server = storage
-        .get_server(server_id)
-        .ok_or("Server not found")?;
+    let server = storage.get_connect_server(server_id)?;
 
-    mqtt.connect(server).await
+    mqtt.connect(server).await?;
+
+    // 恢复该服务器已启用的定时任务
+    scheduler.resume_server(&storage, server_id);
+    Ok(())
 }
 
 #[tauri::command]
-pub async fn mqtt_disconnect(mqtt: State<'_, MqttManager>, server_id: i64) -> Result<(), String> {
+pub async fn mqtt_disconnect(
+    mqtt: State<'_, MqttManager>,
+    scheduler: State<'_, SchedulerManager>,
+    server_id: i64,
+) -> Result<(), String> {
+    scheduler.suspend_server(server_id);
     mqtt.disconnect(server_id).await
 }
 
@@ -39,7 +49,7 @@ pub async fn mqtt_subscribe(
     server_id: i64,
     topic: String,
     qos: u8,
-) -> Result<(), String> {
+) -> Result<TopicGrant, String> {
     mqtt.subscribe(server_id, topic, qos).await
 }
 
@@ -1,6 +1,11 @@
-use crate::db::models::{MessageHistory, PublishPayload};
+use crate::db::models::{
+    MessageHistory, MessagePage, MessageProperties, MessageQuery, PublishPayload,
+};
 use crate::db::Storage;
+use crate::mqtt::client::OutgoingMessage;
+use crate::mqtt::link::ProtocolVersion;
 use crate::mqtt::MqttManager;
+use std::time::Duration;
 use tauri::State;
 
 #[tauri::command]
@@ -10,39 +15,80 @@ pub async fn publish_message(
     server_id: i64,
     message: PublishPayload,
 ) -> Result<MessageHistory, String> {
-    // 转换消息内容
-    let payload_bytes = match message.format.as_str() {
-        "hex" => hex::decode(message.payload.replace(" ", ""))
-            .map_err(|e| format!("HEX decode failed: {}", e))?,
-        _ => message.payload.as_bytes().to_vec(),
-    };
+    let await_ack = message.await_ack;
+    let ack_timeout_ms = message.ack_timeout_ms;
+    let (history, receiver) = mqtt_manager
+        .publish_message(&storage, server_id, message)
+        .await?;
+    let message_id = history.id.ok_or("Message ID missing")?;
 
-    // 发布消息
-    mqtt_manager
-        .publish(
-            server_id,
-            message.topic.clone(),
-            payload_bytes,
-            message.qos as u8,
-            message.retain,
-        )
-        .await
-        .map_err(|e| e.to_string())?;
+    if await_ack {
+        let timeout = Duration::from_millis(ack_timeout_ms.unwrap_or(10_000));
+        let error = match tokio::time::timeout(timeout, receiver).await {
+            Ok(Ok(_)) => None,
+            Ok(Err(_)) => Some("Connection closed before acknowledgement".to_string()),
+            // 确认已经先一步到达时保留其结果
+            Err(_) if !mqtt_manager.abandon_publish(server_id, message_id) => None,
+            Err(_) => Some("Timed out waiting for acknowledgement".to_string()),
+        };
+        if let Some(e) = error {
+            storage.update_message_delivery(message_id, "failed", None, Some(e))?;
+        }
+    }
+
+    Ok(storage.get_message(message_id).unwrap_or(history))
+}
+
+/// 按原始字节重新发布一条历史消息，返回新的历史记录
+#[tauri::command]
+pub async fn resend_message(
+    storage: State<'_, Storage>,
+    mqtt_manager: State<'_, MqttManager>,
+    message_id: i64,
+) -> Result<MessageHistory, String> {
+    let original = storage.get_message(message_id).ok_or("Message not found")?;
+    let server = storage
+        .get_server(original.server_id)
+        .ok_or("Server not found")?;
+    let payload_bytes = original.payload_bytes();
+
+    // MQTT 3.1.1 连接不能携带属性
+    let properties = original
+        .properties
+        .clone()
+        .filter(|_| ProtocolVersion::parse(&server.protocol_version) == Ok(ProtocolVersion::V5))
+        .map(MessageProperties::publishable)
+        .filter(|p| !p.is_empty());
 
-    // 保存到历史记录
-    let history = MessageHistory {
+    let history = storage.create_message(MessageHistory {
         id: None,
-        server_id,
-        topic: message.topic,
-        payload: Some(message.payload),
-        payload_format: Some(message.format),
         direction: "publish".to_string(),
-        qos: message.qos,
-        retain: message.retain,
+        raw_payload: Some(payload_bytes.clone()),
+        properties: properties.clone(),
+        delivery_state: Some("queued".to_string()),
+        latency_ms: None,
+        delivery_error: None,
         created_at: None,
+        ..original
+    })?;
+    let new_id = history.id.ok_or("Message ID missing")?;
+
+    let outgoing = OutgoingMessage {
+        topic: history.topic.clone(),
+        payload: payload_bytes,
+        qos: history.qos as u8,
+        retain: history.retain,
+        properties,
     };
+    if let Err(e) = mqtt_manager
+        .publish_tracked(history.server_id, outgoing, Some(new_id))
+        .await
+    {
+        storage.update_message_delivery(new_id, "failed", None, Some(e.clone()))?;
+        return Err(e);
+    }
 
-    storage.create_message(history)
+    Ok(history)
 }
 
 #[tauri::command]
@@ -54,6 +100,15 @@ pub async fn get_message_history(
     Ok(storage.get_messages(server_id, limit.unwrap_or(100)))
 }
 
+/// 按条件分页查询历史消息
+#[tauri::command]
+pub async fn search_message_history(
+    storage: State<'_, Storage>,
+    query: MessageQuery,
+) -> Result<MessagePage, String> {
+    storage.search_messages(&query)
+}
+
 #[tauri::command]
 pub async fn clear_message_history(
     storage: State<'_, Storage>,
@@ -0,0 +1,40 @@
+use crate::replay::{ReplayManager, ReplayOptions, ReplaySource};
+use tauri::State;
+
+/// 开始回放录制的消息，返回回放任务 ID
+#[tauri::command]
+pub async fn start_replay(
+    replay_manager: State<'_, ReplayManager>,
+    server_id: i64,
+    source: ReplaySource,
+    options: Option<ReplayOptions>,
+) -> Result<String, String> {
+    replay_manager.start(server_id, source, options.unwrap_or_default())
+}
+
+/// 暂停回放
+#[tauri::command]
+pub fn pause_replay(
+    replay_manager: State<'_, ReplayManager>,
+    replay_id: String,
+) -> Result<(), String> {
+    replay_manager.pause(&replay_id)
+}
+
+/// 继续回放
+#[tauri::command]
+pub fn resume_replay(
+    replay_manager: State<'_, ReplayManager>,
+    replay_id: String,
+) -> Result<(), String> {
+    replay_manager.resume(&replay_id)
+}
+
+/// 停止回放
+#[tauri::command]
+pub fn stop_replay(
+    replay_manager: State<'_, ReplayManager>,
+    replay_id: String,
+) -> Result<(), String> {
+    replay_manager.stop(&replay_id)
+}
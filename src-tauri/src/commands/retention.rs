@@ -0,0 +1,23 @@
+use crate::db::Storage;
+use crate::retention::{RetentionManager, RetentionResult};
+use tauri::State;
+
+/// 立即按保留策略清理指定 Server 的历史消息
+#[tauri::command]
+pub async fn apply_message_retention(
+    storage: State<'_, Storage>,
+    retention_manager: State<'_, RetentionManager>,
+    server_id: i64,
+) -> Result<RetentionResult, String> {
+    let server = storage.get_server(server_id).ok_or("Server not found")?;
+    retention_manager.apply(&storage, &server)
+}
+
+/// 获取归档目录路径
+#[tauri::command]
+pub fn get_archive_dir(retention_manager: State<'_, RetentionManager>) -> String {
+    retention_manager
+        .get_archive_dir()
+        .to_string_lossy()
+        .to_string()
+}
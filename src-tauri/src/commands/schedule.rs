@@ -0,0 +1,116 @@
+use crate::db::models::ScheduledJob;
+use crate::db::Storage;
+use crate::mqtt::MqttManager;
+use crate::scheduler::{self, SchedulerManager};
+use tauri::State;
+
+/// 获取服务器的所有定时任务
+#[tauri::command]
+pub async fn list_scheduled_jobs(
+    storage: State<'_, Storage>,
+    server_id: i64,
+) -> Result<Vec<ScheduledJob>, String> {
+    Ok(storage.get_scheduled_jobs(server_id))
+}
+
+/// 创建定时任务，已启用且服务器已连接时立即开始
+#[tauri::command]
+pub async fn create_scheduled_job(
+    storage: State<'_, Storage>,
+    mqtt_manager: State<'_, MqttManager>,
+    scheduler: State<'_, SchedulerManager>,
+    job: ScheduledJob,
+) -> Result<ScheduledJob, String> {
+    scheduler::validate_job(&job)?;
+    let job = storage.create_scheduled_job(ScheduledJob {
+        id: None,
+        run_count: 0,
+        last_run_at: None,
+        ..job
+    })?;
+
+    if job.enabled && mqtt_manager.is_connected(job.server_id) {
+        scheduler.start(job.clone())?;
+    }
+    Ok(job)
+}
+
+/// 更新定时任务配置，运行中的任务按新配置重新开始
+#[tauri::command]
+pub async fn update_scheduled_job(
+    storage: State<'_, Storage>,
+    mqtt_manager: State<'_, MqttManager>,
+    scheduler: State<'_, SchedulerManager>,
+    job: ScheduledJob,
+) -> Result<ScheduledJob, String> {
+    scheduler::validate_job(&job)?;
+    let id = job.id.ok_or("Job ID missing")?;
+    let existing = storage
+        .get_scheduled_job(id)
+        .ok_or("Scheduled job not found")?;
+
+    // 发送次数由后台任务维护
+    let job = ScheduledJob {
+        server_id: existing.server_id,
+        run_count: existing.run_count,
+        last_run_at: existing.last_run_at,
+        ..job
+    };
+    scheduler.stop(id);
+    storage.update_scheduled_job(&job)?;
+
+    if job.enabled && mqtt_manager.is_connected(job.server_id) {
+        scheduler.start(job.clone())?;
+    }
+    Ok(storage.get_scheduled_job(id).unwrap_or(job))
+}
+
+/// 删除定时任务
+#[tauri::command]
+pub async fn delete_scheduled_job(
+    storage: State<'_, Storage>,
+    scheduler: State<'_, SchedulerManager>,
+    id: i64,
+) -> Result<(), String> {
+    scheduler.stop(id);
+    storage.delete_scheduled_job(id)
+}
+
+/// 启用并从头开始运行定时任务；服务器未连接时在连接后开始
+#[tauri::command]
+pub async fn start_scheduled_job(
+    storage: State<'_, Storage>,
+    mqtt_manager: State<'_, MqttManager>,
+    scheduler: State<'_, SchedulerManager>,
+    id: i64,
+) -> Result<ScheduledJob, String> {
+    let job = storage
+        .get_scheduled_job(id)
+        .ok_or("Scheduled job not found")?;
+    scheduler::validate_job(&job)?;
+
+    let job = ScheduledJob {
+        enabled: true,
+        run_count: 0,
+        last_run_at: None,
+        ..job
+    };
+    storage.update_scheduled_job(&job)?;
+
+    if mqtt_manager.is_connected(job.server_id) {
+        scheduler.start(job.clone())?;
+    }
+    Ok(job)
+}
+
+/// 停用并停止定时任务
+#[tauri::command]
+pub async fn stop_scheduled_job(
+    storage: State<'_, Storage>,
+    scheduler: State<'_, SchedulerManager>,
+    id: i64,
+) -> Result<(), String> {
+    storage.set_job_enabled(id, false)?;
+    scheduler.stop(id);
+    Ok(())
+}
@@ -1,6 +1,8 @@
 use tauri::State;
 use crate::db::models::{Script, CreateScriptRequest, UpdateScriptRequest};
 use crate::db::Storage;
+use crate::env;
+use crate::script::{self, ScriptTestRequest, ScriptTestResult};
 
 /// 获取服务器的所有脚本
 #[tauri::command]
@@ -23,12 +25,20 @@ pub fn get_enabled_scripts(storage: State<Storage>, server_id: i64, script_type:
 /// 创建脚本
 #[tauri::command]
 pub fn create_script(storage: State<Storage>, request: CreateScriptRequest) -> Result<i64, String> {
+    script::validate_script(&request.script_type, request.topic_filter.as_deref())?;
     storage.create_script(request)
 }
 
 /// 更新脚本
 #[tauri::command]
 pub fn update_script(storage: State<Storage>, request: UpdateScriptRequest) -> Result<(), String> {
+    if let Some(existing) = storage.get_script(request.id) {
+        let topic_filter = request
+            .topic_filter
+            .as_deref()
+            .or(existing.topic_filter.as_deref());
+        script::validate_script(&existing.script_type, topic_filter)?;
+    }
     storage.update_script(request)
 }
 
@@ -43,3 +53,26 @@ pub fn delete_script(storage: State<Storage>, id: i64) -> Result<(), String> {
 pub fn toggle_script(storage: State<Storage>, id: i64, enabled: bool) -> Result<(), String> {
     storage.toggle_script(id, enabled)
 }
+
+/// 使用示例主题和消息试运行脚本，返回处理结果、日志和耗时
+#[tauri::command]
+pub async fn test_script(
+    storage: State<'_, Storage>,
+    request: ScriptTestRequest,
+) -> Result<ScriptTestResult, String> {
+    let saved = match request.script_id {
+        Some(id) => Some(storage.get_script(id).ok_or("Script not found")?),
+        None => None,
+    };
+    let code = request
+        .code
+        .or_else(|| saved.as_ref().map(|s| s.code.clone()))
+        .ok_or("Script ID or code is required")?;
+    let env = request
+        .server_id
+        .or(saved.map(|s| s.server_id))
+        .map(|server_id| env::variables(&storage, server_id))
+        .unwrap_or_default();
+
+    Ok(script::test(code, request.payload, request.topic, env).await)
+}
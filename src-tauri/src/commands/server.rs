@@ -1,5 +1,6 @@
 use crate::db::models::MqttServer;
 use crate::db::Storage;
+use crate::scheduler::SchedulerManager;
 use tauri::State;
 
 #[tauri::command]
@@ -18,7 +19,12 @@ pub async fn update_server(storage: State<'_, Storage>, server: MqttServer) -> R
 }
 
 #[tauri::command]
-pub async fn delete_server(storage: State<'_, Storage>, id: i64) -> Result<(), String> {
+pub async fn delete_server(
+    storage: State<'_, Storage>,
+    scheduler: State<'_, SchedulerManager>,
+    id: i64,
+) -> Result<(), String> {
+    scheduler.suspend_server(id);
     storage.delete_server(id)
 }
 
@@ -3,6 +3,7 @@ use std::fs;
 use std::path::PathBuf;
 use tauri::{AppHandle, Manager};
 
+use crate::db::backup::{self, BackupInfo, StorageRecovery, StorageStatus};
 use crate::db::Storage;
 
 /// 获取当前数据存储路径
@@ -20,60 +21,123 @@ pub fn migrate_data_path(
     migrate: bool,
 ) -> Result<(), String> {
     let new_path = PathBuf::from(&new_path);
-    
+
     // 验证新路径
     if !new_path.is_absolute() {
         return Err("Please provide an absolute path".to_string());
     }
-    
+
     // 确保目标目录存在
     if let Some(parent) = new_path.parent() {
         fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
     }
-    
+
     // 如果需要迁移，复制当前数据到新位置
     if migrate {
-        let current_path = storage.get_file_path();
-        if current_path.exists() {
-            fs::copy(current_path, &new_path)
-                .map_err(|e| format!("Failed to copy data file: {}", e))?;
+        if new_path.exists() {
+            return Err("Target data file already exists".to_string());
         }
+        storage
+            .backup_to(&new_path)
+            .map_err(|e| format!("Failed to copy data file: {}", e))?;
     }
-    
+
     // 保存新路径配置（使用单独的配置文件）
     let config_path = app_handle
         .path()
         .app_data_dir()
         .map_err(|e| e.to_string())?
         .join("config.yaml");
-    
+
     let mut config_map: HashMap<String, String> = HashMap::new();
-    config_map.insert("data_path".to_string(), new_path.to_string_lossy().to_string());
-    
+    config_map.insert(
+        "data_path".to_string(),
+        new_path.to_string_lossy().to_string(),
+    );
+
     let config = serde_yaml::to_string(&config_map).map_err(|e| e.to_string())?;
-    
-    fs::write(&config_path, config)
+
+    backup::write_atomic(&config_path, config.as_bytes())
         .map_err(|e| format!("Failed to save config: {}", e))?;
-    
+
     Ok(())
 }
 
+/// 获取数据存储状态（数据文件损坏时 ok 为 false）
+#[tauri::command]
+pub fn get_storage_status(app_handle: AppHandle) -> Result<StorageStatus, String> {
+    if let Some(storage) = app_handle.try_state::<Storage>() {
+        return Ok(StorageStatus {
+            ok: true,
+            error: None,
+            file_path: storage.get_file_path().to_string_lossy().to_string(),
+        });
+    }
+    let recovery = app_handle
+        .try_state::<StorageRecovery>()
+        .ok_or("Storage is not initialized")?;
+    Ok(StorageStatus {
+        ok: false,
+        error: Some(recovery.error.clone()),
+        file_path: recovery.file_path.to_string_lossy().to_string(),
+    })
+}
+
+/// 列出数据文件的备份
+#[tauri::command]
+pub fn list_backups(app_handle: AppHandle) -> Result<Vec<BackupInfo>, String> {
+    Ok(backup::list_backups(&data_file_path(&app_handle)?))
+}
+
+/// 立即备份数据文件
+#[tauri::command]
+pub async fn create_backup(app_handle: AppHandle) -> Result<Option<BackupInfo>, String> {
+    tokio::task::spawn_blocking(move || {
+        let storage = app_handle
+            .try_state::<Storage>()
+            .ok_or("Storage is not available")?;
+        backup::rotate(&storage, true)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// 从备份恢复数据文件并重启应用（仅在恢复模式下可用）
+#[tauri::command]
+pub fn restore_backup(app_handle: AppHandle, name: String) -> Result<(), String> {
+    let recovery = app_handle
+        .try_state::<StorageRecovery>()
+        .ok_or("Backups can only be restored when the data file is damaged")?;
+    backup::restore(&recovery.file_path, &name)?;
+    app_handle.restart()
+}
+
+fn data_file_path(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    if let Some(storage) = app_handle.try_state::<Storage>() {
+        return Ok(storage.get_file_path().clone());
+    }
+    app_handle
+        .try_state::<StorageRecovery>()
+        .map(|recovery| recovery.file_path.clone())
+        .ok_or_else(|| "Storage is not initialized".to_string())
+}
+
 /// 选择文件夹对话框
 #[tauri::command]
 pub async fn select_data_folder(app_handle: AppHandle) -> Result<Option<String>, String> {
     use tauri_plugin_dialog::DialogExt;
-    
+
     let folder = app_handle
         .dialog()
         .file()
         .set_title("Select Data Directory")
         .blocking_pick_folder();
-    
+
     match folder {
         Some(file_path) => {
             // FilePath 需要转换为 PathBuf
             let path_buf = file_path.as_path().ok_or("Invalid path")?;
-            let data_file = path_buf.join("data.yaml");
+            let data_file = path_buf.join("data.db");
             Ok(Some(data_file.to_string_lossy().to_string()))
         }
         None => Ok(None),
@@ -19,17 +19,22 @@ pub async fn add_subscription(
         qos,
         is_active: true,
         color: None,
+        last_grant: None,
         created_at: None,
     };
 
     let subscription = storage.create_subscription(sub)?;
 
-    // 如果已连接，则订阅主题
+    // 如果已连接，则订阅主题（授权结果由 MqttManager 写入 last_grant）
     if mqtt_manager.is_connected(server_id) {
         mqtt_manager
             .subscribe(server_id, topic, qos as u8)
             .await
             .map_err(|e| e.to_string())?;
+
+        if let Some(updated) = subscription.id.and_then(|id| storage.get_subscription(id)) {
+            return Ok(updated);
+        }
     }
 
     Ok(subscription)
@@ -137,5 +142,9 @@ pub async fn update_subscription(
         }
     }
 
-    Ok(subscription)
+    // 重新读取以带上最新的授权结果
+    Ok(subscription
+        .id
+        .and_then(|id| storage.get_subscription(id))
+        .unwrap_or(subscription))
 }
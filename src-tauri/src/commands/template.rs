@@ -1,5 +1,13 @@
-use crate::db::models::{CommandTemplate, CreateTemplateRequest, UpdateTemplateRequest};
+use crate::db::models::{
+    CommandTemplate, CreateTemplateRequest, TemplateImportIssue, TemplateImportReport,
+    TemplateImportStrategy, UpdateTemplateRequest,
+};
 use crate::db::Storage;
+use crate::env;
+use crate::mqtt::topic;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
 use tauri::{command, State};
 
 #[command]
@@ -50,35 +58,149 @@ pub async fn export_templates(server_id: i64, storage: State<'_, Storage>) -> Re
     serde_json::to_string_pretty(&templates).map_err(|e| e.to_string())
 }
 
+/// 导入模板，逐条校验并按策略处理同名模板
 #[command]
 pub async fn import_templates(
     server_id: i64,
     json_data: String,
+    strategy: TemplateImportStrategy,
     storage: State<'_, Storage>,
-) -> Result<i32, String> {
-    let templates: Vec<CommandTemplate> =
+) -> Result<TemplateImportReport, String> {
+    let items: Vec<Value> =
         serde_json::from_str(&json_data).map_err(|e| format!("JSON parse error: {}", e))?;
 
-    let mut imported = 0;
-    for template in templates {
-        let req = CreateTemplateRequest {
-            server_id,
-            name: template.name,
-            topic: template.topic,
-            payload: template.payload,
-            payload_type: template.payload_type,
-            qos: template.qos,
-            retain: template.retain,
-            description: template.description,
-            category: template.category,
+    let existing: HashMap<String, i64> = storage
+        .get_templates(server_id)
+        .into_iter()
+        .filter_map(|t| Some((t.name.trim().to_string(), t.id?)))
+        .collect();
+    let mut seen = HashSet::new();
+    let mut report = TemplateImportReport::default();
+
+    for (index, item) in items.into_iter().enumerate() {
+        let name = item
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        let issue = |reason: String| TemplateImportIssue {
+            index,
+            name: name.clone(),
+            reason,
         };
 
-        if storage.create_template(req).is_ok() {
-            imported += 1;
+        let template = match serde_json::from_value::<ImportedTemplate>(item)
+            .map(|t| ImportedTemplate {
+                name: name.clone(),
+                ..t
+            })
+            .map_err(|e| e.to_string())
+            .and_then(|t| validate_template(&t).map(|_| t))
+        {
+            Ok(template) => template,
+            Err(e) => {
+                report.failed.push(issue(e));
+                continue;
+            }
+        };
+        if !seen.insert(name.clone()) {
+            report
+                .skipped
+                .push(issue("Duplicate name in import file".to_string()));
+            continue;
         }
+
+        let result = match (existing.get(&name), strategy) {
+            (Some(_), TemplateImportStrategy::Skip) => {
+                report.skipped.push(issue(
+                    "A template with this name already exists".to_string(),
+                ));
+                continue;
+            }
+            (Some(&id), TemplateImportStrategy::Overwrite) => storage
+                .update_template(UpdateTemplateRequest {
+                    id,
+                    name: Some(name.clone()),
+                    topic: Some(template.topic),
+                    payload: Some(template.payload),
+                    payload_type: Some(template.payload_type),
+                    qos: Some(template.qos),
+                    retain: Some(template.retain),
+                    // 空字符串清除原有的描述和分类
+                    description: Some(template.description.unwrap_or_default()),
+                    category: Some(template.category.unwrap_or_default()),
+                })
+                .map(|_| &mut report.updated),
+            (None, _) => storage
+                .create_template(CreateTemplateRequest {
+                    server_id,
+                    name: name.clone(),
+                    topic: template.topic,
+                    payload: template.payload,
+                    payload_type: template.payload_type,
+                    qos: template.qos,
+                    retain: template.retain,
+                    description: template.description,
+                    category: template.category,
+                })
+                .map(|_| &mut report.created),
+        };
+        match result {
+            Ok(names) => names.push(name),
+            Err(e) => report.failed.push(issue(e)),
+        }
+    }
+
+    Ok(report)
+}
+
+/// 导入文件中的模板（导出格式），id、使用次数等字段忽略
+#[derive(Deserialize)]
+struct ImportedTemplate {
+    name: String,
+    topic: String,
+    #[serde(default)]
+    payload: String,
+    payload_type: String,
+    #[serde(default)]
+    qos: i32,
+    #[serde(default)]
+    retain: bool,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    category: Option<String>,
+}
+
+/// 检查模板内容，负载中的变量占位符不参与格式检查
+fn validate_template(template: &ImportedTemplate) -> Result<(), String> {
+    if template.name.is_empty() {
+        return Err("Name must not be empty".to_string());
     }
+    if !(0..=2).contains(&template.qos) {
+        return Err(format!("Invalid QoS: {}", template.qos));
+    }
+    topic::validate_topic(&template.topic)?;
 
-    Ok(imported)
+    match template.payload_type.as_str() {
+        "json" => {
+            let payload = env::mask_placeholders(&template.payload, "0");
+            serde_json::from_str::<Value>(&payload)
+                .map(|_| ())
+                .map_err(|e| format!("Invalid JSON payload: {}", e))
+        }
+        "hex" => {
+            let payload: String = env::mask_placeholders(&template.payload, "")
+                .split_whitespace()
+                .collect();
+            hex::decode(payload)
+                .map(|_| ())
+                .map_err(|e| format!("Invalid HEX payload: {}", e))
+        }
+        "text" => Ok(()),
+        other => Err(format!("Invalid payload type: {}", other)),
+    }
 }
 
 #[command]
@@ -105,3 +227,56 @@ pub async fn duplicate_template(
 
     storage.create_template(req)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(topic: &str, payload_type: &str, payload: &str) -> ImportedTemplate {
+        ImportedTemplate {
+            name: "t".to_string(),
+            topic: topic.to_string(),
+            payload: payload.to_string(),
+            payload_type: payload_type.to_string(),
+            qos: 0,
+            retain: false,
+            description: None,
+            category: None,
+        }
+    }
+
+    #[test]
+    fn accepts_valid_templates() {
+        assert!(validate_template(&template("a/b", "text", "{{anything")).is_ok());
+        assert!(validate_template(&template("a/b", "json", r#"{"v": 1}"#)).is_ok());
+        assert!(validate_template(&template("a/b", "hex", "de ad BE EF")).is_ok());
+    }
+
+    #[test]
+    fn ignores_placeholders_in_payload() {
+        let json = r#"{"t": {{$timestamp}}, "id": "{{deviceId}}", "n": {{$randomInt(1, 9)}}}"#;
+        assert!(validate_template(&template("a/{{room}}", "json", json)).is_ok());
+        assert!(validate_template(&template("a/b", "hex", "01 {{crc}} 02")).is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        let mut unnamed = template("a/b", "text", "");
+        unnamed.name = String::new();
+        assert!(validate_template(&unnamed).is_err());
+
+        let mut bad_qos = template("a/b", "text", "");
+        bad_qos.qos = 3;
+        assert_eq!(validate_template(&bad_qos).unwrap_err(), "Invalid QoS: 3");
+
+        assert!(validate_template(&template("", "text", "")).is_err());
+        assert!(validate_template(&template("a/+", "text", "")).is_err());
+        assert!(validate_template(&template("a/b", "json", "{\"v\":")).is_err());
+        assert!(validate_template(&template("a/b", "hex", "0g")).is_err());
+        assert!(validate_template(&template("a/b", "hex", "abc")).is_err());
+        assert_eq!(
+            validate_template(&template("a/b", "xml", "")).unwrap_err(),
+            "Invalid payload type: xml"
+        );
+    }
+}
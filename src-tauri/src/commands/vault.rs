@@ -0,0 +1,37 @@
+use crate::db::vault::VaultStatus;
+use crate::db::Storage;
+use tauri::State;
+
+/// 获取保险库状态
+#[tauri::command]
+pub fn vault_status(storage: State<Storage>) -> VaultStatus {
+    storage.vault_status()
+}
+
+/// 设置主密码，已保存的 Server 凭据会被加密
+#[tauri::command]
+pub async fn setup_vault(storage: State<'_, Storage>, passphrase: String) -> Result<(), String> {
+    storage.setup_vault(&passphrase)
+}
+
+/// 使用主密码解锁保险库
+#[tauri::command]
+pub async fn unlock_vault(storage: State<'_, Storage>, passphrase: String) -> Result<(), String> {
+    storage.unlock_vault(&passphrase)
+}
+
+/// 锁定保险库，清除内存中的密钥
+#[tauri::command]
+pub fn lock_vault(storage: State<Storage>) {
+    storage.lock_vault()
+}
+
+/// 修改主密码
+#[tauri::command]
+pub async fn change_vault_passphrase(
+    storage: State<'_, Storage>,
+    current: String,
+    passphrase: String,
+) -> Result<(), String> {
+    storage.change_vault_passphrase(&current, &passphrase)
+}
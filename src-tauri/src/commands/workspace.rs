@@ -0,0 +1,83 @@
+use crate::db::Storage;
+use crate::mqtt::MqttManager;
+use crate::scheduler::SchedulerManager;
+use crate::workspace::external::{self, ExternalSource};
+use crate::workspace::{
+    self, ExportOptions, ImportMode, ImportReport, SERVER_FORMAT, WORKSPACE_FORMAT,
+};
+use std::path::PathBuf;
+use tauri::State;
+
+/// 导出整个工作区到文件，返回导出过程中的提示
+#[tauri::command]
+pub async fn export_workspace(
+    storage: State<'_, Storage>,
+    options: ExportOptions,
+    path: String,
+) -> Result<Vec<String>, String> {
+    let archive = workspace::export_workspace(&storage, &options)?;
+    workspace::write_archive(&archive, &PathBuf::from(path))?;
+    Ok(archive.warnings)
+}
+
+/// 从文件导入工作区
+///
+/// 替换模式会删除现有服务器，需要先断开所有连接。
+#[tauri::command]
+pub async fn import_workspace(
+    storage: State<'_, Storage>,
+    mqtt: State<'_, MqttManager>,
+    scheduler: State<'_, SchedulerManager>,
+    path: String,
+    mode: ImportMode,
+) -> Result<ImportReport, String> {
+    let archive = workspace::read_archive(&PathBuf::from(path), WORKSPACE_FORMAT)?;
+
+    if mode == ImportMode::Replace {
+        let server_ids: Vec<i64> = storage
+            .get_servers()
+            .into_iter()
+            .filter_map(|s| s.id)
+            .collect();
+        if server_ids.iter().any(|&id| mqtt.is_connected(id)) {
+            return Err("Disconnect all servers before replacing the workspace".to_string());
+        }
+        for id in server_ids {
+            scheduler.suspend_server(id);
+        }
+    }
+
+    workspace::import_workspace(&storage, archive, mode)
+}
+
+/// 导出单个服务器的配置（订阅、模板、脚本、定时任务和 Server 变量）到文件
+#[tauri::command]
+pub async fn export_server_config(
+    storage: State<'_, Storage>,
+    server_id: i64,
+    strip_credentials: bool,
+    path: String,
+) -> Result<(), String> {
+    let archive = workspace::export_server(&storage, server_id, strip_credentials)?;
+    workspace::write_archive(&archive, &PathBuf::from(path))
+}
+
+/// 从文件导入服务器配置，创建为新的服务器
+#[tauri::command]
+pub async fn import_server_config(
+    storage: State<'_, Storage>,
+    path: String,
+) -> Result<ImportReport, String> {
+    let archive = workspace::read_archive(&PathBuf::from(path), SERVER_FORMAT)?;
+    workspace::import_server(&storage, archive)
+}
+
+/// 从其他工具（MQTTX、MQTT Explorer）的导出文件导入连接配置
+#[tauri::command]
+pub async fn import_external_profiles(
+    storage: State<'_, Storage>,
+    path: String,
+    source: ExternalSource,
+) -> Result<ImportReport, String> {
+    external::import_profiles(&storage, &PathBuf::from(path), source)
+}
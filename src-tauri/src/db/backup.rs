@@ -0,0 +1,196 @@
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use super::Storage;
+
+/// 保留的自动备份数量
+pub const MAX_BACKUPS: usize = 5;
+/// 两次自动备份的最小间隔
+const BACKUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const BACKUP_PREFIX: &str = "data-";
+const BACKUP_EXTENSION: &str = "db";
+
+/// 数据库备份文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupInfo {
+    pub name: String,
+    pub path: String,
+    pub size: u64,
+    /// 备份时间（RFC 3339）
+    pub created_at: String,
+}
+
+/// 数据库无法打开时的状态，应用以恢复模式启动
+pub struct StorageRecovery {
+    pub file_path: PathBuf,
+    pub error: String,
+}
+
+/// 数据存储状态，ok 为 false 时前端进入恢复流程
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageStatus {
+    pub ok: bool,
+    pub error: Option<String>,
+    pub file_path: String,
+}
+
+/// 备份目录（数据文件所在目录下的 backups）
+pub fn backup_dir(file_path: &Path) -> PathBuf {
+    file_path.with_file_name("backups")
+}
+
+/// 列出备份文件，最新的在前
+pub fn list_backups(file_path: &Path) -> Vec<BackupInfo> {
+    let Ok(entries) = fs::read_dir(backup_dir(file_path)) else {
+        return Vec::new();
+    };
+
+    let mut backups: Vec<(SystemTime, BackupInfo)> = entries
+        .flatten()
+        .filter(|entry| is_backup_name(&entry.file_name().to_string_lossy()))
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let modified = metadata.modified().ok()?;
+            Some((
+                modified,
+                BackupInfo {
+                    name: entry.file_name().to_string_lossy().to_string(),
+                    path: entry.path().to_string_lossy().to_string(),
+                    size: metadata.len(),
+                    created_at: chrono::DateTime::<chrono::Utc>::from(modified).to_rfc3339(),
+                },
+            ))
+        })
+        .collect();
+    backups.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    backups.into_iter().map(|(_, info)| info).collect()
+}
+
+/// 创建备份并删除超出数量的旧备份
+///
+/// force 为 false 时，距上次备份不足 BACKUP_INTERVAL 则跳过。
+pub fn rotate(storage: &Storage, force: bool) -> Result<Option<BackupInfo>, String> {
+    let file_path = storage.get_file_path();
+    let existing = list_backups(file_path);
+    let recent = existing.first().and_then(|latest| {
+        let created = chrono::DateTime::parse_from_rfc3339(&latest.created_at).ok()?;
+        let age = chrono::Utc::now().signed_duration_since(created).to_std().ok()?;
+        Some(age < BACKUP_INTERVAL)
+    });
+    if !force && recent == Some(true) {
+        return Ok(None);
+    }
+
+    let dir = backup_dir(file_path);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create backup directory: {}", e))?;
+    let name = format!(
+        "{}{}.{}",
+        BACKUP_PREFIX,
+        chrono::Local::now().format("%Y%m%d-%H%M%S"),
+        BACKUP_EXTENSION
+    );
+    let path = dir.join(&name);
+
+    // 先写入临时文件，完整写入后再改名，避免留下不完整的备份
+    let temp_path = temp_path(&path);
+    let _ = fs::remove_file(&temp_path);
+    storage.backup_to(&temp_path)?;
+    sync_file(&temp_path)?;
+    fs::rename(&temp_path, &path).map_err(|e| format!("Failed to save backup: {}", e))?;
+
+    for old in list_backups(file_path).iter().skip(MAX_BACKUPS) {
+        let _ = fs::remove_file(&old.path);
+    }
+
+    Ok(list_backups(file_path).into_iter().find(|b| b.name == name))
+}
+
+/// 检查数据库文件是否完整
+pub fn check_integrity(path: &Path) -> Result<(), String> {
+    // 以读写方式打开（不创建），崩溃后残留的 WAL 需要写权限才能恢复
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_WRITE)
+        .map_err(|e| e.to_string())?;
+    let result: String = conn
+        .query_row("PRAGMA quick_check", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    match result.as_str() {
+        "ok" => Ok(()),
+        _ => Err(result),
+    }
+}
+
+/// 用备份替换数据文件，原文件改名保留为 .corrupt-<时间>
+///
+/// 只能在数据库未打开（恢复模式）时调用。
+pub fn restore(file_path: &Path, backup_name: &str) -> Result<(), String> {
+    if !is_backup_name(backup_name) {
+        return Err(format!("Invalid backup name: {}", backup_name));
+    }
+    let backup_path = backup_dir(file_path).join(backup_name);
+    check_integrity(&backup_path).map_err(|e| format!("Backup is damaged: {}", e))?;
+
+    let temp_path = temp_path(file_path);
+    fs::copy(&backup_path, &temp_path).map_err(|e| format!("Failed to copy backup: {}", e))?;
+    sync_file(&temp_path)?;
+
+    if file_path.exists() {
+        let mut corrupt = file_path.as_os_str().to_owned();
+        corrupt.push(format!(".corrupt-{}", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+        fs::rename(file_path, corrupt).map_err(|e| e.to_string())?;
+    }
+    // 旧的 WAL 文件属于损坏的数据库，不能应用到备份上
+    for suffix in ["-wal", "-shm"] {
+        let mut side = file_path.as_os_str().to_owned();
+        side.push(suffix);
+        let _ = fs::remove_file(PathBuf::from(side));
+    }
+
+    fs::rename(&temp_path, file_path).map_err(|e| format!("Failed to restore backup: {}", e))
+}
+
+/// 原子写入文件：先写临时文件并同步到磁盘，再替换目标文件
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), String> {
+    let temp_path = temp_path(path);
+    let mut file = File::create(&temp_path).map_err(|e| e.to_string())?;
+    file.write_all(contents).map_err(|e| e.to_string())?;
+    file.sync_all().map_err(|e| e.to_string())?;
+    drop(file);
+    fs::rename(&temp_path, path).map_err(|e| e.to_string())?;
+    sync_dir(path);
+    Ok(())
+}
+
+fn is_backup_name(name: &str) -> bool {
+    name.starts_with(BACKUP_PREFIX)
+        && Path::new(name).extension().is_some_and(|e| e == BACKUP_EXTENSION)
+        && !name.contains(['/', '\\'])
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    PathBuf::from(temp)
+}
+
+fn sync_file(path: &Path) -> Result<(), String> {
+    // Windows 上同步文件需要写权限
+    OpenOptions::new()
+        .write(true)
+        .open(path)
+        .and_then(|file| file.sync_all())
+        .map_err(|e| e.to_string())
+}
+
+/// 同步目录，确保改名操作已落盘（仅 Unix 支持）
+fn sync_dir(path: &Path) {
+    #[cfg(unix)]
+    if let Some(dir) = path.parent() {
+        let _ = File::open(dir).and_then(|dir| dir.sync_all());
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
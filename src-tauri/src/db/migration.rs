@@ -0,0 +1,404 @@
+use rusqlite::{params, Connection, Transaction};
+use std::fs;
+use std::path::Path;
+
+use super::models::{
+    CommandTemplate, EnvVariable, MessageHistory, MqttServer, Script, Subscription,
+};
+use super::to_json;
+
+/// 当前数据库结构版本（记录在 PRAGMA user_version 中）
+const SCHEMA_VERSION: i32 = 7;
+
+/// 旧版 data.yaml 的数据结构，仅用于一次性导入
+#[derive(Debug, serde::Deserialize, Default)]
+struct LegacyData {
+    #[serde(default)]
+    servers: Vec<MqttServer>,
+    #[serde(default)]
+    subscriptions: Vec<Subscription>,
+    #[serde(default)]
+    messages: Vec<MessageHistory>,
+    #[serde(default)]
+    templates: Vec<CommandTemplate>,
+    #[serde(default)]
+    scripts: Vec<Script>,
+    #[serde(default)]
+    env_variables: Vec<EnvVariable>,
+}
+
+/// 创建或升级数据库结构
+pub fn migrate(conn: &mut Connection) -> Result<(), String> {
+    let version: i32 = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+
+    if version > SCHEMA_VERSION {
+        return Err(format!(
+            "Database schema version {} is newer than supported version {}",
+            version, SCHEMA_VERSION
+        ));
+    }
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    if version < 1 {
+        tx.execute_batch(SCHEMA_V1).map_err(|e| e.to_string())?;
+    }
+    if version < 2 {
+        tx.execute_batch(SCHEMA_V2).map_err(|e| e.to_string())?;
+    }
+    if version < 3 {
+        tx.execute_batch(SCHEMA_V3).map_err(|e| e.to_string())?;
+    }
+    if version < 4 {
+        tx.execute_batch(SCHEMA_V4).map_err(|e| e.to_string())?;
+    }
+    if version < 5 {
+        tx.execute_batch(SCHEMA_V5).map_err(|e| e.to_string())?;
+    }
+    if version < 6 {
+        tx.execute_batch(SCHEMA_V6).map_err(|e| e.to_string())?;
+    }
+    if version < 7 {
+        tx.execute_batch(SCHEMA_V7).map_err(|e| e.to_string())?;
+    }
+    tx.pragma_update(None, "user_version", SCHEMA_VERSION)
+        .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())
+}
+
+const SCHEMA_V1: &str = "
+CREATE TABLE IF NOT EXISTS mqtt_servers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    host TEXT NOT NULL,
+    port INTEGER NOT NULL DEFAULT 1883,
+    protocol_version TEXT NOT NULL DEFAULT '3.1.1',
+    username TEXT,
+    password TEXT,
+    client_id TEXT,
+    keep_alive INTEGER NOT NULL DEFAULT 60,
+    clean_session INTEGER NOT NULL DEFAULT 1,
+    use_tls INTEGER NOT NULL DEFAULT 0,
+    ca_cert TEXT,
+    client_cert TEXT,
+    client_key TEXT,
+    client_key_password TEXT,
+    reconnect TEXT,
+    save_received_messages INTEGER NOT NULL DEFAULT 1,
+    created_at TEXT,
+    updated_at TEXT
+);
+
+CREATE TABLE IF NOT EXISTS subscriptions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    server_id INTEGER NOT NULL REFERENCES mqtt_servers(id) ON DELETE CASCADE,
+    topic TEXT NOT NULL,
+    qos INTEGER NOT NULL DEFAULT 0,
+    is_active INTEGER NOT NULL DEFAULT 1,
+    color TEXT,
+    last_grant TEXT,
+    created_at TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_subscriptions_server ON subscriptions(server_id);
+CREATE INDEX IF NOT EXISTS idx_subscriptions_topic ON subscriptions(server_id, topic);
+
+CREATE TABLE IF NOT EXISTS message_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    server_id INTEGER NOT NULL REFERENCES mqtt_servers(id) ON DELETE CASCADE,
+    direction TEXT NOT NULL,
+    topic TEXT NOT NULL,
+    payload TEXT,
+    payload_format TEXT,
+    qos INTEGER NOT NULL DEFAULT 0,
+    retain INTEGER NOT NULL DEFAULT 0,
+    properties TEXT,
+    delivery_state TEXT,
+    latency_ms INTEGER,
+    delivery_error TEXT,
+    created_at TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_messages_server ON message_history(server_id, id);
+CREATE INDEX IF NOT EXISTS idx_messages_topic ON message_history(topic);
+CREATE INDEX IF NOT EXISTS idx_messages_created_at ON message_history(server_id, created_at);
+
+CREATE TABLE IF NOT EXISTS command_templates (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    server_id INTEGER NOT NULL REFERENCES mqtt_servers(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    topic TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    payload_type TEXT NOT NULL,
+    qos INTEGER NOT NULL DEFAULT 0,
+    retain INTEGER NOT NULL DEFAULT 0,
+    description TEXT,
+    category TEXT,
+    use_count INTEGER NOT NULL DEFAULT 0,
+    last_used_at TEXT,
+    created_at TEXT,
+    updated_at TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_templates_server ON command_templates(server_id);
+
+CREATE TABLE IF NOT EXISTS scripts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    server_id INTEGER NOT NULL REFERENCES mqtt_servers(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    script_type TEXT NOT NULL,
+    code TEXT NOT NULL,
+    enabled INTEGER NOT NULL DEFAULT 0,
+    description TEXT,
+    created_at TEXT,
+    updated_at TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_scripts_server ON scripts(server_id, script_type);
+
+CREATE TABLE IF NOT EXISTS env_variables (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    server_id INTEGER NOT NULL REFERENCES mqtt_servers(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    value TEXT NOT NULL,
+    description TEXT,
+    created_at TEXT,
+    updated_at TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_env_variables_server ON env_variables(server_id, name);
+";
+
+/// 每个 Server 的消息保留策略
+const SCHEMA_V2: &str = "
+ALTER TABLE mqtt_servers ADD COLUMN retention TEXT;
+";
+
+/// 历史消息保存原始字节，已有记录按显示格式还原
+const SCHEMA_V3: &str = "
+ALTER TABLE message_history ADD COLUMN raw_payload BLOB;
+UPDATE message_history SET raw_payload = CASE
+    WHEN payload_format = 'hex' THEN COALESCE(unhex(replace(payload, ' ', '')), CAST(payload AS BLOB))
+    ELSE CAST(payload AS BLOB)
+END
+WHERE payload IS NOT NULL;
+";
+
+/// 后台定时发布任务
+const SCHEMA_V4: &str = "
+CREATE TABLE IF NOT EXISTS scheduled_jobs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    server_id INTEGER NOT NULL REFERENCES mqtt_servers(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    template_ids TEXT,
+    message TEXT,
+    trigger TEXT NOT NULL,
+    max_runs INTEGER,
+    start_at TEXT,
+    end_at TEXT,
+    enabled INTEGER NOT NULL DEFAULT 0,
+    run_count INTEGER NOT NULL DEFAULT 0,
+    last_run_at TEXT,
+    created_at TEXT,
+    updated_at TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_scheduled_jobs_server ON scheduled_jobs(server_id);
+";
+
+/// 消息过滤和自动回复脚本的主题过滤器
+const SCHEMA_V5: &str = "
+ALTER TABLE scripts ADD COLUMN topic_filter TEXT;
+";
+
+/// 环境变量支持全局作用域（server_id 为空），SQLite 无法修改约束，需重建表
+const SCHEMA_V6: &str = "
+CREATE TABLE env_variables_v6 (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    server_id INTEGER REFERENCES mqtt_servers(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    value TEXT NOT NULL,
+    description TEXT,
+    created_at TEXT,
+    updated_at TEXT
+);
+INSERT INTO env_variables_v6 (id, server_id, name, value, description, created_at, updated_at)
+    SELECT id, server_id, name, value, description, created_at, updated_at FROM env_variables;
+DROP TABLE env_variables;
+ALTER TABLE env_variables_v6 RENAME TO env_variables;
+CREATE INDEX IF NOT EXISTS idx_env_variables_server ON env_variables(server_id, name);
+";
+
+/// 敏感字段加密：保险库配置和敏感环境变量标记
+const SCHEMA_V7: &str = "
+ALTER TABLE env_variables ADD COLUMN secret INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS vault (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    salt TEXT NOT NULL,
+    kdf_params TEXT NOT NULL,
+    verifier TEXT NOT NULL,
+    created_at TEXT
+);
+";
+
+/// 将旧版 data.yaml 中的数据导入到新建的数据库
+///
+/// 导入成功后原文件重命名为 data.yaml.migrated，避免重复导入。
+pub fn import_yaml(conn: &mut Connection, yaml_path: &Path) -> Result<(), String> {
+    let content = fs::read_to_string(yaml_path).map_err(|e| e.to_string())?;
+    let data: LegacyData = serde_yaml::from_str(&content)
+        .map_err(|e| format!("Failed to parse {}: {}", yaml_path.display(), e))?;
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    insert_legacy(&tx, data).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    let mut migrated = yaml_path.as_os_str().to_owned();
+    migrated.push(".migrated");
+    fs::rename(yaml_path, migrated).map_err(|e| e.to_string())
+}
+
+fn insert_legacy(tx: &Transaction, data: LegacyData) -> rusqlite::Result<()> {
+    let server_ids: Vec<i64> = data.servers.iter().filter_map(|s| s.id).collect();
+    // 丢弃所属服务器已不存在的记录
+    let owned = |server_id: i64| server_ids.contains(&server_id);
+
+    for s in data.servers {
+        tx.execute(
+            "INSERT INTO mqtt_servers (id, name, host, port, protocol_version, username, password,
+                client_id, keep_alive, clean_session, use_tls, ca_cert, client_cert, client_key,
+                client_key_password, reconnect, save_received_messages, retention, created_at,
+                updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)",
+            params![
+                s.id,
+                s.name,
+                s.host,
+                s.port,
+                s.protocol_version,
+                s.username,
+                s.password,
+                s.client_id,
+                s.keep_alive,
+                s.clean_session,
+                s.use_tls,
+                s.ca_cert,
+                s.client_cert,
+                s.client_key,
+                s.client_key_password,
+                to_json(&s.reconnect),
+                s.save_received_messages,
+                to_json(&s.retention),
+                s.created_at,
+                s.updated_at,
+            ],
+        )?;
+    }
+
+    for s in data
+        .subscriptions
+        .into_iter()
+        .filter(|s| owned(s.server_id))
+    {
+        tx.execute(
+            "INSERT INTO subscriptions (id, server_id, topic, qos, is_active, color, last_grant, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                s.id,
+                s.server_id,
+                s.topic,
+                s.qos,
+                s.is_active,
+                s.color,
+                s.last_grant.as_ref().map(to_json),
+                s.created_at,
+            ],
+        )?;
+    }
+
+    for m in data.messages.into_iter().filter(|m| owned(m.server_id)) {
+        tx.execute(
+            "INSERT INTO message_history (id, server_id, direction, topic, payload, payload_format,
+                raw_payload, qos, retain, properties, delivery_state, latency_ms, delivery_error,
+                created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            params![
+                m.id,
+                m.server_id,
+                m.direction,
+                m.topic,
+                m.payload,
+                m.payload_format,
+                m.payload_bytes(),
+                m.qos,
+                m.retain,
+                m.properties.as_ref().map(to_json),
+                m.delivery_state,
+                m.latency_ms.map(|v| v as i64),
+                m.delivery_error,
+                m.created_at,
+            ],
+        )?;
+    }
+
+    for t in data.templates.into_iter().filter(|t| owned(t.server_id)) {
+        tx.execute(
+            "INSERT INTO command_templates (id, server_id, name, topic, payload, payload_type, qos,
+                retain, description, category, use_count, last_used_at, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            params![
+                t.id,
+                t.server_id,
+                t.name,
+                t.topic,
+                t.payload,
+                t.payload_type,
+                t.qos,
+                t.retain,
+                t.description,
+                t.category,
+                t.use_count,
+                t.last_used_at,
+                t.created_at,
+                t.updated_at,
+            ],
+        )?;
+    }
+
+    for s in data.scripts.into_iter().filter(|s| owned(s.server_id)) {
+        tx.execute(
+            "INSERT INTO scripts (id, server_id, name, script_type, code, enabled, description,
+                created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                s.id,
+                s.server_id,
+                s.name,
+                s.script_type,
+                s.code,
+                s.enabled,
+                s.description,
+                s.created_at,
+                s.updated_at,
+            ],
+        )?;
+    }
+
+    for e in data
+        .env_variables
+        .into_iter()
+        .filter(|e| e.server_id.is_none_or(owned))
+    {
+        tx.execute(
+            "INSERT INTO env_variables (id, server_id, name, value, description, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                e.id,
+                e.server_id,
+                e.name,
+                e.value,
+                e.description,
+                e.created_at,
+                e.updated_at,
+            ],
+        )?;
+    }
+
+    Ok(())
+}
@@ -1,36 +1,26 @@
+pub mod backup;
+pub mod migration;
 pub mod models;
+pub mod vault;
 
-use models::{CommandTemplate, CreateTemplateRequest, CreateScriptRequest, MessageHistory, MqttServer, Script, Subscription, UpdateSubscriptionRequest, UpdateTemplateRequest, UpdateScriptRequest, EnvVariable, CreateEnvVariableRequest, UpdateEnvVariableRequest};
-use parking_lot::RwLock;
+use crate::mqtt::topic;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use models::{
+    CommandTemplate, CreateEnvVariableRequest, CreateScriptRequest, CreateTemplateRequest,
+    EnvVariable, MessageHistory, MessagePage, MessageQuery, MqttServer, RetentionPolicy,
+    ScheduledJob, Script, Subscription, SubscriptionGrant, UpdateEnvVariableRequest,
+    UpdateScriptRequest,
+    UpdateSubscriptionRequest, UpdateTemplateRequest,
+};
+use parking_lot::Mutex;
+use regex::Regex;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row, ToSql};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tauri::AppHandle;
 use tauri::Manager;
-
-#[derive(Debug, serde::Serialize, serde::Deserialize, Default)]
-pub struct AppData {
-    pub servers: Vec<MqttServer>,
-    pub subscriptions: Vec<Subscription>,
-    pub messages: Vec<MessageHistory>,
-    #[serde(default)]
-    pub templates: Vec<CommandTemplate>,
-    #[serde(default)]
-    pub scripts: Vec<Script>,
-    #[serde(default)]
-    pub env_variables: Vec<EnvVariable>,
-    #[serde(default)]
-    next_server_id: i64,
-    #[serde(default)]
-    next_subscription_id: i64,
-    #[serde(default)]
-    next_message_id: i64,
-    #[serde(default)]
-    next_template_id: i64,
-    #[serde(default)]
-    next_script_id: i64,
-    #[serde(default)]
-    next_env_variable_id: i64,
-}
+use vault::{is_sealed, seal_with, Vault, VaultConfig, VaultStatus};
 
 /// 应用配置（用于存储自定义数据路径等）
 #[derive(Debug, serde::Serialize, serde::Deserialize, Default)]
@@ -39,12 +29,16 @@ pub struct AppConfig {
 }
 
 pub struct Storage {
-    data: RwLock<AppData>,
+    conn: Mutex<Connection>,
     file_path: PathBuf,
+    vault: Vault,
 }
 
 impl Storage {
-    pub fn new(app_handle: &AppHandle) -> Result<Self, String> {
+    /// 解析数据库和旧版 data.yaml 的路径
+    ///
+    /// config.yaml 存在但无法解析时返回错误，避免改用默认路径打开一个空数据库。
+    pub fn data_paths(app_handle: &AppHandle) -> Result<(PathBuf, PathBuf), String> {
         let app_dir = app_handle
             .path()
             .app_data_dir()
@@ -54,485 +48,1751 @@ impl Storage {
 
         // 检查是否有自定义配置
         let config_path = app_dir.join("config.yaml");
-        let file_path = if config_path.exists() {
-            if let Ok(content) = fs::read_to_string(&config_path) {
-                if let Ok(config) = serde_yaml::from_str::<AppConfig>(&content) {
-                    if let Some(custom_path) = config.data_path {
-                        let custom_path = PathBuf::from(custom_path);
-                        if custom_path.exists() || custom_path.parent().map(|p| p.exists()).unwrap_or(false) {
-                            custom_path
-                        } else {
-                            app_dir.join("data.yaml")
-                        }
-                    } else {
-                        app_dir.join("data.yaml")
-                    }
-                } else {
-                    app_dir.join("data.yaml")
-                }
-            } else {
-                app_dir.join("data.yaml")
-            }
-        } else {
-            app_dir.join("data.yaml")
+        let config = match fs::read_to_string(&config_path) {
+            Ok(content) => serde_yaml::from_str::<AppConfig>(&content)
+                .map_err(|e| format!("Failed to parse {}: {}", config_path.display(), e))?,
+            Err(_) => AppConfig::default(),
         };
+        let custom_path = config
+            .data_path
+            .map(PathBuf::from)
+            .filter(|p| p.exists() || p.parent().map(|p| p.exists()).unwrap_or(false));
 
-        let data = if file_path.exists() {
-            let content = fs::read_to_string(&file_path).map_err(|e| e.to_string())?;
-            serde_yaml::from_str(&content).unwrap_or_default()
-        } else {
-            AppData::default()
-        };
+        // 旧版本的自定义路径指向 data.yaml，数据库放在同一目录下
+        Ok(match custom_path {
+            Some(path) if path.extension().map(|e| e == "yaml").unwrap_or(false) => {
+                (path.with_file_name("data.db"), path)
+            }
+            Some(path) => (path.clone(), path.with_file_name("data.yaml")),
+            None => (app_dir.join("data.db"), app_dir.join("data.yaml")),
+        })
+    }
+
+    /// 打开数据库；新建数据库时从旧版 data.yaml 导入数据
+    ///
+    /// 已有的数据库文件损坏时返回错误，不会新建空数据库覆盖。
+    pub fn open(file_path: PathBuf, legacy_path: Option<&Path>) -> Result<Self, String> {
+        let is_new = !file_path.exists();
+        if !is_new {
+            backup::check_integrity(&file_path)
+                .map_err(|e| format!("Data file {} is damaged: {}", file_path.display(), e))?;
+        }
+
+        let result = Self::open_connection(&file_path, legacy_path.filter(|_| is_new));
+        if result.is_err() && is_new {
+            // 导入失败时删除新建的数据库，下次启动重新导入
+            for suffix in ["", "-wal", "-shm"] {
+                let mut path = file_path.as_os_str().to_owned();
+                path.push(suffix);
+                let _ = fs::remove_file(PathBuf::from(path));
+            }
+        }
 
         Ok(Self {
-            data: RwLock::new(data),
+            conn: Mutex::new(result?),
             file_path,
+            vault: Vault::default(),
         })
     }
-    
+
+    /// 内存数据库，仅用于测试
+    #[cfg(test)]
+    pub(crate) fn in_memory() -> Self {
+        Self {
+            conn: Mutex::new(Self::open_connection(Path::new(":memory:"), None).unwrap()),
+            file_path: PathBuf::new(),
+            vault: Vault::default(),
+        }
+    }
+
+    fn open_connection(file_path: &Path, legacy_path: Option<&Path>) -> Result<Connection, String> {
+        let mut conn = Connection::open(file_path).map_err(|e| e.to_string())?;
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             PRAGMA synchronous = NORMAL;
+             PRAGMA foreign_keys = ON;",
+        )
+        .map_err(|e| e.to_string())?;
+
+        migration::migrate(&mut conn)?;
+
+        if let Some(legacy_path) = legacy_path.filter(|p| p.exists()) {
+            migration::import_yaml(&mut conn, legacy_path)?;
+        }
+        Ok(conn)
+    }
+
     /// 获取当前数据文件路径
     pub fn get_file_path(&self) -> &PathBuf {
         &self.file_path
     }
 
-    fn save(&self) -> Result<(), String> {
-        let data = self.data.read();
-        let content = serde_yaml::to_string(&*data).map_err(|e| e.to_string())?;
-        fs::write(&self.file_path, content).map_err(|e| e.to_string())
+    /// 将数据库完整复制到指定文件
+    pub fn backup_to(&self, path: &Path) -> Result<(), String> {
+        let conn = self.conn.lock();
+        conn.execute("VACUUM INTO ?1", [path.to_string_lossy()])
+            .map_err(|e| e.to_string())?;
+        Ok(())
     }
 
     // ===== Server 操作 =====
     pub fn get_servers(&self) -> Vec<MqttServer> {
-        let data = self.data.read();
-        data.servers.clone()
+        let conn = self.conn.lock();
+        query_all(
+            &conn,
+            &format!("SELECT {} FROM mqtt_servers ORDER BY id", SERVER_COLUMNS),
+            [],
+            server_from_row,
+        )
+        .into_iter()
+        .map(|server| self.reveal_server(server))
+        .collect()
     }
 
     pub fn get_server(&self, id: i64) -> Option<MqttServer> {
-        let data = self.data.read();
-        data.servers.iter().find(|s| s.id == Some(id)).cloned()
+        let conn = self.conn.lock();
+        query_one(
+            &conn,
+            &format!("SELECT {} FROM mqtt_servers WHERE id = ?1", SERVER_COLUMNS),
+            [id],
+            server_from_row,
+        )
+        .map(|server| self.reveal_server(server))
     }
 
-    pub fn create_server(&self, mut server: MqttServer) -> Result<i64, String> {
-        let mut data = self.data.write();
-        data.next_server_id += 1;
-        let id = data.next_server_id;
-        server.id = Some(id);
-        server.created_at = Some(chrono::Utc::now().to_rfc3339());
-        server.updated_at = server.created_at.clone();
-        data.servers.push(server);
-        drop(data);
-        self.save()?;
-        Ok(id)
+    /// 获取用于连接的 Server 配置，凭据已加密且保险库锁定时返回错误
+    pub fn get_connect_server(&self, id: i64) -> Result<MqttServer, String> {
+        let conn = self.conn.lock();
+        let server = query_one(
+            &conn,
+            &format!("SELECT {} FROM mqtt_servers WHERE id = ?1", SERVER_COLUMNS),
+            [id],
+            server_from_row,
+        )
+        .ok_or("Server not found")?;
+
+        let sealed = [&server.password, &server.client_key_password]
+            .into_iter()
+            .flatten()
+            .any(|value| is_sealed(value));
+        if sealed && !self.vault.is_unlocked() {
+            return Err("Vault is locked, unlock it to use the saved credentials".to_string());
+        }
+        Ok(self.reveal_server(server))
+    }
+
+    pub fn create_server(&self, server: MqttServer) -> Result<i64, String> {
+        let conn = self.conn.lock();
+        let password = self.seal_value(&conn, server.password)?;
+        let client_key_password = self.seal_value(&conn, server.client_key_password)?;
+        let now = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO mqtt_servers (name, host, port, protocol_version, username, password,
+                client_id, keep_alive, clean_session, use_tls, ca_cert, client_cert, client_key,
+                client_key_password, reconnect, save_received_messages, retention, created_at,
+                updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?18)",
+            params![
+                server.name,
+                server.host,
+                server.port,
+                server.protocol_version,
+                server.username,
+                password,
+                server.client_id,
+                server.keep_alive,
+                server.clean_session,
+                server.use_tls,
+                server.ca_cert,
+                server.client_cert,
+                server.client_key,
+                client_key_password,
+                to_json(&server.reconnect),
+                server.save_received_messages,
+                to_json(&server.retention),
+                now,
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(conn.last_insert_rowid())
     }
 
     pub fn update_server(&self, server: MqttServer) -> Result<(), String> {
-        let mut data = self.data.write();
-        if let Some(existing) = data.servers.iter_mut().find(|s| s.id == server.id) {
-            *existing = server;
-            existing.updated_at = Some(chrono::Utc::now().to_rfc3339());
-        }
-        drop(data);
-        self.save()
+        let conn = self.conn.lock();
+
+        // 保险库锁定时保留已保存的凭据，只有写入新的凭据时才需要解锁
+        let (password, client_key_password) =
+            if vault_config(&conn).is_some() && !self.vault.is_unlocked() {
+                let (stored_password, stored_key_password) = conn
+                    .query_row(
+                        "SELECT password, client_key_password FROM mqtt_servers WHERE id = ?1",
+                        [server.id],
+                        |row| Ok((row.get(0)?, row.get(1)?)),
+                    )
+                    .map_err(|e| e.to_string())?;
+                (
+                    keep_stored_secret(stored_password, server.password)?,
+                    keep_stored_secret(stored_key_password, server.client_key_password)?,
+                )
+            } else {
+                (
+                    self.seal_value(&conn, server.password)?,
+                    self.seal_value(&conn, server.client_key_password)?,
+                )
+            };
+
+        conn.execute(
+            "UPDATE mqtt_servers SET name = ?1, host = ?2, port = ?3, protocol_version = ?4,
+                username = ?5, password = ?6,
+                client_id = ?7, keep_alive = ?8, clean_session = ?9, use_tls = ?10,
+                ca_cert = ?11, client_cert = ?12, client_key = ?13,
+                client_key_password = ?14,
+                reconnect = ?15, save_received_messages = ?16, retention = ?17, updated_at = ?18
+             WHERE id = ?19",
+            params![
+                server.name,
+                server.host,
+                server.port,
+                server.protocol_version,
+                server.username,
+                password,
+                server.client_id,
+                server.keep_alive,
+                server.clean_session,
+                server.use_tls,
+                server.ca_cert,
+                server.client_cert,
+                server.client_key,
+                client_key_password,
+                to_json(&server.reconnect),
+                server.save_received_messages,
+                to_json(&server.retention),
+                chrono::Utc::now().to_rfc3339(),
+                server.id,
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
     }
 
     pub fn delete_server(&self, id: i64) -> Result<(), String> {
-        let mut data = self.data.write();
-        data.servers.retain(|s| s.id != Some(id));
-        // 同时删除相关订阅、消息、模板、脚本和环境变量
-        data.subscriptions.retain(|s| s.server_id != id);
-        data.messages.retain(|m| m.server_id != id);
-        data.templates.retain(|t| t.server_id != id);
-        data.scripts.retain(|s| s.server_id != id);
-        data.env_variables.retain(|e| e.server_id != id);
-        drop(data);
-        self.save()
+        let conn = self.conn.lock();
+        // 相关订阅、消息、模板、脚本和环境变量通过外键级联删除
+        conn.execute("DELETE FROM mqtt_servers WHERE id = ?1", [id])
+            .map_err(|e| e.to_string())?;
+        Ok(())
     }
 
     // ===== 订阅操作 =====
     pub fn get_subscriptions(&self, server_id: i64) -> Vec<Subscription> {
-        let data = self.data.read();
-        data.subscriptions
-            .iter()
-            .filter(|s| s.server_id == server_id)
-            .cloned()
-            .collect()
+        let conn = self.conn.lock();
+        query_all(
+            &conn,
+            &format!(
+                "SELECT {} FROM subscriptions WHERE server_id = ?1 ORDER BY id",
+                SUBSCRIPTION_COLUMNS
+            ),
+            [server_id],
+            subscription_from_row,
+        )
+    }
+
+    pub fn get_subscription(&self, id: i64) -> Option<Subscription> {
+        let conn = self.conn.lock();
+        query_one(
+            &conn,
+            &format!(
+                "SELECT {} FROM subscriptions WHERE id = ?1",
+                SUBSCRIPTION_COLUMNS
+            ),
+            [id],
+            subscription_from_row,
+        )
+    }
+
+    /// 记录订阅的授权结果（同一服务器下相同主题的订阅一并更新）
+    pub fn update_subscription_grants(
+        &self,
+        server_id: i64,
+        grants: Vec<(String, SubscriptionGrant)>,
+    ) -> Result<(), String> {
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        for (topic, grant) in grants {
+            tx.execute(
+                "UPDATE subscriptions SET last_grant = ?1 WHERE server_id = ?2 AND topic = ?3",
+                params![to_json(&grant), server_id, topic],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())
     }
 
     pub fn create_subscription(&self, mut sub: Subscription) -> Result<Subscription, String> {
-        let mut data = self.data.write();
-        data.next_subscription_id += 1;
-        sub.id = Some(data.next_subscription_id);
+        let conn = self.conn.lock();
         sub.created_at = Some(chrono::Utc::now().to_rfc3339());
-        let result = sub.clone();
-        data.subscriptions.push(sub);
-        drop(data);
-        self.save()?;
-        Ok(result)
+        conn.execute(
+            "INSERT INTO subscriptions (server_id, topic, qos, is_active, color, last_grant, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                sub.server_id,
+                sub.topic,
+                sub.qos,
+                sub.is_active,
+                sub.color,
+                sub.last_grant.as_ref().map(to_json),
+                sub.created_at,
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        sub.id = Some(conn.last_insert_rowid());
+        Ok(sub)
     }
 
     pub fn update_subscription_status(&self, id: i64, is_active: bool) -> Result<(), String> {
-        let mut data = self.data.write();
-        if let Some(sub) = data.subscriptions.iter_mut().find(|s| s.id == Some(id)) {
-            sub.is_active = is_active;
-        }
-        drop(data);
-        self.save()
+        let conn = self.conn.lock();
+        conn.execute(
+            "UPDATE subscriptions SET is_active = ?1 WHERE id = ?2",
+            params![is_active, id],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
     }
 
-    pub fn update_subscription(&self, req: UpdateSubscriptionRequest) -> Result<Subscription, String> {
-        let mut data = self.data.write();
-        if let Some(sub) = data.subscriptions.iter_mut().find(|s| s.id == Some(req.id)) {
-            if let Some(topic) = req.topic {
-                sub.topic = topic;
-            }
-            if let Some(qos) = req.qos {
-                sub.qos = qos;
-            }
-            // color 可以设置为 None（清除颜色）
-            sub.color = req.color;
-            let result = sub.clone();
-            drop(data);
-            self.save()?;
-            Ok(result)
-        } else {
-            Err("Subscription not found".to_string())
+    pub fn update_subscription(
+        &self,
+        req: UpdateSubscriptionRequest,
+    ) -> Result<Subscription, String> {
+        let mut sub = self
+            .get_subscription(req.id)
+            .ok_or("Subscription not found")?;
+        if let Some(topic) = req.topic {
+            sub.topic = topic;
         }
+        if let Some(qos) = req.qos {
+            sub.qos = qos;
+        }
+        // color 可以设置为 None（清除颜色）
+        sub.color = req.color;
+
+        let conn = self.conn.lock();
+        conn.execute(
+            "UPDATE subscriptions SET topic = ?1, qos = ?2, color = ?3 WHERE id = ?4",
+            params![sub.topic, sub.qos, sub.color, req.id],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(sub)
     }
 
     pub fn delete_subscription(&self, id: i64) -> Result<(), String> {
-        let mut data = self.data.write();
-        data.subscriptions.retain(|s| s.id != Some(id));
-        drop(data);
-        self.save()
+        let conn = self.conn.lock();
+        conn.execute("DELETE FROM subscriptions WHERE id = ?1", [id])
+            .map_err(|e| e.to_string())?;
+        Ok(())
     }
 
     // ===== 消息操作 =====
     pub fn get_messages(&self, server_id: i64, limit: usize) -> Vec<MessageHistory> {
-        let data = self.data.read();
-        data.messages
-            .iter()
-            .filter(|m| m.server_id == server_id)
-            .rev()
-            .take(limit)
-            .cloned()
-            .collect()
+        let conn = self.conn.lock();
+        query_all(
+            &conn,
+            &format!(
+                "SELECT {} FROM message_history WHERE server_id = ?1 ORDER BY id DESC LIMIT ?2",
+                MESSAGE_COLUMNS
+            ),
+            params![server_id, limit as i64],
+            message_from_row,
+        )
     }
 
     pub fn create_message(&self, mut msg: MessageHistory) -> Result<MessageHistory, String> {
-        let mut data = self.data.write();
-        data.next_message_id += 1;
-        msg.id = Some(data.next_message_id);
+        let conn = self.conn.lock();
         msg.created_at = Some(chrono::Utc::now().to_rfc3339());
-        let result = msg.clone();
-        data.messages.push(msg);
-
-        // 限制消息数量，每个server最多保存1000条
-        let server_id = result.server_id;
-        let count = data
-            .messages
-            .iter()
-            .filter(|m| m.server_id == server_id)
-            .count();
-        if count > 1000 {
-            let to_remove = count - 1000;
-            let mut removed = 0;
-            data.messages.retain(|m| {
-                if m.server_id == server_id && removed < to_remove {
-                    removed += 1;
-                    false
-                } else {
-                    true
-                }
+        conn.execute(
+            "INSERT INTO message_history (server_id, direction, topic, payload, payload_format,
+                raw_payload, qos, retain, properties, delivery_state, latency_ms, delivery_error,
+                created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            params![
+                msg.server_id,
+                msg.direction,
+                msg.topic,
+                msg.payload,
+                msg.payload_format,
+                msg.raw_payload,
+                msg.qos,
+                msg.retain,
+                msg.properties.as_ref().map(to_json),
+                msg.delivery_state,
+                msg.latency_ms.map(|v| v as i64),
+                msg.delivery_error,
+                msg.created_at,
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        msg.id = Some(conn.last_insert_rowid());
+        Ok(msg)
+    }
+
+    /// 批量导入历史消息，保留原始时间，返回导入的条数
+    pub fn import_messages(&self, messages: &[MessageHistory]) -> Result<usize, String> {
+        let mut conn = self.conn.lock();
+        let now = chrono::Utc::now().to_rfc3339();
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        for msg in messages {
+            tx.execute(
+                "INSERT INTO message_history (server_id, direction, topic, payload, payload_format,
+                    raw_payload, qos, retain, properties, delivery_state, latency_ms, delivery_error,
+                    created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                params![
+                    msg.server_id,
+                    msg.direction,
+                    msg.topic,
+                    msg.payload,
+                    msg.payload_format,
+                    msg.payload_bytes(),
+                    msg.qos,
+                    msg.retain,
+                    msg.properties.as_ref().map(to_json),
+                    msg.delivery_state,
+                    msg.latency_ms.map(|v| v as i64),
+                    msg.delivery_error,
+                    msg.created_at.as_deref().unwrap_or(&now),
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(messages.len())
+    }
+
+    pub fn get_message(&self, id: i64) -> Option<MessageHistory> {
+        let conn = self.conn.lock();
+        query_one(
+            &conn,
+            &format!(
+                "SELECT {} FROM message_history WHERE id = ?1",
+                MESSAGE_COLUMNS
+            ),
+            [id],
+            message_from_row,
+        )
+    }
+
+    /// 更新发布消息的投递状态
+    pub fn update_message_delivery(
+        &self,
+        id: i64,
+        state: &str,
+        latency_ms: Option<u64>,
+        error: Option<String>,
+    ) -> Result<(), String> {
+        let conn = self.conn.lock();
+        let updated = conn
+            .execute(
+                "UPDATE message_history SET delivery_state = ?1, latency_ms = ?2, delivery_error = ?3
+                 WHERE id = ?4",
+                params![state, latency_ms.map(|v| v as i64), error, id],
+            )
+            .map_err(|e| e.to_string())?;
+        if updated == 0 {
+            return Err("Message not found".to_string());
+        }
+        Ok(())
+    }
+
+    /// 按条件分页查询历史消息
+    ///
+    /// 可以在 SQL 中表达的条件直接下推，主题通配符和正则在扫描时过滤。
+    pub fn search_messages(&self, query: &MessageQuery) -> Result<MessagePage, String> {
+        let limit = query.limit.unwrap_or(100).clamp(1, 1000);
+
+        let topic_filter = query.topic.as_deref().filter(|t| !t.is_empty());
+        if let Some(filter) = topic_filter {
+            topic::validate_filter(filter)?;
+        }
+        let payload_regex = query
+            .payload_regex
+            .as_deref()
+            .filter(|r| !r.is_empty())
+            .map(Regex::new)
+            .transpose()
+            .map_err(|e| format!("Invalid payload regex: {}", e))?;
+
+        let mut sql = format!(
+            "SELECT {} FROM message_history WHERE server_id = ?",
+            MESSAGE_COLUMNS
+        );
+        let mut args: Vec<Box<dyn ToSql>> = vec![Box::new(query.server_id)];
+
+        if let Some(cursor) = query.cursor {
+            sql.push_str(if query.ascending {
+                " AND id > ?"
+            } else {
+                " AND id < ?"
             });
+            args.push(Box::new(cursor));
+        }
+        if let Some(direction) = &query.direction {
+            sql.push_str(" AND direction = ?");
+            args.push(Box::new(direction.clone()));
+        }
+        if let Some(qos) = query.qos {
+            sql.push_str(" AND qos = ?");
+            args.push(Box::new(qos));
+        }
+        if let Some(retain) = query.retain {
+            sql.push_str(" AND retain = ?");
+            args.push(Box::new(retain));
+        }
+        if let Some(since) = &query.since {
+            sql.push_str(" AND created_at >= ?");
+            args.push(Box::new(normalize_time(since)?));
+        }
+        if let Some(until) = &query.until {
+            sql.push_str(" AND created_at < ?");
+            args.push(Box::new(normalize_time(until)?));
+        }
+        if let Some(text) = query.payload_contains.as_deref().filter(|t| !t.is_empty()) {
+            sql.push_str(" AND instr(payload, ?) > 0");
+            args.push(Box::new(text.to_string()));
+        }
+        if let Some(filter) = topic_filter {
+            // 用第一个通配符之前的部分缩小扫描范围
+            let prefix = filter.split(['+', '#']).next().unwrap_or_default();
+            if prefix.len() == filter.len() {
+                sql.push_str(" AND topic = ?");
+                args.push(Box::new(filter.to_string()));
+            } else if !prefix.is_empty() {
+                sql.push_str(" AND substr(topic, 1, ?) = ?");
+                args.push(Box::new(prefix.chars().count() as i64));
+                args.push(Box::new(prefix.to_string()));
+            }
+        }
+        sql.push_str(if query.ascending {
+            " ORDER BY id"
+        } else {
+            " ORDER BY id DESC"
+        });
+
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+        let mut rows = stmt
+            .query(params_from_iter(args.iter()))
+            .map_err(|e| e.to_string())?;
+
+        let mut messages: Vec<MessageHistory> = Vec::new();
+        let mut next_cursor = None;
+        while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+            let message = message_from_row(row).map_err(|e| e.to_string())?;
+            if let Some(filter) = topic_filter {
+                if !topic::matches_filter(filter, &message.topic) {
+                    continue;
+                }
+            }
+            if let Some(regex) = &payload_regex {
+                if !regex.is_match(message.payload.as_deref().unwrap_or_default()) {
+                    continue;
+                }
+            }
+            if messages.len() == limit {
+                next_cursor = messages.last().and_then(|m| m.id);
+                break;
+            }
+            messages.push(message);
         }
 
-        drop(data);
-        self.save()?;
-        Ok(result)
+        Ok(MessagePage {
+            messages,
+            next_cursor,
+        })
+    }
+
+    /// 按保留策略取出最旧的一批待清理消息
+    pub fn get_expired_messages(
+        &self,
+        server_id: i64,
+        policy: &RetentionPolicy,
+        limit: usize,
+    ) -> Result<Vec<MessageHistory>, String> {
+        let conn = self.conn.lock();
+        let Some((max_id, cutoff)) = expired_bounds(&conn, server_id, policy)? else {
+            return Ok(Vec::new());
+        };
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {} FROM message_history
+                 WHERE server_id = ?1 AND (id <= ?2 OR created_at < ?3)
+                 ORDER BY id LIMIT ?4",
+                MESSAGE_COLUMNS
+            ))
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(
+                params![server_id, max_id, cutoff, limit as i64],
+                message_from_row,
+            )
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+
+    /// 按保留策略直接删除超出限制的消息，返回删除的条数
+    pub fn prune_messages(
+        &self,
+        server_id: i64,
+        policy: &RetentionPolicy,
+    ) -> Result<usize, String> {
+        let conn = self.conn.lock();
+        let Some((max_id, cutoff)) = expired_bounds(&conn, server_id, policy)? else {
+            return Ok(0);
+        };
+        conn.execute(
+            "DELETE FROM message_history WHERE server_id = ?1 AND (id <= ?2 OR created_at < ?3)",
+            params![server_id, max_id, cutoff],
+        )
+        .map_err(|e| e.to_string())
+    }
+
+    pub fn delete_messages(&self, ids: &[i64]) -> Result<usize, String> {
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let mut deleted = 0;
+        for id in ids {
+            deleted += tx
+                .execute("DELETE FROM message_history WHERE id = ?1", [id])
+                .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(deleted)
     }
 
     pub fn clear_messages(&self, server_id: i64) -> Result<(), String> {
-        let mut data = self.data.write();
-        data.messages.retain(|m| m.server_id != server_id);
-        drop(data);
-        self.save()
+        let conn = self.conn.lock();
+        conn.execute(
+            "DELETE FROM message_history WHERE server_id = ?1",
+            [server_id],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
     }
 
     // ===== 模板操作 =====
     pub fn get_templates(&self, server_id: i64) -> Vec<CommandTemplate> {
-        let data = self.data.read();
-        data.templates
-            .iter()
-            .filter(|t| t.server_id == server_id)
-            .cloned()
-            .collect()
+        let conn = self.conn.lock();
+        query_all(
+            &conn,
+            &format!(
+                "SELECT {} FROM command_templates WHERE server_id = ?1 ORDER BY id",
+                TEMPLATE_COLUMNS
+            ),
+            [server_id],
+            template_from_row,
+        )
     }
 
     pub fn get_template(&self, id: i64) -> Option<CommandTemplate> {
-        let data = self.data.read();
-        data.templates.iter().find(|t| t.id == Some(id)).cloned()
+        let conn = self.conn.lock();
+        query_one(
+            &conn,
+            &format!(
+                "SELECT {} FROM command_templates WHERE id = ?1",
+                TEMPLATE_COLUMNS
+            ),
+            [id],
+            template_from_row,
+        )
     }
 
     pub fn create_template(&self, req: CreateTemplateRequest) -> Result<i64, String> {
-        let mut data = self.data.write();
-        data.next_template_id += 1;
-        let id = data.next_template_id;
+        let conn = self.conn.lock();
         let now = chrono::Utc::now().to_rfc3339();
-        
-        let template = CommandTemplate {
-            id: Some(id),
-            server_id: req.server_id,
-            name: req.name,
-            topic: req.topic,
-            payload: req.payload,
-            payload_type: req.payload_type,
-            qos: req.qos,
-            retain: req.retain,
-            description: req.description,
-            category: req.category,
-            use_count: 0,
-            last_used_at: None,
-            created_at: Some(now.clone()),
-            updated_at: Some(now),
-        };
-        
-        data.templates.push(template);
-        drop(data);
-        self.save()?;
-        Ok(id)
+        conn.execute(
+            "INSERT INTO command_templates (server_id, name, topic, payload, payload_type, qos,
+                retain, description, category, use_count, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, 0, ?10, ?10)",
+            params![
+                req.server_id,
+                req.name,
+                req.topic,
+                req.payload,
+                req.payload_type,
+                req.qos,
+                req.retain,
+                req.description,
+                req.category,
+                now,
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(conn.last_insert_rowid())
     }
 
     pub fn update_template(&self, req: UpdateTemplateRequest) -> Result<(), String> {
-        let mut data = self.data.write();
-        if let Some(template) = data.templates.iter_mut().find(|t| t.id == Some(req.id)) {
-            if let Some(name) = req.name {
-                template.name = name;
-            }
-            if let Some(topic) = req.topic {
-                template.topic = topic;
-            }
-            if let Some(payload) = req.payload {
-                template.payload = payload;
-            }
-            if let Some(payload_type) = req.payload_type {
-                template.payload_type = payload_type;
-            }
-            if let Some(qos) = req.qos {
-                template.qos = qos;
-            }
-            if let Some(retain) = req.retain {
-                template.retain = retain;
-            }
-            if let Some(description) = req.description {
-                template.description = Some(description);
-            }
-            if let Some(category) = req.category {
-                template.category = Some(category);
-            }
-            template.updated_at = Some(chrono::Utc::now().to_rfc3339());
+        let Some(mut template) = self.get_template(req.id) else {
+            return Ok(());
+        };
+        if let Some(name) = req.name {
+            template.name = name;
+        }
+        if let Some(topic) = req.topic {
+            template.topic = topic;
+        }
+        if let Some(payload) = req.payload {
+            template.payload = payload;
+        }
+        if let Some(payload_type) = req.payload_type {
+            template.payload_type = payload_type;
+        }
+        if let Some(qos) = req.qos {
+            template.qos = qos;
         }
-        drop(data);
-        self.save()
+        if let Some(retain) = req.retain {
+            template.retain = retain;
+        }
+        // 空字符串表示清除描述和分类
+        if let Some(description) = req.description {
+            template.description = Some(description).filter(|d| !d.is_empty());
+        }
+        if let Some(category) = req.category {
+            template.category = Some(category).filter(|c| !c.is_empty());
+        }
+
+        let conn = self.conn.lock();
+        conn.execute(
+            "UPDATE command_templates SET name = ?1, topic = ?2, payload = ?3, payload_type = ?4,
+                qos = ?5, retain = ?6, description = ?7, category = ?8, updated_at = ?9
+             WHERE id = ?10",
+            params![
+                template.name,
+                template.topic,
+                template.payload,
+                template.payload_type,
+                template.qos,
+                template.retain,
+                template.description,
+                template.category,
+                chrono::Utc::now().to_rfc3339(),
+                req.id,
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
     }
 
     pub fn delete_template(&self, id: i64) -> Result<(), String> {
-        let mut data = self.data.write();
-        data.templates.retain(|t| t.id != Some(id));
-        drop(data);
-        self.save()
+        let conn = self.conn.lock();
+        conn.execute("DELETE FROM command_templates WHERE id = ?1", [id])
+            .map_err(|e| e.to_string())?;
+        Ok(())
     }
 
     pub fn increment_template_use_count(&self, id: i64) -> Result<(), String> {
-        let mut data = self.data.write();
-        if let Some(template) = data.templates.iter_mut().find(|t| t.id == Some(id)) {
-            template.use_count += 1;
-            template.last_used_at = Some(chrono::Utc::now().to_rfc3339());
-        }
-        drop(data);
-        self.save()
+        let conn = self.conn.lock();
+        conn.execute(
+            "UPDATE command_templates SET use_count = use_count + 1, last_used_at = ?1 WHERE id = ?2",
+            params![chrono::Utc::now().to_rfc3339(), id],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
     }
 
     pub fn get_template_categories(&self, server_id: i64) -> Vec<String> {
-        let data = self.data.read();
-        let mut categories: Vec<String> = data
-            .templates
-            .iter()
-            .filter(|t| t.server_id == server_id)
-            .filter_map(|t| t.category.clone())
-            .collect();
-        categories.sort();
-        categories.dedup();
-        categories
+        let conn = self.conn.lock();
+        query_all(
+            &conn,
+            "SELECT DISTINCT category FROM command_templates
+             WHERE server_id = ?1 AND category IS NOT NULL ORDER BY category",
+            [server_id],
+            |row| row.get(0),
+        )
     }
 
     // ===== 脚本操作 =====
     pub fn get_scripts(&self, server_id: i64) -> Vec<Script> {
-        let data = self.data.read();
-        data.scripts
-            .iter()
-            .filter(|s| s.server_id == server_id)
-            .cloned()
-            .collect()
+        let conn = self.conn.lock();
+        query_all(
+            &conn,
+            &format!(
+                "SELECT {} FROM scripts WHERE server_id = ?1 ORDER BY id",
+                SCRIPT_COLUMNS
+            ),
+            [server_id],
+            script_from_row,
+        )
     }
 
     pub fn get_script(&self, id: i64) -> Option<Script> {
-        let data = self.data.read();
-        data.scripts.iter().find(|s| s.id == Some(id)).cloned()
+        let conn = self.conn.lock();
+        query_one(
+            &conn,
+            &format!("SELECT {} FROM scripts WHERE id = ?1", SCRIPT_COLUMNS),
+            [id],
+            script_from_row,
+        )
     }
 
     pub fn get_enabled_scripts(&self, server_id: i64, script_type: &str) -> Vec<Script> {
-        let data = self.data.read();
-        data.scripts
-            .iter()
-            .filter(|s| s.server_id == server_id && s.enabled && s.script_type == script_type)
-            .cloned()
-            .collect()
+        let conn = self.conn.lock();
+        query_all(
+            &conn,
+            &format!(
+                "SELECT {} FROM scripts WHERE server_id = ?1 AND enabled = 1 AND script_type = ?2
+                 ORDER BY id",
+                SCRIPT_COLUMNS
+            ),
+            params![server_id, script_type],
+            script_from_row,
+        )
     }
 
     pub fn create_script(&self, req: CreateScriptRequest) -> Result<i64, String> {
-        let mut data = self.data.write();
-        data.next_script_id += 1;
-        let id = data.next_script_id;
+        let conn = self.conn.lock();
         let now = chrono::Utc::now().to_rfc3339();
-        
-        let script = Script {
-            id: Some(id),
-            server_id: req.server_id,
-            name: req.name,
-            script_type: req.script_type,
-            code: req.code,
-            enabled: req.enabled,
-            description: req.description,
-            created_at: Some(now.clone()),
-            updated_at: Some(now),
-        };
-        
-        data.scripts.push(script);
-        drop(data);
-        self.save()?;
-        Ok(id)
+        conn.execute(
+            "INSERT INTO scripts (server_id, name, script_type, code, enabled, description,
+                topic_filter, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8)",
+            params![
+                req.server_id,
+                req.name,
+                req.script_type,
+                req.code,
+                req.enabled,
+                req.description,
+                req.topic_filter.filter(|f| !f.is_empty()),
+                now,
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(conn.last_insert_rowid())
     }
 
     pub fn update_script(&self, req: UpdateScriptRequest) -> Result<(), String> {
-        let mut data = self.data.write();
-        if let Some(script) = data.scripts.iter_mut().find(|s| s.id == Some(req.id)) {
-            if let Some(name) = req.name {
-                script.name = name;
-            }
-            if let Some(code) = req.code {
-                script.code = code;
-            }
-            if let Some(enabled) = req.enabled {
-                script.enabled = enabled;
-            }
-            if let Some(description) = req.description {
-                script.description = Some(description);
-            }
-            script.updated_at = Some(chrono::Utc::now().to_rfc3339());
+        let Some(mut script) = self.get_script(req.id) else {
+            return Ok(());
+        };
+        if let Some(name) = req.name {
+            script.name = name;
+        }
+        if let Some(code) = req.code {
+            script.code = code;
+        }
+        if let Some(enabled) = req.enabled {
+            script.enabled = enabled;
+        }
+        if let Some(description) = req.description {
+            script.description = Some(description);
         }
-        drop(data);
-        self.save()
+        if let Some(topic_filter) = req.topic_filter {
+            script.topic_filter = Some(topic_filter).filter(|f| !f.is_empty());
+        }
+
+        let conn = self.conn.lock();
+        conn.execute(
+            "UPDATE scripts SET name = ?1, code = ?2, enabled = ?3, description = ?4,
+                topic_filter = ?5, updated_at = ?6
+             WHERE id = ?7",
+            params![
+                script.name,
+                script.code,
+                script.enabled,
+                script.description,
+                script.topic_filter,
+                chrono::Utc::now().to_rfc3339(),
+                req.id,
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
     }
 
     pub fn delete_script(&self, id: i64) -> Result<(), String> {
-        let mut data = self.data.write();
-        data.scripts.retain(|s| s.id != Some(id));
-        drop(data);
-        self.save()
+        let conn = self.conn.lock();
+        conn.execute("DELETE FROM scripts WHERE id = ?1", [id])
+            .map_err(|e| e.to_string())?;
+        Ok(())
     }
 
     pub fn toggle_script(&self, id: i64, enabled: bool) -> Result<(), String> {
-        let mut data = self.data.write();
-        if let Some(script) = data.scripts.iter_mut().find(|s| s.id == Some(id)) {
-            script.enabled = enabled;
-            script.updated_at = Some(chrono::Utc::now().to_rfc3339());
-        }
-        drop(data);
-        self.save()
+        let conn = self.conn.lock();
+        conn.execute(
+            "UPDATE scripts SET enabled = ?1, updated_at = ?2 WHERE id = ?3",
+            params![enabled, chrono::Utc::now().to_rfc3339(), id],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    // ===== 定时任务操作 =====
+    pub fn get_scheduled_jobs(&self, server_id: i64) -> Vec<ScheduledJob> {
+        let conn = self.conn.lock();
+        query_all(
+            &conn,
+            &format!(
+                "SELECT {} FROM scheduled_jobs WHERE server_id = ?1 ORDER BY id",
+                JOB_COLUMNS
+            ),
+            [server_id],
+            job_from_row,
+        )
+    }
+
+    pub fn get_scheduled_job(&self, id: i64) -> Option<ScheduledJob> {
+        let conn = self.conn.lock();
+        query_one(
+            &conn,
+            &format!("SELECT {} FROM scheduled_jobs WHERE id = ?1", JOB_COLUMNS),
+            [id],
+            job_from_row,
+        )
+    }
+
+    pub fn create_scheduled_job(&self, mut job: ScheduledJob) -> Result<ScheduledJob, String> {
+        let conn = self.conn.lock();
+        let now = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO scheduled_jobs (server_id, name, template_ids, message, trigger, max_runs,
+                start_at, end_at, enabled, run_count, last_run_at, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?12)",
+            params![
+                job.server_id,
+                job.name,
+                to_json(&job.template_ids),
+                job.message.as_ref().map(to_json),
+                to_json(&job.trigger),
+                job.max_runs.map(|v| v as i64),
+                job.start_at,
+                job.end_at,
+                job.enabled,
+                job.run_count as i64,
+                job.last_run_at,
+                now,
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        job.id = Some(conn.last_insert_rowid());
+        job.created_at = Some(now.clone());
+        job.updated_at = Some(now);
+        Ok(job)
+    }
+
+    pub fn update_scheduled_job(&self, job: &ScheduledJob) -> Result<(), String> {
+        let conn = self.conn.lock();
+        conn.execute(
+            "UPDATE scheduled_jobs SET name = ?1, template_ids = ?2, message = ?3, trigger = ?4,
+                max_runs = ?5, start_at = ?6, end_at = ?7, enabled = ?8, run_count = ?9,
+                last_run_at = ?10, updated_at = ?11
+             WHERE id = ?12",
+            params![
+                job.name,
+                to_json(&job.template_ids),
+                job.message.as_ref().map(to_json),
+                to_json(&job.trigger),
+                job.max_runs.map(|v| v as i64),
+                job.start_at,
+                job.end_at,
+                job.enabled,
+                job.run_count as i64,
+                job.last_run_at,
+                chrono::Utc::now().to_rfc3339(),
+                job.id,
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// 记录一次发送
+    pub fn record_job_run(&self, id: i64, run_count: u64, last_run_at: &str) -> Result<(), String> {
+        let conn = self.conn.lock();
+        conn.execute(
+            "UPDATE scheduled_jobs SET run_count = ?1, last_run_at = ?2 WHERE id = ?3",
+            params![run_count as i64, last_run_at, id],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn set_job_enabled(&self, id: i64, enabled: bool) -> Result<(), String> {
+        let conn = self.conn.lock();
+        conn.execute(
+            "UPDATE scheduled_jobs SET enabled = ?1, updated_at = ?2 WHERE id = ?3",
+            params![enabled, chrono::Utc::now().to_rfc3339(), id],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn delete_scheduled_job(&self, id: i64) -> Result<(), String> {
+        let conn = self.conn.lock();
+        conn.execute("DELETE FROM scheduled_jobs WHERE id = ?1", [id])
+            .map_err(|e| e.to_string())?;
+        Ok(())
     }
 
     // ===== 环境变量操作 =====
     pub fn get_env_variables(&self, server_id: i64) -> Vec<EnvVariable> {
-        let data = self.data.read();
-        data.env_variables
-            .iter()
-            .filter(|e| e.server_id == server_id)
-            .cloned()
-            .collect()
+        let conn = self.conn.lock();
+        query_all(
+            &conn,
+            &format!(
+                "SELECT {} FROM env_variables WHERE server_id = ?1 ORDER BY id",
+                ENV_VARIABLE_COLUMNS
+            ),
+            [server_id],
+            env_variable_from_row,
+        )
+        .into_iter()
+        .map(|variable| self.reveal_env_variable(variable))
+        .collect()
+    }
+
+    pub fn get_global_env_variables(&self) -> Vec<EnvVariable> {
+        let conn = self.conn.lock();
+        query_all(
+            &conn,
+            &format!(
+                "SELECT {} FROM env_variables WHERE server_id IS NULL ORDER BY id",
+                ENV_VARIABLE_COLUMNS
+            ),
+            [],
+            env_variable_from_row,
+        )
+        .into_iter()
+        .map(|variable| self.reveal_env_variable(variable))
+        .collect()
     }
 
     pub fn get_env_variable(&self, id: i64) -> Option<EnvVariable> {
-        let data = self.data.read();
-        data.env_variables.iter().find(|e| e.id == Some(id)).cloned()
+        let conn = self.conn.lock();
+        query_one(
+            &conn,
+            &format!(
+                "SELECT {} FROM env_variables WHERE id = ?1",
+                ENV_VARIABLE_COLUMNS
+            ),
+            [id],
+            env_variable_from_row,
+        )
+        .map(|variable| self.reveal_env_variable(variable))
     }
 
     pub fn create_env_variable(&self, req: CreateEnvVariableRequest) -> Result<i64, String> {
-        let mut data = self.data.write();
-        
+        let conn = self.conn.lock();
+
         // 检查变量名是否重复
-        let exists = data.env_variables.iter().any(|e| {
-            e.server_id == req.server_id && e.name == req.name
-        });
-        if exists {
+        if env_variable_exists(&conn, req.server_id, &req.name, None)? {
             return Err("Variable name already exists".to_string());
         }
 
-        data.next_env_variable_id += 1;
-        let id = data.next_env_variable_id;
-        let now = chrono::Utc::now().to_rfc3339();
-        
-        let env_var = EnvVariable {
-            id: Some(id),
-            server_id: req.server_id,
-            name: req.name,
-            value: req.value,
-            description: req.description,
-            created_at: Some(now.clone()),
-            updated_at: Some(now),
+        let value = match req.secret {
+            true => self.seal_secret(&conn, &req.value)?,
+            false => req.value,
         };
-        
-        data.env_variables.push(env_var);
-        drop(data);
-        self.save()?;
-        Ok(id)
+
+        let now = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO env_variables (server_id, name, value, description, secret, created_at,
+                updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)",
+            params![
+                req.server_id,
+                req.name,
+                value,
+                req.description,
+                req.secret,
+                now
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(conn.last_insert_rowid())
     }
 
     pub fn update_env_variable(&self, req: UpdateEnvVariableRequest) -> Result<(), String> {
-        let mut data = self.data.write();
-        
+        let conn = self.conn.lock();
+        // 读取未解密的记录，保险库锁定时也能保留已加密的值
+        let Some(mut env_var) = query_one(
+            &conn,
+            &format!(
+                "SELECT {} FROM env_variables WHERE id = ?1",
+                ENV_VARIABLE_COLUMNS
+            ),
+            [req.id],
+            env_variable_from_row,
+        ) else {
+            return Ok(());
+        };
+
         // 如果要更新名称，检查是否与其他变量重复
         if let Some(new_name) = &req.name {
-            let current = data.env_variables.iter().find(|e| e.id == Some(req.id));
-            if let Some(current) = current {
-                let exists = data.env_variables.iter().any(|e| {
-                    e.server_id == current.server_id && e.name == *new_name && e.id != Some(req.id)
-                });
-                if exists {
-                    return Err("Variable name already exists".to_string());
-                }
+            if env_variable_exists(&conn, env_var.server_id, new_name, Some(req.id))? {
+                return Err("Variable name already exists".to_string());
+            }
+        }
+
+        if let Some(name) = req.name {
+            env_var.name = name;
+        }
+        if let Some(description) = req.description {
+            env_var.description = Some(description);
+        }
+
+        // 修改值或敏感标记时需要重新加密或解密
+        let secret = req.secret.unwrap_or(env_var.secret);
+        let value = match req.value {
+            Some(value) => Some(value),
+            None if secret != env_var.secret => {
+                Some(self.vault.reveal(&env_var.value).ok_or("Vault is locked")?)
             }
+            None => None,
+        };
+        if let Some(value) = value {
+            env_var.value = match secret {
+                true => self.seal_secret(&conn, &value)?,
+                false => value,
+            };
+        }
+
+        conn.execute(
+            "UPDATE env_variables SET name = ?1, value = ?2, description = ?3, secret = ?4,
+                updated_at = ?5
+             WHERE id = ?6",
+            params![
+                env_var.name,
+                env_var.value,
+                env_var.description,
+                secret,
+                chrono::Utc::now().to_rfc3339(),
+                req.id,
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn delete_env_variable(&self, id: i64) -> Result<(), String> {
+        let conn = self.conn.lock();
+        conn.execute("DELETE FROM env_variables WHERE id = ?1", [id])
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    // ===== 保险库操作 =====
+    pub fn vault_status(&self) -> VaultStatus {
+        let conn = self.conn.lock();
+        VaultStatus {
+            initialized: vault_config(&conn).is_some(),
+            unlocked: self.vault.is_unlocked(),
+        }
+    }
+
+    /// 设置主密码并加密已有的敏感字段
+    pub fn setup_vault(&self, passphrase: &str) -> Result<(), String> {
+        let mut conn = self.conn.lock();
+        if vault_config(&conn).is_some() {
+            return Err("Vault is already set up".to_string());
         }
+        let (cipher, config) = Vault::create(passphrase)?;
 
-        if let Some(env_var) = data.env_variables.iter_mut().find(|e| e.id == Some(req.id)) {
-            if let Some(name) = req.name {
-                env_var.name = name;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        save_vault_config(&tx, &config)?;
+        update_secrets(&tx, |value| {
+            if is_sealed(value) {
+                return Ok(None);
             }
-            if let Some(value) = req.value {
-                env_var.value = value;
+            seal_with(&cipher, value).map(Some)
+        })?;
+        tx.commit().map_err(|e| e.to_string())?;
+
+        self.vault.set_cipher(cipher);
+        Ok(())
+    }
+
+    pub fn unlock_vault(&self, passphrase: &str) -> Result<(), String> {
+        let config = vault_config(&self.conn.lock()).ok_or("Vault is not set up")?;
+        self.vault.unlock(passphrase, &config)
+    }
+
+    pub fn lock_vault(&self) {
+        self.vault.lock();
+    }
+
+    /// 修改主密码，所有敏感字段使用新密钥重新加密
+    pub fn change_vault_passphrase(&self, current: &str, passphrase: &str) -> Result<(), String> {
+        let mut conn = self.conn.lock();
+        let config = vault_config(&conn).ok_or("Vault is not set up")?;
+        self.vault.unlock(current, &config)?;
+        let (cipher, config) = Vault::create(passphrase)?;
+
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        save_vault_config(&tx, &config)?;
+        update_secrets(&tx, |value| {
+            if !is_sealed(value) {
+                return Ok(None);
             }
-            if let Some(description) = req.description {
-                env_var.description = Some(description);
+            let plain = self
+                .vault
+                .reveal(value)
+                .ok_or("Failed to decrypt a stored secret")?;
+            seal_with(&cipher, &plain).map(Some)
+        })?;
+        tx.commit().map_err(|e| e.to_string())?;
+
+        self.vault.set_cipher(cipher);
+        Ok(())
+    }
+
+    /// Server 相关的敏感值（凭据和敏感环境变量），用于导出时脱敏
+    ///
+    /// 保险库锁定时无法得知加密内容，返回错误。
+    pub fn secret_values(&self, server_id: i64) -> Result<Vec<String>, String> {
+        let conn = self.conn.lock();
+        let mut values: Vec<String> = query_all(
+            &conn,
+            "SELECT value FROM env_variables
+             WHERE secret = 1 AND (server_id IS NULL OR server_id = ?1)",
+            [server_id],
+            |row| row.get(0),
+        );
+        let credentials = query_one(
+            &conn,
+            "SELECT password, client_key_password FROM mqtt_servers WHERE id = ?1",
+            [server_id],
+            |row| Ok([row.get::<_, Option<String>>(0)?, row.get(1)?]),
+        );
+        values.extend(credentials.into_iter().flatten().flatten());
+
+        values
+            .into_iter()
+            .filter(|value| !value.is_empty())
+            .map(|value| {
+                self.vault
+                    .reveal(&value)
+                    .ok_or_else(|| "Vault is locked, unlock it to redact secrets".to_string())
+            })
+            .collect()
+    }
+
+    /// 解密 Server 凭据，保险库锁定时为空
+    fn reveal_server(&self, mut server: MqttServer) -> MqttServer {
+        server.password = server.password.and_then(|v| self.vault.reveal(&v));
+        server.client_key_password = server
+            .client_key_password
+            .and_then(|v| self.vault.reveal(&v));
+        server
+    }
+
+    /// 解密敏感环境变量，保险库锁定时值为空
+    fn reveal_env_variable(&self, mut variable: EnvVariable) -> EnvVariable {
+        if variable.secret {
+            variable.value = self.vault.reveal(&variable.value).unwrap_or_default();
+        }
+        variable
+    }
+
+    /// 已设置保险库时加密字段值，否则按明文保存
+    fn seal_value(
+        &self,
+        conn: &Connection,
+        value: Option<String>,
+    ) -> Result<Option<String>, String> {
+        match value {
+            Some(value) if !value.is_empty() && vault_config(conn).is_some() => {
+                self.vault.seal(&value).map(Some)
             }
-            env_var.updated_at = Some(chrono::Utc::now().to_rfc3339());
+            value => Ok(value),
         }
-        drop(data);
-        self.save()
     }
 
-    pub fn delete_env_variable(&self, id: i64) -> Result<(), String> {
-        let mut data = self.data.write();
-        data.env_variables.retain(|e| e.id != Some(id));
-        drop(data);
-        self.save()
+    /// 加密敏感变量的值，未设置保险库时返回错误
+    fn seal_secret(&self, conn: &Connection, value: &str) -> Result<String, String> {
+        if vault_config(conn).is_none() {
+            return Err("Set up the vault before storing secrets".to_string());
+        }
+        self.vault.seal(value)
+    }
+}
+
+// ===== 行映射 =====
+
+const SERVER_COLUMNS: &str = "id, name, host, port, protocol_version, username, password, \
+    client_id, keep_alive, clean_session, use_tls, ca_cert, client_cert, client_key, \
+    client_key_password, reconnect, save_received_messages, retention, created_at, updated_at";
+
+const SUBSCRIPTION_COLUMNS: &str =
+    "id, server_id, topic, qos, is_active, color, last_grant, created_at";
+
+const MESSAGE_COLUMNS: &str = "id, server_id, direction, topic, payload, payload_format, \
+    raw_payload, qos, retain, properties, delivery_state, latency_ms, delivery_error, created_at";
+
+const TEMPLATE_COLUMNS: &str = "id, server_id, name, topic, payload, payload_type, qos, retain, \
+    description, category, use_count, last_used_at, created_at, updated_at";
+
+const SCRIPT_COLUMNS: &str = "id, server_id, name, script_type, code, enabled, description, \
+    created_at, updated_at, topic_filter";
+
+const JOB_COLUMNS: &str = "id, server_id, name, template_ids, message, trigger, max_runs, \
+    start_at, end_at, enabled, run_count, last_run_at, created_at, updated_at";
+
+const ENV_VARIABLE_COLUMNS: &str =
+    "id, server_id, name, value, description, created_at, updated_at, secret";
+
+/// 加密保存的字段：(表, 列, 条件)
+const SECRET_FIELDS: [(&str, &str, &str); 3] = [
+    ("mqtt_servers", "password", "1"),
+    ("mqtt_servers", "client_key_password", "1"),
+    ("env_variables", "value", "secret = 1"),
+];
+
+/// 序列化嵌套结构，存入 TEXT 列
+pub(crate) fn to_json<T: serde::Serialize>(value: &T) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+/// 读取 JSON 列，无法解析时视为空
+fn json_column<T: serde::de::DeserializeOwned>(
+    row: &Row,
+    idx: usize,
+) -> rusqlite::Result<Option<T>> {
+    Ok(row
+        .get::<_, Option<String>>(idx)?
+        .and_then(|s| serde_json::from_str(&s).ok()))
+}
+
+fn query_all<T, P, F>(conn: &Connection, sql: &str, params: P, f: F) -> Vec<T>
+where
+    P: rusqlite::Params,
+    F: FnMut(&Row) -> rusqlite::Result<T>,
+{
+    let Ok(mut stmt) = conn.prepare(sql) else {
+        return Vec::new();
+    };
+    stmt.query_map(params, f)
+        .map(|rows| rows.filter_map(Result::ok).collect())
+        .unwrap_or_default()
+}
+
+fn query_one<T, P, F>(conn: &Connection, sql: &str, params: P, f: F) -> Option<T>
+where
+    P: rusqlite::Params,
+    F: FnOnce(&Row) -> rusqlite::Result<T>,
+{
+    conn.query_row(sql, params, f).optional().ok().flatten()
+}
+
+/// 计算保留策略的清理边界：ID 不大于 max_id 或早于 cutoff 的消息都应清理
+///
+/// 两个条件都不命中时返回 None。
+fn expired_bounds(
+    conn: &Connection,
+    server_id: i64,
+    policy: &RetentionPolicy,
+) -> Result<Option<(i64, String)>, String> {
+    let mut max_id: i64 = 0;
+
+    if let Some(max_messages) = policy.max_messages {
+        let id: Option<i64> = conn
+            .query_row(
+                "SELECT id FROM message_history WHERE server_id = ?1
+                 ORDER BY id DESC LIMIT 1 OFFSET ?2",
+                params![server_id, max_messages as i64],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        max_id = max_id.max(id.unwrap_or(0));
+    }
+
+    if let Some(max_bytes) = policy.max_bytes {
+        // 从最新的消息开始累计大小，超出上限之后的全部清理
+        let id: Option<i64> = conn
+            .query_row(
+                "SELECT MAX(id) FROM (
+                    SELECT id, SUM(LENGTH(CAST(topic AS BLOB)) + IFNULL(LENGTH(raw_payload), 0))
+                        OVER (ORDER BY id DESC) AS total
+                    FROM message_history WHERE server_id = ?1
+                 ) WHERE total > ?2",
+                params![server_id, max_bytes as i64],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        max_id = max_id.max(id.unwrap_or(0));
+    }
+
+    // created_at 均为 UTC RFC3339 格式，可直接按字符串比较
+    let cutoff = policy
+        .max_age_secs
+        .map(|secs| (chrono::Utc::now() - chrono::Duration::seconds(secs as i64)).to_rfc3339())
+        .unwrap_or_default();
+
+    if max_id == 0 && cutoff.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some((max_id, cutoff)))
+}
+
+/// 将 RFC3339 时间统一转换为 UTC，与 created_at 的格式保持一致
+fn normalize_time(value: &str) -> Result<String, String> {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&chrono::Utc).to_rfc3339())
+        .map_err(|e| format!("Invalid time '{}': {}", value, e))
+}
+
+/// 保险库锁定时保存凭据：未提交或与已保存的值相同则保留，空字符串表示清空，写入新值时返回错误
+///
+/// 锁定时加密的凭据读取为空，前端未修改时按读取到的值（None）提交。
+fn keep_stored_secret(
+    stored: Option<String>,
+    value: Option<String>,
+) -> Result<Option<String>, String> {
+    match value {
+        None => Ok(stored),
+        Some(value) if value.is_empty() => Ok(None),
+        Some(value) if stored.as_deref() == Some(value.as_str()) => Ok(stored),
+        Some(_) => Err("Vault is locked".to_string()),
+    }
+}
+
+fn vault_config(conn: &Connection) -> Option<VaultConfig> {
+    query_one(
+        conn,
+        "SELECT salt, kdf_params, verifier FROM vault WHERE id = 1",
+        [],
+        |row| {
+            let salt: String = row.get(0)?;
+            Ok(VaultConfig {
+                salt: STANDARD.decode(salt).unwrap_or_default(),
+                params: json_column(row, 1)?.unwrap_or_default(),
+                verifier: row.get(2)?,
+            })
+        },
+    )
+}
+
+fn save_vault_config(conn: &Connection, config: &VaultConfig) -> Result<(), String> {
+    conn.execute(
+        "INSERT OR REPLACE INTO vault (id, salt, kdf_params, verifier, created_at)
+         VALUES (1, ?1, ?2, ?3, ?4)",
+        params![
+            STANDARD.encode(&config.salt),
+            to_json(&config.params),
+            config.verifier,
+            chrono::Utc::now().to_rfc3339(),
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// 逐个处理所有非空的敏感字段，f 返回 Some 时写入新值
+fn update_secrets<F>(conn: &Connection, f: F) -> Result<(), String>
+where
+    F: Fn(&str) -> Result<Option<String>, String>,
+{
+    for (table, column, condition) in SECRET_FIELDS {
+        let rows: Vec<(i64, String)> = query_all(
+            conn,
+            &format!(
+                "SELECT id, {column} FROM {table}
+                 WHERE {column} IS NOT NULL AND {column} != '' AND {condition}"
+            ),
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        );
+        for (id, value) in rows {
+            if let Some(value) = f(&value)? {
+                conn.execute(
+                    &format!("UPDATE {table} SET {column} = ?1 WHERE id = ?2"),
+                    params![value, id],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn env_variable_exists(
+    conn: &Connection,
+    server_id: Option<i64>,
+    name: &str,
+    exclude_id: Option<i64>,
+) -> Result<bool, String> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM env_variables
+            WHERE server_id IS ?1 AND name = ?2 AND id IS NOT ?3)",
+        params![server_id, name, exclude_id],
+        |row| row.get(0),
+    )
+    .map_err(|e| e.to_string())
+}
+
+fn server_from_row(row: &Row) -> rusqlite::Result<MqttServer> {
+    Ok(MqttServer {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        host: row.get(2)?,
+        port: row.get(3)?,
+        protocol_version: row.get(4)?,
+        username: row.get(5)?,
+        password: row.get(6)?,
+        client_id: row.get(7)?,
+        keep_alive: row.get(8)?,
+        clean_session: row.get(9)?,
+        use_tls: row.get(10)?,
+        ca_cert: row.get(11)?,
+        client_cert: row.get(12)?,
+        client_key: row.get(13)?,
+        client_key_password: row.get(14)?,
+        reconnect: json_column(row, 15)?.unwrap_or_default(),
+        save_received_messages: row.get(16)?,
+        retention: json_column(row, 17)?.unwrap_or_default(),
+        created_at: row.get(18)?,
+        updated_at: row.get(19)?,
+    })
+}
+
+fn subscription_from_row(row: &Row) -> rusqlite::Result<Subscription> {
+    Ok(Subscription {
+        id: row.get(0)?,
+        server_id: row.get(1)?,
+        topic: row.get(2)?,
+        qos: row.get(3)?,
+        is_active: row.get(4)?,
+        color: row.get(5)?,
+        last_grant: json_column(row, 6)?,
+        created_at: row.get(7)?,
+    })
+}
+
+fn message_from_row(row: &Row) -> rusqlite::Result<MessageHistory> {
+    Ok(MessageHistory {
+        id: row.get(0)?,
+        server_id: row.get(1)?,
+        direction: row.get(2)?,
+        topic: row.get(3)?,
+        payload: row.get(4)?,
+        payload_format: row.get(5)?,
+        raw_payload: row.get(6)?,
+        qos: row.get(7)?,
+        retain: row.get(8)?,
+        properties: json_column(row, 9)?,
+        delivery_state: row.get(10)?,
+        latency_ms: row.get::<_, Option<i64>>(11)?.map(|v| v as u64),
+        delivery_error: row.get(12)?,
+        created_at: row.get(13)?,
+    })
+}
+
+fn template_from_row(row: &Row) -> rusqlite::Result<CommandTemplate> {
+    Ok(CommandTemplate {
+        id: row.get(0)?,
+        server_id: row.get(1)?,
+        name: row.get(2)?,
+        topic: row.get(3)?,
+        payload: row.get(4)?,
+        payload_type: row.get(5)?,
+        qos: row.get(6)?,
+        retain: row.get(7)?,
+        description: row.get(8)?,
+        category: row.get(9)?,
+        use_count: row.get(10)?,
+        last_used_at: row.get(11)?,
+        created_at: row.get(12)?,
+        updated_at: row.get(13)?,
+    })
+}
+
+fn script_from_row(row: &Row) -> rusqlite::Result<Script> {
+    Ok(Script {
+        id: row.get(0)?,
+        server_id: row.get(1)?,
+        name: row.get(2)?,
+        script_type: row.get(3)?,
+        code: row.get(4)?,
+        enabled: row.get(5)?,
+        description: row.get(6)?,
+        created_at: row.get(7)?,
+        updated_at: row.get(8)?,
+        topic_filter: row.get(9)?,
+    })
+}
+
+fn job_from_row(row: &Row) -> rusqlite::Result<ScheduledJob> {
+    let trigger: String = row.get(5)?;
+    Ok(ScheduledJob {
+        id: row.get(0)?,
+        server_id: row.get(1)?,
+        name: row.get(2)?,
+        template_ids: json_column(row, 3)?.unwrap_or_default(),
+        message: json_column(row, 4)?,
+        trigger: serde_json::from_str(&trigger).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, Box::new(e))
+        })?,
+        max_runs: row.get::<_, Option<i64>>(6)?.map(|v| v as u64),
+        start_at: row.get(7)?,
+        end_at: row.get(8)?,
+        enabled: row.get(9)?,
+        run_count: row.get::<_, i64>(10)? as u64,
+        last_run_at: row.get(11)?,
+        created_at: row.get(12)?,
+        updated_at: row.get(13)?,
+    })
+}
+
+fn env_variable_from_row(row: &Row) -> rusqlite::Result<EnvVariable> {
+    Ok(EnvVariable {
+        id: row.get(0)?,
+        server_id: row.get(1)?,
+        name: row.get(2)?,
+        value: row.get(3)?,
+        description: row.get(4)?,
+        secret: row.get(7)?,
+        created_at: row.get(5)?,
+        updated_at: row.get(6)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 每条消息按主题和内容计 10 字节，ID 1、2 为一天前的消息
+    fn history() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migration::migrate(&mut conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO mqtt_servers (id, name, host) VALUES (1, 'a', 'h'), (2, 'b', 'h')",
+        )
+        .unwrap();
+        let old = (chrono::Utc::now() - chrono::Duration::days(1)).to_rfc3339();
+        let now = chrono::Utc::now().to_rfc3339();
+        for (server_id, created_at) in [(1, &old), (1, &old), (1, &now), (1, &now), (1, &now)]
+            .into_iter()
+            .chain([(2, &old); 3])
+        {
+            conn.execute(
+                "INSERT INTO message_history (server_id, direction, topic, raw_payload, created_at)
+                 VALUES (?1, 'receive', 't', ?2, ?3)",
+                params![server_id, vec![0u8; 9], created_at],
+            )
+            .unwrap();
+        }
+        conn
+    }
+
+    fn policy(
+        max_messages: Option<u64>,
+        max_bytes: Option<u64>,
+        max_age_secs: Option<u64>,
+    ) -> RetentionPolicy {
+        RetentionPolicy {
+            max_messages,
+            max_age_secs,
+            max_bytes,
+            archive: false,
+        }
+    }
+
+    #[test]
+    fn expired_bounds_by_count() {
+        let conn = history();
+        let bounds = expired_bounds(&conn, 1, &policy(Some(3), None, None)).unwrap();
+        assert_eq!(bounds, Some((2, String::new())));
+        assert_eq!(
+            expired_bounds(&conn, 1, &policy(Some(5), None, None)).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn expired_bounds_by_bytes() {
+        let conn = history();
+        let bounds = expired_bounds(&conn, 1, &policy(None, Some(25), None)).unwrap();
+        assert_eq!(bounds, Some((3, String::new())));
+        assert_eq!(
+            expired_bounds(&conn, 1, &policy(None, Some(50), None)).unwrap(),
+            None
+        );
+        // 两个条件同时设置时取清理范围更大的一个
+        let bounds = expired_bounds(&conn, 1, &policy(Some(4), Some(25), None)).unwrap();
+        assert_eq!(bounds, Some((3, String::new())));
+    }
+
+    #[test]
+    fn expired_bounds_by_age() {
+        let conn = history();
+        let (max_id, cutoff) = expired_bounds(&conn, 1, &policy(None, None, Some(3600)))
+            .unwrap()
+            .unwrap();
+        assert_eq!(max_id, 0);
+        let expired: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM message_history WHERE server_id = 1 AND created_at < ?1",
+                [&cutoff],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(expired, 2);
+        assert_eq!(
+            expired_bounds(&conn, 1, &policy(None, None, None)).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn change_vault_passphrase_reseals_secrets() {
+        let storage = Storage::in_memory();
+        storage
+            .conn
+            .lock()
+            .execute(
+                "INSERT INTO mqtt_servers (name, host, password) VALUES ('a', 'h', 'broker-pass')",
+                [],
+            )
+            .unwrap();
+        storage.setup_vault("first passphrase").unwrap();
+        let sealed: String = storage
+            .conn
+            .lock()
+            .query_row(
+                "SELECT password FROM mqtt_servers WHERE id = 1",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(is_sealed(&sealed));
+
+        assert_eq!(
+            storage
+                .change_vault_passphrase("wrong passphrase", "second passphrase")
+                .unwrap_err(),
+            "Incorrect passphrase"
+        );
+        storage
+            .change_vault_passphrase("first passphrase", "second passphrase")
+            .unwrap();
+        storage.lock_vault();
+        assert_eq!(storage.get_server(1).unwrap().password, None);
+
+        assert!(storage.unlock_vault("first passphrase").is_err());
+        storage.unlock_vault("second passphrase").unwrap();
+        assert_eq!(
+            storage.get_server(1).unwrap().password.as_deref(),
+            Some("broker-pass")
+        );
+    }
+
+    #[test]
+    fn locked_vault_keeps_unchanged_credentials() {
+        let stored = Some("vault:v1:sealed".to_string());
+        let keep =
+            |value: Option<&str>| keep_stored_secret(stored.clone(), value.map(String::from));
+
+        assert_eq!(keep(None).unwrap(), stored);
+        assert_eq!(keep(Some("vault:v1:sealed")).unwrap(), stored);
+        assert_eq!(keep(Some("")).unwrap(), None);
+        assert_eq!(keep(Some("new-pass")).unwrap_err(), "Vault is locked");
     }
 }
@@ -17,10 +17,71 @@ pub struct MqttServer {
     pub client_cert: Option<String>,
     pub client_key: Option<String>,
     pub client_key_password: Option<String>,
+    /// 自动重连策略
+    #[serde(default)]
+    pub reconnect: ReconnectPolicy,
+    /// 是否由后端保存收到的消息到历史记录
+    #[serde(default = "default_true")]
+    pub save_received_messages: bool,
+    /// 历史消息保留策略
+    #[serde(default)]
+    pub retention: RetentionPolicy,
     pub created_at: Option<String>,
     pub updated_at: Option<String>,
 }
 
+/// 自动重连策略（指数退避）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReconnectPolicy {
+    pub enabled: bool,
+    /// 首次重连等待时间（毫秒）
+    pub initial_delay_ms: u64,
+    /// 最大重连等待时间（毫秒）
+    pub max_delay_ms: u64,
+    /// 随机抖动比例（0.0 ~ 1.0）
+    pub jitter: f64,
+    /// 最大重连次数，0 表示不限制
+    pub max_attempts: u32,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            initial_delay_ms: 1000,
+            max_delay_ms: 30_000,
+            jitter: 0.2,
+            max_attempts: 0,
+        }
+    }
+}
+
+/// 历史消息保留策略，超出任一限制的旧消息会在后台清理
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    /// 最多保留的消息条数
+    pub max_messages: Option<u64>,
+    /// 消息最长保留时间（秒）
+    pub max_age_secs: Option<u64>,
+    /// 消息总大小上限（字节，按主题和内容计算）
+    pub max_bytes: Option<u64>,
+    /// 删除前是否写入归档文件
+    pub archive: bool,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_messages: Some(1000),
+            max_age_secs: None,
+            max_bytes: None,
+            archive: false,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Subscription {
     pub id: Option<i64>,
@@ -32,9 +93,21 @@ pub struct Subscription {
     /// 订阅的颜色标记（用于消息列表中高亮显示）
     #[serde(default)]
     pub color: Option<String>,
+    /// 最近一次订阅时 broker 返回的结果
+    #[serde(default)]
+    pub last_grant: Option<SubscriptionGrant>,
     pub created_at: Option<String>,
 }
 
+/// 订阅授权结果（来自 SUBACK）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscriptionGrant {
+    /// Broker 授予的 QoS，失败时为 None
+    pub granted_qos: Option<i32>,
+    pub error: Option<String>,
+    pub updated_at: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateSubscriptionRequest {
     pub id: i64,
@@ -56,11 +129,99 @@ pub struct MessageHistory {
     pub payload: Option<String>,
     #[serde(default)]
     pub payload_format: Option<String>, // "text", "json", "hex"
+    /// 原始内容字节（JSON 中为 base64），payload 仅用于显示
+    #[serde(default, with = "base64_bytes")]
+    pub raw_payload: Option<Vec<u8>>,
     pub qos: i32,
     pub retain: bool,
+    /// MQTT 5 消息属性（仅 5.0 连接）
+    #[serde(default)]
+    pub properties: Option<MessageProperties>,
+    /// 投递状态（仅发布消息）："queued" | "sent" | "acked" | "failed"
+    #[serde(default)]
+    pub delivery_state: Option<String>,
+    /// 从报文写出到收到 PUBACK / PUBCOMP 的耗时（毫秒）
+    #[serde(default)]
+    pub latency_ms: Option<u64>,
+    #[serde(default)]
+    pub delivery_error: Option<String>,
     pub created_at: Option<String>,
 }
 
+impl MessageHistory {
+    /// 消息的原始字节；旧记录没有 raw_payload 时按显示格式还原
+    pub fn payload_bytes(&self) -> Vec<u8> {
+        if let Some(raw) = &self.raw_payload {
+            return raw.clone();
+        }
+        let payload = self.payload.as_deref().unwrap_or_default();
+        match self.payload_format.as_deref() {
+            Some("hex") => hex::decode(payload.replace(' ', ""))
+                .unwrap_or_else(|_| payload.as_bytes().to_vec()),
+            _ => payload.as_bytes().to_vec(),
+        }
+    }
+}
+
+/// 以 base64 字符串序列化字节内容
+pub(crate) mod base64_bytes {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<Vec<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(bytes) => serializer.serialize_some(&STANDARD.encode(bytes)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<u8>>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|s| STANDARD.decode(s).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+/// 历史消息查询条件，所有条件均为可选
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MessageQuery {
+    pub server_id: i64,
+    /// 主题过滤器，支持 `+` / `#` 通配符
+    pub topic: Option<String>,
+    pub direction: Option<String>,
+    pub qos: Option<i32>,
+    pub retain: Option<bool>,
+    /// 起始时间（RFC3339，含）
+    pub since: Option<String>,
+    /// 结束时间（RFC3339，不含）
+    pub until: Option<String>,
+    /// 内容包含的文本
+    pub payload_contains: Option<String>,
+    /// 内容匹配的正则表达式
+    pub payload_regex: Option<String>,
+    /// 上一页返回的 next_cursor，从该消息之后继续查询
+    pub cursor: Option<i64>,
+    /// 是否按时间正序返回（默认倒序）
+    pub ascending: bool,
+    /// 每页条数，默认 100，最大 1000
+    pub limit: Option<usize>,
+}
+
+/// 历史消息分页结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessagePage {
+    pub messages: Vec<MessageHistory>,
+    /// 还有更多结果时，作为下一次查询的 cursor
+    pub next_cursor: Option<i64>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PublishPayload {
     pub topic: String,
@@ -68,6 +229,69 @@ pub struct PublishPayload {
     pub qos: i32,
     pub retain: bool,
     pub format: String, // "json" | "hex" | "text"
+    /// MQTT 5 发布属性，仅当服务器 protocol_version 为 "5.0" 时可用
+    #[serde(default)]
+    pub properties: Option<MessageProperties>,
+    /// 是否等待 Broker 确认后再返回
+    #[serde(default)]
+    pub await_ack: bool,
+    /// 等待确认的超时时间（毫秒），默认 10000
+    #[serde(default)]
+    pub ack_timeout_ms: Option<u64>,
+}
+
+/// MQTT 5 用户属性
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserProperty {
+    pub key: String,
+    pub value: String,
+}
+
+/// MQTT 5 消息属性
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MessageProperties {
+    #[serde(default)]
+    pub user_properties: Vec<UserProperty>,
+    #[serde(default)]
+    pub content_type: Option<String>,
+    #[serde(default)]
+    pub response_topic: Option<String>,
+    #[serde(default)]
+    pub correlation_data: Option<Vec<u8>>,
+    /// 消息过期时间（秒）
+    #[serde(default)]
+    pub message_expiry_interval: Option<u32>,
+    /// 0 = 未指定字节流，1 = UTF-8 字符串
+    #[serde(default)]
+    pub payload_format_indicator: Option<u8>,
+    /// 匹配的订阅标识符（仅接收）
+    #[serde(default)]
+    pub subscription_identifiers: Vec<u32>,
+    /// Broker 使用的主题别名（仅接收）
+    #[serde(default)]
+    pub topic_alias: Option<u16>,
+}
+
+impl MessageProperties {
+    pub fn is_empty(&self) -> bool {
+        self.user_properties.is_empty()
+            && self.content_type.is_none()
+            && self.response_topic.is_none()
+            && self.correlation_data.is_none()
+            && self.message_expiry_interval.is_none()
+            && self.payload_format_indicator.is_none()
+            && self.subscription_identifiers.is_empty()
+            && self.topic_alias.is_none()
+    }
+
+    /// 去掉只在接收时有意义的属性（订阅标识符、主题别名），用于重新发布
+    pub fn publishable(self) -> Self {
+        Self {
+            subscription_identifiers: Vec::new(),
+            topic_alias: None,
+            ..self
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -115,17 +339,52 @@ pub struct UpdateTemplateRequest {
     pub category: Option<String>,
 }
 
+/// 导入模板时同名模板的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateImportStrategy {
+    /// 跳过已存在的同名模板
+    Skip,
+    /// 用导入的内容覆盖同名模板
+    Overwrite,
+}
+
+/// 导入模板时跳过或失败的条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateImportIssue {
+    /// 在导入文件中的位置（从 0 开始）
+    pub index: usize,
+    pub name: String,
+    pub reason: String,
+}
+
+/// 模板导入结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TemplateImportReport {
+    /// 新建的模板名称
+    pub created: Vec<String>,
+    /// 被覆盖的模板名称
+    pub updated: Vec<String>,
+    pub skipped: Vec<TemplateImportIssue>,
+    pub failed: Vec<TemplateImportIssue>,
+}
+
 /// 预处理脚本
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Script {
     pub id: Option<i64>,
     pub server_id: i64,
     pub name: String,
-    pub script_type: String, // "before_publish" | "after_receive"
+    /// "before_publish" | "after_receive" | "message_filter" | "auto_responder"
+    /// | "on_connect" | "on_disconnect"
+    pub script_type: String,
     pub code: String,
     #[serde(default)]
     pub enabled: bool,
     pub description: Option<String>,
+    /// 生效的主题过滤器（消息过滤和自动回复脚本），为空时匹配所有主题
+    #[serde(default)]
+    pub topic_filter: Option<String>,
     pub created_at: Option<String>,
     pub updated_at: Option<String>,
 }
@@ -138,6 +397,8 @@ pub struct CreateScriptRequest {
     pub code: String,
     pub enabled: bool,
     pub description: Option<String>,
+    #[serde(default)]
+    pub topic_filter: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -147,26 +408,84 @@ pub struct UpdateScriptRequest {
     pub code: Option<String>,
     pub enabled: Option<bool>,
     pub description: Option<String>,
+    /// 空字符串表示清除主题过滤器
+    #[serde(default)]
+    pub topic_filter: Option<String>,
+}
+
+/// 定时发布任务的触发方式
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JobTrigger {
+    /// 固定间隔发送；每发送完一轮消息后可使用单独的轮次间隔
+    Interval {
+        interval_ms: u64,
+        #[serde(default)]
+        round_interval_ms: Option<u64>,
+    },
+    /// Cron 表达式（5 位，或带秒的 6/7 位），按本地时间计算
+    Cron { expression: String },
+}
+
+/// 定时发布任务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledJob {
+    pub id: Option<i64>,
+    pub server_id: i64,
+    pub name: String,
+    /// 按顺序循环发送的命令模板，为空时发送 message
+    #[serde(default)]
+    pub template_ids: Vec<i64>,
+    #[serde(default)]
+    pub message: Option<PublishPayload>,
+    pub trigger: JobTrigger,
+    /// 最多发送的消息条数，None 表示不限
+    #[serde(default)]
+    pub max_runs: Option<u64>,
+    /// 开始时间（RFC 3339），早于该时间不发送
+    #[serde(default)]
+    pub start_at: Option<String>,
+    /// 结束时间（RFC 3339），到达后任务完成
+    #[serde(default)]
+    pub end_at: Option<String>,
+    #[serde(default)]
+    pub enabled: bool,
+    /// 已发送的消息条数
+    #[serde(default)]
+    pub run_count: u64,
+    #[serde(default)]
+    pub last_run_at: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
 }
 
 /// 环境变量
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EnvVariable {
     pub id: Option<i64>,
-    pub server_id: i64,
+    /// 为空表示全局变量，同名时 Server 变量优先
+    pub server_id: Option<i64>,
     pub name: String,
+    /// 敏感变量在保险库锁定时为空
     pub value: String,
     pub description: Option<String>,
+    /// 是否为敏感变量（加密存储）
+    #[serde(default)]
+    pub secret: bool,
     pub created_at: Option<String>,
     pub updated_at: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateEnvVariableRequest {
-    pub server_id: i64,
+    /// 为空表示创建全局变量
+    #[serde(default)]
+    pub server_id: Option<i64>,
     pub name: String,
     pub value: String,
     pub description: Option<String>,
+    #[serde(default)]
+    pub secret: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -175,4 +494,5 @@ pub struct UpdateEnvVariableRequest {
     pub name: Option<String>,
     pub value: Option<String>,
     pub description: Option<String>,
-}
\ No newline at end of file
+    pub secret: Option<bool>,
+}
@@ -0,0 +1,257 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use parking_lot::Mutex;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+/// 加密字段的前缀，格式为 `vault:v1:<base64(nonce || ciphertext)>`
+const SEALED_PREFIX: &str = "vault:v1:";
+/// 用于校验主密码的明文
+const VERIFIER: &str = "mqtt-vault";
+const NONCE_LEN: usize = 12;
+const SALT_LEN: usize = 16;
+/// 主密码最短长度
+pub const MIN_PASSPHRASE_LEN: usize = 8;
+/// 导出时替换敏感内容的占位符
+pub const REDACTED: &str = "******";
+/// 参与脱敏的敏感值最短长度，过短的值容易误替换普通内容
+pub const MIN_REDACT_LEN: usize = 6;
+
+/// 密钥派生参数（Argon2id），随保险库一起保存
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            m_cost: Params::DEFAULT_M_COST,
+            t_cost: Params::DEFAULT_T_COST,
+            p_cost: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+/// 保险库状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultStatus {
+    /// 是否已设置主密码
+    pub initialized: bool,
+    pub unlocked: bool,
+}
+
+/// 保存在数据库中的保险库配置
+pub struct VaultConfig {
+    pub salt: Vec<u8>,
+    pub params: KdfParams,
+    /// 用派生密钥加密的 VERIFIER
+    pub verifier: String,
+}
+
+/// 敏感字段的加解密，解锁后密钥只保存在内存中
+#[derive(Default)]
+pub struct Vault {
+    cipher: Mutex<Option<Aes256Gcm>>,
+}
+
+impl Vault {
+    pub fn is_unlocked(&self) -> bool {
+        self.cipher.lock().is_some()
+    }
+
+    pub fn lock(&self) {
+        *self.cipher.lock() = None;
+    }
+
+    /// 使用主密码解锁，密码错误时返回错误
+    pub fn unlock(&self, passphrase: &str, config: &VaultConfig) -> Result<(), String> {
+        let cipher = derive_cipher(passphrase, &config.salt, &config.params)?;
+        open(&cipher, &config.verifier).map_err(|_| "Incorrect passphrase".to_string())?;
+        *self.cipher.lock() = Some(cipher);
+        Ok(())
+    }
+
+    /// 设置新的主密码，返回需要保存的配置
+    pub fn create(passphrase: &str) -> Result<(Aes256Gcm, VaultConfig), String> {
+        if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+            return Err(format!(
+                "Passphrase must be at least {} characters",
+                MIN_PASSPHRASE_LEN
+            ));
+        }
+        let mut salt = vec![0u8; SALT_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let params = KdfParams::default();
+        let cipher = derive_cipher(passphrase, &salt, &params)?;
+        let verifier = seal_with(&cipher, VERIFIER)?;
+        Ok((
+            cipher,
+            VaultConfig {
+                salt,
+                params,
+                verifier,
+            },
+        ))
+    }
+
+    /// 替换当前密钥（设置或修改主密码后）
+    pub fn set_cipher(&self, cipher: Aes256Gcm) {
+        *self.cipher.lock() = Some(cipher);
+    }
+
+    /// 加密字段值，保险库锁定时返回错误
+    pub fn seal(&self, value: &str) -> Result<String, String> {
+        let cipher = self.cipher.lock();
+        let cipher = cipher.as_ref().ok_or("Vault is locked")?;
+        seal_with(cipher, value)
+    }
+
+    /// 解密字段值；未加密的值原样返回，锁定或无法解密时返回 None
+    pub fn reveal(&self, value: &str) -> Option<String> {
+        if !is_sealed(value) {
+            return Some(value.to_string());
+        }
+        let cipher = self.cipher.lock();
+        open(cipher.as_ref()?, value).ok()
+    }
+}
+
+/// 字段值是否为加密内容
+pub fn is_sealed(value: &str) -> bool {
+    value.starts_with(SEALED_PREFIX)
+}
+
+/// 将文本中出现的敏感值替换为占位符
+///
+/// 只替换前后不紧邻字母或数字的完整出现，短于 MIN_REDACT_LEN 的敏感值不参与替换。
+pub fn redact(text: &str, secrets: &[String]) -> String {
+    secrets
+        .iter()
+        .filter(|secret| secret.chars().count() >= MIN_REDACT_LEN)
+        .fold(text.to_string(), |text, secret| redact_one(&text, secret))
+}
+
+fn redact_one(text: &str, secret: &str) -> String {
+    let is_word = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+    let mut result = String::with_capacity(text.len());
+    let mut last = 0;
+    for (start, _) in text.match_indices(secret) {
+        let end = start + secret.len();
+        // 处在更长的单词中间时保留原文
+        if is_word(text[..start].chars().next_back()) || is_word(text[end..].chars().next()) {
+            continue;
+        }
+        result.push_str(&text[last..start]);
+        result.push_str(REDACTED);
+        last = end;
+    }
+    result.push_str(&text[last..]);
+    result
+}
+
+fn derive_cipher(passphrase: &str, salt: &[u8], params: &KdfParams) -> Result<Aes256Gcm, String> {
+    let params = Params::new(params.m_cost, params.t_cost, params.p_cost, Some(32))
+        .map_err(|e| format!("Invalid key derivation parameters: {}", e))?;
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Key derivation failed: {}", e))?;
+    Aes256Gcm::new_from_slice(&key).map_err(|e| e.to_string())
+}
+
+/// 使用指定密钥加密字段值
+pub fn seal_with(cipher: &Aes256Gcm, plain: &str) -> Result<String, String> {
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plain.as_bytes())
+        .map_err(|e| format!("Encryption failed: {}", e))?;
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    Ok(format!("{}{}", SEALED_PREFIX, STANDARD.encode(sealed)))
+}
+
+fn open(cipher: &Aes256Gcm, value: &str) -> Result<String, String> {
+    let encoded = value
+        .strip_prefix(SEALED_PREFIX)
+        .ok_or("Value is not sealed")?;
+    let sealed = STANDARD.decode(encoded).map_err(|e| e.to_string())?;
+    if sealed.len() < NONCE_LEN {
+        return Err("Sealed value is too short".to_string());
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let plain = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Decryption failed".to_string())?;
+    String::from_utf8(plain).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unlocked(passphrase: &str) -> (Vault, VaultConfig) {
+        let (cipher, config) = Vault::create(passphrase).unwrap();
+        let vault = Vault::default();
+        vault.set_cipher(cipher);
+        (vault, config)
+    }
+
+    #[test]
+    fn seals_and_reveals_values() {
+        let (vault, _) = unlocked("correct horse");
+        let sealed = vault.seal("s3cret-密码").unwrap();
+
+        assert!(is_sealed(&sealed));
+        assert!(!sealed.contains("s3cret"));
+        assert_ne!(vault.seal("s3cret-密码").unwrap(), sealed);
+        assert_eq!(vault.reveal(&sealed).as_deref(), Some("s3cret-密码"));
+        // 未加密的旧数据原样返回
+        assert_eq!(vault.reveal("plain").as_deref(), Some("plain"));
+    }
+
+    #[test]
+    fn locked_vault_cannot_seal_or_reveal() {
+        let (vault, config) = unlocked("correct horse");
+        let sealed = vault.seal("value").unwrap();
+        vault.lock();
+
+        assert!(!vault.is_unlocked());
+        assert_eq!(vault.seal("value").unwrap_err(), "Vault is locked");
+        assert_eq!(vault.reveal(&sealed), None);
+        assert_eq!(vault.reveal("plain").as_deref(), Some("plain"));
+
+        vault.unlock("correct horse", &config).unwrap();
+        assert_eq!(vault.reveal(&sealed).as_deref(), Some("value"));
+    }
+
+    #[test]
+    fn rejects_wrong_or_short_passphrase() {
+        let (vault, config) = unlocked("correct horse");
+        vault.lock();
+
+        assert_eq!(
+            vault.unlock("wrong horse", &config).unwrap_err(),
+            "Incorrect passphrase"
+        );
+        assert!(!vault.is_unlocked());
+        assert!(Vault::create("short").is_err());
+    }
+
+    #[test]
+    fn rejects_tampered_values() {
+        let (vault, _) = unlocked("correct horse");
+        let (other, _) = unlocked("another passphrase");
+        let sealed = vault.seal("value").unwrap();
+
+        assert_eq!(other.reveal(&sealed), None);
+        assert_eq!(vault.reveal("vault:v1:AAAA"), None);
+        assert_eq!(vault.reveal("vault:v1:not base64"), None);
+    }
+}
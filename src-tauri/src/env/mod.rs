@@ -0,0 +1,72 @@
+use rand::Rng;
+use regex::{Captures, NoExpand, Regex};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use crate::db::Storage;
+
+/// `{{name}}`、`{{$name}}` 或 `{{$name(args)}}` 格式的占位符
+static PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\{\{\s*(\$?\w+)\s*(?:\(([^()]*)\))?\s*\}\}").expect("invalid placeholder pattern")
+});
+
+/// Server 可用的环境变量：全局变量 + Server 变量，同名时 Server 变量优先
+///
+/// 保险库锁定时跳过敏感变量，对应的占位符保持原样。
+pub fn variables(storage: &Storage, server_id: i64) -> HashMap<String, String> {
+    let unlocked = storage.vault_status().unlocked;
+    storage
+        .get_global_env_variables()
+        .into_iter()
+        .chain(storage.get_env_variables(server_id))
+        .filter(|v| unlocked || !v.secret)
+        .map(|v| (v.name, v.value))
+        .collect()
+}
+
+/// 替换文本中的环境变量和内置动态变量，无法识别的占位符保持原样
+///
+/// 内置变量：`{{$timestamp}}`（秒）、`{{$timestampMs}}`、`{{$isoTimestamp}}`、
+/// `{{$uuid}}`、`{{$randomInt(min, max)}}`（含两端）。
+pub fn substitute(text: &str, variables: &HashMap<String, String>) -> String {
+    if !text.contains("{{") {
+        return text.to_string();
+    }
+
+    PLACEHOLDER
+        .replace_all(text, |caps: &Captures| {
+            let name = &caps[1];
+            let args = caps.get(2).map(|m| m.as_str());
+            let value = match name.strip_prefix('$') {
+                Some(builtin) => dynamic_variable(builtin, args),
+                None if args.is_none() => variables.get(name).cloned(),
+                None => None,
+            };
+            value.unwrap_or_else(|| caps[0].to_string())
+        })
+        .into_owned()
+}
+
+/// 将所有占位符替换为固定内容，用于在变量替换前检查负载格式
+pub fn mask_placeholders(text: &str, replacement: &str) -> String {
+    PLACEHOLDER
+        .replace_all(text, NoExpand(replacement))
+        .into_owned()
+}
+
+fn dynamic_variable(name: &str, args: Option<&str>) -> Option<String> {
+    let now = chrono::Utc::now();
+    match (name, args) {
+        ("timestamp", None) => Some(now.timestamp().to_string()),
+        ("timestampMs", None) => Some(now.timestamp_millis().to_string()),
+        ("isoTimestamp", None) => Some(now.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)),
+        ("uuid", None) => Some(uuid::Uuid::new_v4().to_string()),
+        ("randomInt", Some(args)) => {
+            let (min, max) = args.split_once(',')?;
+            let min: i64 = min.trim().parse().ok()?;
+            let max: i64 = max.trim().parse().ok()?;
+            (min <= max).then(|| rand::thread_rng().gen_range(min..=max).to_string())
+        }
+        _ => None,
+    }
+}
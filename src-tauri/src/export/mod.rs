@@ -0,0 +1,200 @@
+pub mod replay;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::db::models::{MessageHistory, MessageQuery};
+use crate::db::{vault, Storage};
+use replay::{ReplayRecord, ReplayWriter};
+
+/// 每次从数据库读取的消息数
+const EXPORT_BATCH_SIZE: usize = 1000;
+
+/// 历史消息导出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Ndjson,
+    Replay,
+}
+
+impl ExportFormat {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "csv" => Ok(Self::Csv),
+            "ndjson" | "jsonl" => Ok(Self::Ndjson),
+            "replay" | "mqrp" => Ok(Self::Replay),
+            other => Err(format!("Unsupported export format: {}", other)),
+        }
+    }
+}
+
+/// 历史消息导出结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportSummary {
+    pub exported: usize,
+    /// 要求脱敏但未能脱敏时的提示（例如保险库已锁定）
+    pub warning: Option<String>,
+}
+
+/// 将符合查询条件的历史消息按时间正序导出到文件
+///
+/// 查询条件中的 cursor 和 limit 会被忽略。redact 为 true 时替换主题和内容中的敏感值，
+/// 保险库锁定时跳过脱敏并返回提示。
+pub fn export_messages(
+    storage: &Storage,
+    query: &MessageQuery,
+    format: ExportFormat,
+    path: &Path,
+    redact: bool,
+) -> Result<ExportSummary, String> {
+    let mut warning = None;
+    let redactor = match redact {
+        true => Redactor::for_server(storage, query.server_id)
+            .map_err(|e| warning = Some(e))
+            .ok(),
+        false => None,
+    };
+
+    let file = File::create(path).map_err(|e| format!("Failed to create export file: {}", e))?;
+    let mut sink = ExportSink::new(format, BufWriter::new(file))?;
+
+    let mut query = MessageQuery {
+        cursor: None,
+        limit: Some(EXPORT_BATCH_SIZE),
+        ascending: true,
+        ..query.clone()
+    };
+
+    let mut exported = 0;
+    loop {
+        let page = storage.search_messages(&query)?;
+        for message in &page.messages {
+            match &redactor {
+                Some(redactor) => sink.write(&redactor.redacted(message))?,
+                None => sink.write(message)?,
+            }
+        }
+        exported += page.messages.len();
+
+        match page.next_cursor {
+            Some(cursor) => query.cursor = Some(cursor),
+            None => break,
+        }
+    }
+
+    sink.finish()?;
+    Ok(ExportSummary { exported, warning })
+}
+
+/// 导出时将凭据和敏感变量的值替换为占位符
+///
+/// 只处理主题和文本内容，原始字节保持不变，保证导出的消息可以按原样回放。
+pub(crate) struct Redactor {
+    secrets: Vec<String>,
+}
+
+impl Redactor {
+    /// 读取 Server 的敏感值；保险库锁定时无法得知加密内容，返回提示
+    pub(crate) fn for_server(storage: &Storage, server_id: i64) -> Result<Self, String> {
+        storage
+            .secret_values(server_id)
+            .map(|secrets| Self { secrets })
+            .map_err(|_| "Vault is locked, secrets in message history were not redacted".to_string())
+    }
+
+    pub(crate) fn redacted<'a>(&self, message: &'a MessageHistory) -> Cow<'a, MessageHistory> {
+        let topic = vault::redact(&message.topic, &self.secrets);
+        let payload = message
+            .payload
+            .as_deref()
+            .map(|p| vault::redact(p, &self.secrets));
+        if topic == message.topic && payload.as_deref() == message.payload.as_deref() {
+            return Cow::Borrowed(message);
+        }
+
+        let mut redacted = message.clone();
+        // 先固定原始字节，避免由脱敏后的文本重新生成
+        redacted.raw_payload = Some(message.payload_bytes());
+        redacted.topic = topic;
+        redacted.payload = payload;
+        Cow::Owned(redacted)
+    }
+}
+
+enum ExportSink<W: Write> {
+    Csv(Box<csv::Writer<W>>),
+    Ndjson(W),
+    Replay(ReplayWriter<W>),
+}
+
+impl<W: Write> ExportSink<W> {
+    fn new(format: ExportFormat, writer: W) -> Result<Self, String> {
+        Ok(match format {
+            ExportFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(writer);
+                writer
+                    .write_record([
+                        "id",
+                        "server_id",
+                        "direction",
+                        "topic",
+                        "qos",
+                        "retain",
+                        "payload_format",
+                        "payload",
+                        "payload_base64",
+                        "delivery_state",
+                        "latency_ms",
+                        "created_at",
+                    ])
+                    .map_err(|e| e.to_string())?;
+                Self::Csv(Box::new(writer))
+            }
+            ExportFormat::Ndjson => Self::Ndjson(writer),
+            ExportFormat::Replay => Self::Replay(ReplayWriter::new(writer)?),
+        })
+    }
+
+    fn write(&mut self, message: &MessageHistory) -> Result<(), String> {
+        match self {
+            Self::Csv(writer) => writer
+                .write_record([
+                    message.id.map(|v| v.to_string()).unwrap_or_default(),
+                    message.server_id.to_string(),
+                    message.direction.clone(),
+                    message.topic.clone(),
+                    message.qos.to_string(),
+                    message.retain.to_string(),
+                    message.payload_format.clone().unwrap_or_default(),
+                    message.payload.clone().unwrap_or_default(),
+                    STANDARD.encode(message.payload_bytes()),
+                    message.delivery_state.clone().unwrap_or_default(),
+                    message
+                        .latency_ms
+                        .map(|v| v.to_string())
+                        .unwrap_or_default(),
+                    message.created_at.clone().unwrap_or_default(),
+                ])
+                .map_err(|e| e.to_string()),
+            Self::Ndjson(writer) => {
+                serde_json::to_writer(&mut *writer, message).map_err(|e| e.to_string())?;
+                writer.write_all(b"\n").map_err(|e| e.to_string())
+            }
+            Self::Replay(writer) => writer.write(&ReplayRecord::from_message(message)),
+        }
+    }
+
+    fn finish(self) -> Result<(), String> {
+        match self {
+            Self::Csv(mut writer) => writer.flush().map_err(|e| e.to_string()),
+            Self::Ndjson(mut writer) => writer.flush().map_err(|e| e.to_string()),
+            Self::Replay(mut writer) => writer.flush(),
+        }
+    }
+}
@@ -0,0 +1,331 @@
+//! 二进制回放文件格式（.mqrp）
+//!
+//! 文件头：4 字节魔数 `MQRP` + u16 版本号 + u16 保留位。
+//! 之后是连续的记录，每条记录以 u32 长度开头（不含长度字段本身），内容依次为：
+//!
+//! | 字段 | 类型 |
+//! |------|------|
+//! | 时间戳（Unix 微秒） | i64 |
+//! | 标志位：bit0-1 QoS，bit2 retain，bit3 方向（1 为接收） | u8 |
+//! | 主题长度 + 主题 UTF-8 | u16 + bytes |
+//! | 内容长度 + 原始内容 | u32 + bytes |
+//! | 属性 JSON 长度 + 属性 JSON（无属性时长度为 0） | u32 + bytes |
+//!
+//! 所有整数均为大端序。
+
+use std::io::{ErrorKind, Read, Write};
+
+use crate::db::models::{MessageHistory, MessageProperties};
+
+pub const MAGIC: &[u8; 4] = b"MQRP";
+pub const VERSION: u16 = 1;
+
+const FLAG_RETAIN: u8 = 0b0100;
+const FLAG_RECEIVE: u8 = 0b1000;
+
+/// MQTT 协议允许的最大 payload（剩余长度字段的上限）
+const MAX_PAYLOAD_LEN: usize = 268_435_455;
+/// 属性 JSON 的最大长度
+const MAX_PROPERTIES_LEN: usize = 1024 * 1024;
+/// 单条记录的最大长度：固定字段 + 最长主题 + 最大 payload + 最大属性
+const MAX_RECORD_LEN: usize = 19 + u16::MAX as usize + MAX_PAYLOAD_LEN + MAX_PROPERTIES_LEN;
+
+/// 回放文件中的一条消息
+#[derive(Debug, Clone)]
+pub struct ReplayRecord {
+    /// 原始时间（Unix 微秒）
+    pub timestamp_us: i64,
+    pub direction: String,
+    pub topic: String,
+    pub payload: Vec<u8>,
+    pub qos: u8,
+    pub retain: bool,
+    pub properties: Option<MessageProperties>,
+}
+
+impl ReplayRecord {
+    pub fn from_message(message: &MessageHistory) -> Self {
+        let timestamp_us = message
+            .created_at
+            .as_deref()
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.timestamp_micros())
+            .unwrap_or_default();
+
+        Self {
+            timestamp_us,
+            direction: message.direction.clone(),
+            topic: message.topic.clone(),
+            payload: message.payload_bytes(),
+            qos: message.qos.clamp(0, 2) as u8,
+            retain: message.retain,
+            properties: message.properties.clone(),
+        }
+    }
+}
+
+/// 回放文件写入器
+pub struct ReplayWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> ReplayWriter<W> {
+    pub fn new(mut inner: W) -> Result<Self, String> {
+        inner.write_all(MAGIC).map_err(|e| e.to_string())?;
+        inner
+            .write_all(&VERSION.to_be_bytes())
+            .map_err(|e| e.to_string())?;
+        inner.write_all(&[0, 0]).map_err(|e| e.to_string())?;
+        Ok(Self { inner })
+    }
+
+    pub fn write(&mut self, record: &ReplayRecord) -> Result<(), String> {
+        let topic = record.topic.as_bytes();
+        let topic_len = u16::try_from(topic.len()).map_err(|_| "Topic too long")?;
+        if record.payload.len() > MAX_PAYLOAD_LEN {
+            return Err("Payload too large".to_string());
+        }
+        let payload_len = record.payload.len() as u32;
+        let properties = match &record.properties {
+            Some(p) if !p.is_empty() => serde_json::to_vec(p).map_err(|e| e.to_string())?,
+            _ => Vec::new(),
+        };
+        if properties.len() > MAX_PROPERTIES_LEN {
+            return Err("Message properties too large".to_string());
+        }
+
+        let mut flags = record.qos & 0b11;
+        if record.retain {
+            flags |= FLAG_RETAIN;
+        }
+        if record.direction == "receive" {
+            flags |= FLAG_RECEIVE;
+        }
+
+        let mut buf =
+            Vec::with_capacity(19 + topic.len() + record.payload.len() + properties.len());
+        buf.extend_from_slice(&record.timestamp_us.to_be_bytes());
+        buf.push(flags);
+        buf.extend_from_slice(&topic_len.to_be_bytes());
+        buf.extend_from_slice(topic);
+        buf.extend_from_slice(&payload_len.to_be_bytes());
+        buf.extend_from_slice(&record.payload);
+        buf.extend_from_slice(&(properties.len() as u32).to_be_bytes());
+        buf.extend_from_slice(&properties);
+
+        let record_len = u32::try_from(buf.len()).map_err(|_| "Record too large")?;
+        self.inner
+            .write_all(&record_len.to_be_bytes())
+            .map_err(|e| e.to_string())?;
+        self.inner.write_all(&buf).map_err(|e| e.to_string())
+    }
+
+    pub fn flush(&mut self) -> Result<(), String> {
+        self.inner.flush().map_err(|e| e.to_string())
+    }
+}
+
+/// 回放文件读取器
+pub struct ReplayReader<R: Read> {
+    inner: R,
+}
+
+impl<R: Read> ReplayReader<R> {
+    pub fn new(mut inner: R) -> Result<Self, String> {
+        let mut header = [0u8; 8];
+        inner
+            .read_exact(&mut header)
+            .map_err(|_| "Not a replay file".to_string())?;
+        if &header[..4] != MAGIC {
+            return Err("Not a replay file".to_string());
+        }
+        let version = u16::from_be_bytes([header[4], header[5]]);
+        if version != VERSION {
+            return Err(format!("Unsupported replay file version: {}", version));
+        }
+        Ok(Self { inner })
+    }
+
+    /// 读取下一条记录，文件结束时返回 None
+    pub fn next_record(&mut self) -> Result<Option<ReplayRecord>, String> {
+        let mut len = [0u8; 4];
+        let mut filled = 0;
+        while filled < len.len() {
+            match self.inner.read(&mut len[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err("Truncated replay file".to_string()),
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+        let len = u32::from_be_bytes(len) as usize;
+        if len > MAX_RECORD_LEN {
+            return Err(format!("Replay record too large: {} bytes", len));
+        }
+
+        // 按实际读到的数据增长缓冲区，截断的文件不会按声明的长度分配内存
+        let mut buf = Vec::new();
+        (&mut self.inner)
+            .take(len as u64)
+            .read_to_end(&mut buf)
+            .map_err(|e| e.to_string())?;
+        if buf.len() < len {
+            return Err("Truncated replay record".to_string());
+        }
+
+        let mut cursor = RecordCursor { buf: &buf, pos: 0 };
+        let timestamp_us = i64::from_be_bytes(cursor.take(8)?.try_into().unwrap_or_default());
+        let flags = cursor.take(1)?[0];
+        let topic_len = u16::from_be_bytes(cursor.take(2)?.try_into().unwrap_or_default());
+        let topic = String::from_utf8(cursor.take(topic_len as usize)?.to_vec())
+            .map_err(|_| "Invalid topic in replay record".to_string())?;
+        let payload_len = u32::from_be_bytes(cursor.take(4)?.try_into().unwrap_or_default());
+        let payload = cursor.take(payload_len as usize)?.to_vec();
+        let properties_len = u32::from_be_bytes(cursor.take(4)?.try_into().unwrap_or_default());
+        let properties = match properties_len {
+            0 => None,
+            len => Some(
+                serde_json::from_slice(cursor.take(len as usize)?)
+                    .map_err(|e| format!("Invalid properties in replay record: {}", e))?,
+            ),
+        };
+
+        Ok(Some(ReplayRecord {
+            timestamp_us,
+            direction: if flags & FLAG_RECEIVE != 0 {
+                "receive".to_string()
+            } else {
+                "publish".to_string()
+            },
+            topic,
+            payload,
+            qos: flags & 0b11,
+            retain: flags & FLAG_RETAIN != 0,
+            properties,
+        }))
+    }
+}
+
+struct RecordCursor<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> RecordCursor<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.buf.len())
+            .ok_or("Truncated replay record")?;
+        let slice = &self.buf[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(topic: &str, payload: &[u8]) -> ReplayRecord {
+        ReplayRecord {
+            timestamp_us: 1_700_000_000_000_000,
+            direction: "receive".to_string(),
+            topic: topic.to_string(),
+            payload: payload.to_vec(),
+            qos: 1,
+            retain: true,
+            properties: None,
+        }
+    }
+
+    fn write_file(records: &[ReplayRecord]) -> Vec<u8> {
+        let mut writer = ReplayWriter::new(Vec::new()).unwrap();
+        for record in records {
+            writer.write(record).unwrap();
+        }
+        writer.inner
+    }
+
+    #[test]
+    fn round_trip_preserves_records() {
+        let mut with_properties = record("a/b", &[0, 159, 146, 150, 255]);
+        with_properties.direction = "publish".to_string();
+        with_properties.qos = 2;
+        with_properties.retain = false;
+        with_properties.properties = Some(MessageProperties {
+            content_type: Some("application/octet-stream".to_string()),
+            ..Default::default()
+        });
+        let file = write_file(&[record("sensors/1", b"{\"t\":21}"), with_properties]);
+
+        let mut reader = ReplayReader::new(file.as_slice()).unwrap();
+        let first = reader.next_record().unwrap().unwrap();
+        assert_eq!(first.timestamp_us, 1_700_000_000_000_000);
+        assert_eq!(first.direction, "receive");
+        assert_eq!(first.topic, "sensors/1");
+        assert_eq!(first.payload, b"{\"t\":21}");
+        assert_eq!((first.qos, first.retain), (1, true));
+        assert!(first.properties.is_none());
+
+        let second = reader.next_record().unwrap().unwrap();
+        assert_eq!(second.direction, "publish");
+        assert_eq!(second.payload, [0, 159, 146, 150, 255]);
+        assert_eq!((second.qos, second.retain), (2, false));
+        assert_eq!(
+            second.properties.unwrap().content_type.as_deref(),
+            Some("application/octet-stream")
+        );
+
+        assert!(reader.next_record().unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(ReplayReader::new(&b"MQRX\0\x01\0\0"[..]).is_err());
+        assert!(ReplayReader::new(&b"MQRP\0\x02\0\0"[..]).is_err());
+        assert!(ReplayReader::new(&b"MQ"[..]).is_err());
+    }
+
+    #[test]
+    fn reports_truncated_length_prefix() {
+        let mut file = write_file(&[record("a", b"1")]);
+        file.extend_from_slice(&[0, 0]);
+
+        let mut reader = ReplayReader::new(file.as_slice()).unwrap();
+        assert!(reader.next_record().unwrap().is_some());
+        assert_eq!(reader.next_record().unwrap_err(), "Truncated replay file");
+    }
+
+    #[test]
+    fn reports_truncated_record() {
+        let mut file = write_file(&[record("a", b"payload")]);
+        file.truncate(file.len() - 3);
+
+        let mut reader = ReplayReader::new(file.as_slice()).unwrap();
+        assert_eq!(reader.next_record().unwrap_err(), "Truncated replay record");
+    }
+
+    #[test]
+    fn rejects_oversized_record_length() {
+        let mut file = write_file(&[]);
+        file.extend_from_slice(&u32::MAX.to_be_bytes());
+
+        let mut reader = ReplayReader::new(file.as_slice()).unwrap();
+        let error = reader.next_record().unwrap_err();
+        assert!(error.starts_with("Replay record too large"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_inner_length_past_record_end() {
+        let mut file = write_file(&[record("a", b"1")]);
+        // 把 payload 长度改得比记录本身更长
+        let payload_len_at = 8 + 4 + 8 + 1 + 2 + 1;
+        file[payload_len_at..payload_len_at + 4].copy_from_slice(&1000u32.to_be_bytes());
+
+        let mut reader = ReplayReader::new(file.as_slice()).unwrap();
+        assert_eq!(reader.next_record().unwrap_err(), "Truncated replay record");
+    }
+}
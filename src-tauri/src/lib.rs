@@ -1,20 +1,37 @@
 mod commands;
 mod db;
+mod env;
+mod export;
 mod log;
 mod mqtt;
+mod replay;
+mod retention;
+mod scheduler;
+mod script;
+mod workspace;
 
 use commands::env::*;
+use commands::export::*;
 use commands::log::*;
 use commands::mqtt::*;
 use commands::publish::*;
+use commands::replay::*;
+use commands::retention::*;
+use commands::schedule::*;
 use commands::script::*;
 use commands::server::*;
 use commands::settings::*;
 use commands::subscription::*;
 use commands::template::*;
+use commands::vault::*;
+use commands::workspace::*;
+use db::backup::{self, StorageRecovery};
 use db::Storage;
-use log::LogManager;
+use log::{LogEntry, LogManager};
 use mqtt::MqttManager;
+use replay::ReplayManager;
+use retention::RetentionManager;
+use scheduler::SchedulerManager;
 use tauri::Manager;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -24,20 +41,64 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .setup(|app| {
-            // 初始化存储
-            let storage =
-                Storage::new(&app.handle()).expect("Failed to initialize storage");
-            app.manage(storage);
+            // 初始化存储；数据文件损坏时以恢复模式启动，由前端选择备份恢复
+            let (file_path, legacy_path) = Storage::data_paths(app.handle())?;
+            match Storage::open(file_path.clone(), Some(&legacy_path)) {
+                Ok(storage) => {
+                    app.manage(storage);
+                }
+                Err(error) => {
+                    app.manage(StorageRecovery { file_path, error });
+                }
+            }
 
             // 初始化 MQTT 管理器
             let mqtt_manager = MqttManager::new(app.handle().clone());
             app.manage(mqtt_manager);
 
+            // 初始化回放管理器
+            let replay_manager = ReplayManager::new(app.handle().clone());
+            app.manage(replay_manager);
+
+            // 初始化定时发布管理器
+            let scheduler_manager = SchedulerManager::new(app.handle().clone());
+            app.manage(scheduler_manager);
+
             // 初始化日志管理器
             let log_manager =
                 LogManager::new(&app.handle()).expect("Failed to initialize log manager");
             app.manage(log_manager);
 
+            // 初始化消息保留策略并启动后台清理
+            let retention_manager = RetentionManager::new(&app.handle())
+                .expect("Failed to initialize retention manager");
+            app.manage(retention_manager);
+            RetentionManager::start(app.handle().clone());
+
+            // 后台备份数据文件，失败或进入恢复模式时写入日志
+            let app_handle = app.handle().clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                let Some(log_manager) = app_handle.try_state::<LogManager>() else {
+                    return;
+                };
+                let error = match app_handle.try_state::<Storage>() {
+                    Some(storage) => backup::rotate(&storage, false)
+                        .err()
+                        .map(|e| ("Failed to back up data file", e)),
+                    None => app_handle
+                        .try_state::<StorageRecovery>()
+                        .map(|recovery| ("Failed to open data file", recovery.error.clone())),
+                };
+                if let Some((message, details)) = error {
+                    let _ = log_manager.write_log(&LogEntry {
+                        r#type: "error".to_string(),
+                        message: message.to_string(),
+                        details: Some(details),
+                        timestamp: chrono::Local::now().to_rfc3339(),
+                    });
+                }
+            });
+
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -63,7 +124,24 @@ pub fn run() {
             // 消息命令
             publish_message,
             get_message_history,
+            search_message_history,
+            resend_message,
+            export_message_history,
+            // 回放命令
+            start_replay,
+            pause_replay,
+            resume_replay,
+            stop_replay,
+            // 定时发布命令
+            list_scheduled_jobs,
+            create_scheduled_job,
+            update_scheduled_job,
+            delete_scheduled_job,
+            start_scheduled_job,
+            stop_scheduled_job,
             clear_message_history,
+            apply_message_retention,
+            get_archive_dir,
             // 模板命令
             create_template,
             get_template,
@@ -79,6 +157,10 @@ pub fn run() {
             get_data_path,
             migrate_data_path,
             select_data_folder,
+            get_storage_status,
+            list_backups,
+            create_backup,
+            restore_backup,
             // 脚本命令
             list_scripts,
             get_script,
@@ -87,6 +169,7 @@ pub fn run() {
             update_script,
             delete_script,
             toggle_script,
+            test_script,
             // 日志命令
             write_error_log,
             get_recent_logs,
@@ -94,10 +177,23 @@ pub fn run() {
             clear_logs,
             // 环境变量命令
             list_env_variables,
+            list_global_env_variables,
             get_env_variable,
             create_env_variable,
             update_env_variable,
             delete_env_variable,
+            // 保险库命令
+            vault_status,
+            setup_vault,
+            unlock_vault,
+            lock_vault,
+            change_vault_passphrase,
+            // 工作区导入导出命令
+            export_workspace,
+            import_workspace,
+            export_server_config,
+            import_server_config,
+            import_external_profiles,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -2,7 +2,7 @@ use chrono::Local;
 use serde::{Deserialize, Serialize};
 use std::fs::{self, File, OpenOptions};
 use std::io::{BufRead, BufReader, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tauri::AppHandle;
 use tauri::Manager;
 
@@ -53,7 +53,8 @@ impl LogManager {
         if log_file.exists() {
             if let Ok(metadata) = fs::metadata(&log_file) {
                 if metadata.len() > self.max_file_size {
-                    self.rotate_log_file(&log_file)?;
+                    rotate_file(&log_file, "log")
+                        .map_err(|e| format!("Failed to rotate log file: {}", e))?;
                 }
             }
         }
@@ -82,49 +83,7 @@ impl LogManager {
             .map_err(|e| format!("Failed to write log: {}", e))?;
 
         // 清理旧日志文件
-        self.cleanup_old_logs()?;
-
-        Ok(())
-    }
-
-    /// 轮转日志文件
-    fn rotate_log_file(&self, log_file: &PathBuf) -> Result<(), String> {
-        let timestamp = Local::now().format("%Y-%m-%d_%H%M%S").to_string();
-        let rotated_name = log_file.with_extension(format!("{}.log", timestamp));
-        fs::rename(log_file, rotated_name).map_err(|e| format!("Failed to rotate log file: {}", e))
-    }
-
-    /// 清理旧日志文件
-    fn cleanup_old_logs(&self) -> Result<(), String> {
-        let mut log_files: Vec<_> = fs::read_dir(&self.log_dir)
-            .map_err(|e| e.to_string())?
-            .filter_map(|entry| entry.ok())
-            .filter(|entry| {
-                entry
-                    .path()
-                    .extension()
-                    .map(|ext| ext == "log")
-                    .unwrap_or(false)
-            })
-            .collect();
-
-        if log_files.len() <= self.max_log_files {
-            return Ok(());
-        }
-
-        // 按修改时间排序
-        log_files.sort_by_key(|entry| {
-            entry
-                .metadata()
-                .and_then(|m| m.modified())
-                .unwrap_or(std::time::SystemTime::UNIX_EPOCH)
-        });
-
-        // 删除最旧的文件
-        let to_delete = log_files.len() - self.max_log_files;
-        for entry in log_files.into_iter().take(to_delete) {
-            let _ = fs::remove_file(entry.path());
-        }
+        cleanup_old_files(&self.log_dir, "log", self.max_log_files)?;
 
         Ok(())
     }
@@ -172,3 +131,45 @@ impl LogManager {
         Ok(())
     }
 }
+
+/// 轮转文件：重命名为带时间戳的文件名，扩展名保持为 extension
+pub(crate) fn rotate_file(file: &Path, extension: &str) -> std::io::Result<()> {
+    let timestamp = Local::now().format("%Y-%m-%d_%H%M%S").to_string();
+    let rotated_name = file.with_extension(format!("{}.{}", timestamp, extension));
+    fs::rename(file, rotated_name)
+}
+
+/// 清理目录中扩展名为 extension 的旧文件，按修改时间保留最新的 max_files 个
+fn cleanup_old_files(dir: &Path, extension: &str, max_files: usize) -> Result<(), String> {
+    let mut files: Vec<_> = fs::read_dir(dir)
+        .map_err(|e| e.to_string())?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .path()
+                .extension()
+                .map(|ext| ext == extension)
+                .unwrap_or(false)
+        })
+        .collect();
+
+    if files.len() <= max_files {
+        return Ok(());
+    }
+
+    // 按修改时间排序
+    files.sort_by_key(|entry| {
+        entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .unwrap_or(std::time::SystemTime::UNIX_EPOCH)
+    });
+
+    // 删除最旧的文件
+    let to_delete = files.len() - max_files;
+    for entry in files.into_iter().take(to_delete) {
+        let _ = fs::remove_file(entry.path());
+    }
+
+    Ok(())
+}
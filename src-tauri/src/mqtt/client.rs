@@ -1,19 +1,35 @@
-use parking_lot::RwLock;
-use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, Packet, QoS, Transport};
+use parking_lot::{Mutex, RwLock};
+use rand::Rng;
+use rumqttc::Transport;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
-use tauri::{AppHandle, Emitter};
-use tokio::sync::mpsc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::{mpsc, oneshot};
 
-use crate::db::models::MqttServer;
+use super::link::{self, IncomingPublish, LinkEvent, LinkEventLoop, MqttLink};
+use super::tracker::{
+    Delivery, PendingPublish, PendingSubscribe, PendingUnsubscribe, TopicGrant, Trackers,
+};
+use crate::db::models::{
+    MessageHistory, MessageProperties, MqttServer, PublishPayload, ReconnectPolicy,
+    SubscriptionGrant,
+};
+use crate::db::Storage;
+use crate::env;
+use crate::log::{LogEntry, LogManager};
+use crate::scheduler::SchedulerManager;
+use crate::script::{self, hooks};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConnectionState {
     pub server_id: i64,
-    pub status: String, // "disconnected", "connecting", "connected", "error"
+    pub status: String, // "disconnected", "connecting", "connected", "reconnecting", "error"
     pub error: Option<String>,
+    /// 当前重连次数（仅 "reconnecting" 状态）
+    pub attempt: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,14 +39,75 @@ pub struct ReceivedMessage {
     pub payload: Vec<u8>,
     pub qos: u8,
     pub retain: bool,
+    /// MQTT 5 消息属性（仅 5.0 连接）
+    pub properties: Option<MessageProperties>,
+    /// 接收后处理脚本的输出（未配置脚本时为 None）
+    pub processed_payload: Option<String>,
+    /// 接收后处理脚本的错误
+    pub script_error: Option<String>,
+    /// 已保存的历史记录 ID（未开启保存时为 None）
+    pub message_id: Option<i64>,
     pub timestamp: String,
 }
 
+/// 订阅结果事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscriptionResult {
+    pub server_id: i64,
+    pub source: String, // "subscribe" | "resubscribe"
+    pub grants: Vec<TopicGrant>,
+}
+
+/// 投递状态事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeliveryStatus {
+    pub server_id: i64,
+    pub message_id: Option<i64>,
+    pub topic: String,
+    pub qos: u8,
+    pub status: String, // "queued" | "sent" | "acked" | "failed"
+    pub latency_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// 待发布的消息
+#[derive(Debug, Clone)]
+pub struct OutgoingMessage {
+    pub topic: String,
+    pub payload: Vec<u8>,
+    pub qos: u8,
+    pub retain: bool,
+    pub properties: Option<MessageProperties>,
+}
+
 struct ClientHandle {
-    client: AsyncClient,
+    /// 连接序号，用于避免旧事件循环清理掉新连接
+    conn_id: u64,
+    client: MqttLink,
+    trackers: Arc<Mutex<Trackers>>,
+    /// 保证发布请求的登记顺序与提交顺序一致
+    publish_lock: Arc<tokio::sync::Mutex<()>>,
     shutdown_tx: mpsc::Sender<()>,
 }
 
+static NEXT_CONN_ID: AtomicU64 = AtomicU64::new(1);
+
+/// 等待 broker 确认报文的超时时间
+const ACK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 事件循环运行所需的上下文
+struct EventLoopContext {
+    server_id: i64,
+    conn_id: u64,
+    client: MqttLink,
+    trackers: Arc<Mutex<Trackers>>,
+    policy: ReconnectPolicy,
+    app_handle: AppHandle,
+    clients: Arc<RwLock<HashMap<i64, ClientHandle>>>,
+    /// 收到的消息交给独立任务处理，脚本执行不阻塞事件循环
+    incoming_tx: mpsc::UnboundedSender<IncomingPublish>,
+}
+
 pub struct MqttManager {
     clients: Arc<RwLock<HashMap<i64, ClientHandle>>>,
     app_handle: AppHandle,
@@ -54,126 +131,221 @@ impl MqttManager {
         self.emit_state(server_id, "connecting", None);
 
         // 构建 MQTT 配置
-        let client_id = server.client_id.unwrap_or_else(|| {
+        let client_id = server.client_id.clone().unwrap_or_else(|| {
             format!("mqtt_client_{}", uuid::Uuid::new_v4())
         });
 
-        let mut options = MqttOptions::new(client_id, &server.host, server.port as u16);
-        options.set_keep_alive(Duration::from_secs(server.keep_alive as u64));
-        options.set_clean_session(server.clean_session);
-
-        if let (Some(username), Some(password)) = (server.username.as_ref(), server.password.as_ref())
-        {
-            if !username.is_empty() {
-                options.set_credentials(username, password);
-            }
-        }
-
         // 配置 TLS
-        if server.use_tls {
+        let transport = if server.use_tls {
             let tls_config = Self::build_tls_config(
                 server.ca_cert.as_deref(),
                 server.client_cert.as_deref(),
                 server.client_key.as_deref(),
                 server.client_key_password.as_deref(),
             )?;
-            options.set_transport(Transport::tls_with_config(tls_config));
-        }
+            Some(Transport::tls_with_config(tls_config))
+        } else {
+            None
+        };
 
-        // 创建客户端
-        let (client, eventloop) = AsyncClient::new(options, 100);
+        // 创建客户端（根据协议版本选择 v3.1.1 或 v5）
+        let (client, eventloop) = link::open(&server, client_id, transport)?;
 
         // 创建停止信号
         let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>(1);
 
         // 保存客户端句柄
+        let conn_id = NEXT_CONN_ID.fetch_add(1, Ordering::Relaxed);
+        let trackers = Arc::new(Mutex::new(Trackers::default()));
         {
             let mut clients = self.clients.write();
             clients.insert(
                 server_id,
                 ClientHandle {
+                    conn_id,
                     client: client.clone(),
+                    trackers: trackers.clone(),
+                    publish_lock: Arc::new(tokio::sync::Mutex::new(())),
                     shutdown_tx,
                 },
             );
         }
 
+        // 启动消息处理任务（按接收顺序执行脚本并保存）
+        let (incoming_tx, incoming_rx) = mpsc::unbounded_channel::<IncomingPublish>();
+        tokio::spawn(Self::process_incoming(
+            server_id,
+            self.app_handle.clone(),
+            incoming_rx,
+        ));
+
         // 启动事件循环
-        let app_handle = self.app_handle.clone();
-        let clients = self.clients.clone();
+        let ctx = EventLoopContext {
+            server_id,
+            conn_id,
+            client,
+            trackers,
+            policy: server.reconnect.clone(),
+            app_handle: self.app_handle.clone(),
+            clients: self.clients.clone(),
+            incoming_tx,
+        };
 
         tokio::spawn(async move {
-            Self::run_eventloop(server_id, eventloop, shutdown_rx, app_handle, clients).await;
+            Self::run_eventloop(ctx, eventloop, shutdown_rx).await;
         });
 
         Ok(())
     }
 
     async fn run_eventloop(
-        server_id: i64,
-        mut eventloop: EventLoop,
+        ctx: EventLoopContext,
+        mut eventloop: LinkEventLoop,
         mut shutdown_rx: mpsc::Receiver<()>,
-        app_handle: AppHandle,
-        clients: Arc<RwLock<HashMap<i64, ClientHandle>>>,
     ) {
+        let server_id = ctx.server_id;
+        let app_handle = &ctx.app_handle;
+        let policy = &ctx.policy;
         let mut connected = false;
+        let mut attempt: u32 = 0;
 
         loop {
             tokio::select! {
                 _ = shutdown_rx.recv() => {
-                    Self::emit_state_static(&app_handle, server_id, "disconnected", None);
+                    Self::emit_state_static(app_handle, server_id, "disconnected", None);
                     break;
                 }
                 event = eventloop.poll() => {
                     match event {
-                        Ok(Event::Incoming(Packet::ConnAck(ack))) => {
-                            if ack.code == rumqttc::ConnectReturnCode::Success {
+                        Ok(LinkEvent::ConnAck { success, reason }) => {
+                            if success {
                                 connected = true;
-                                Self::emit_state_static(&app_handle, server_id, "connected", None);
+                                attempt = 0;
+                                Self::emit_state_static(app_handle, server_id, "connected", None);
+                                Self::fail_pending_requests(&ctx, "Connection lost before acknowledgement", false);
+                                Self::resubscribe(&ctx);
+                                let hook_app_handle = app_handle.clone();
+                                tokio::spawn(async move {
+                                    hooks::run_connection_hooks(&hook_app_handle, server_id, "on_connect").await;
+                                });
                             } else {
                                 Self::emit_state_static(
-                                    &app_handle,
+                                    app_handle,
                                     server_id,
                                     "error",
-                                    Some(format!("Connection refused: {:?}", ack.code)),
+                                    Some(format!("Connection refused: {}", reason)),
                                 );
                                 break;
                             }
                         }
-                        Ok(Event::Incoming(Packet::Publish(publish))) => {
-                            let msg = ReceivedMessage {
-                                server_id,
-                                topic: publish.topic.clone(),
-                                payload: publish.payload.to_vec(),
-                                qos: publish.qos as u8,
-                                retain: publish.retain,
-                                timestamp: chrono::Utc::now().to_rfc3339(),
-                            };
-                            let _ = app_handle.emit("mqtt-message", msg);
+                        Ok(LinkEvent::Publish(publish)) => {
+                            let _ = ctx.incoming_tx.send(publish);
+                        }
+                        Ok(LinkEvent::DroppedPublish(reason)) => {
+                            if let Some(log_manager) = app_handle.try_state::<LogManager>() {
+                                let _ = log_manager.write_log(&LogEntry {
+                                    r#type: "error".to_string(),
+                                    message: format!(
+                                        "Dropped incoming message on server {}",
+                                        server_id
+                                    ),
+                                    details: Some(reason),
+                                    timestamp: chrono::Local::now().to_rfc3339(),
+                                });
+                            }
                         }
-                        Ok(Event::Incoming(Packet::SubAck(_))) => {
-                            // 订阅成功
+                        Ok(LinkEvent::OutgoingPublish(pkid)) => {
+                            let mut trackers = ctx.trackers.lock();
+                            // 重连后 rumqttc 会重发未确认的报文，此时 pkid 已在跟踪中
+                            if pkid == 0 || trackers.publish.in_flight_mut(pkid).is_none() {
+                                trackers.publish.on_outgoing(pkid);
+                                if let Some(request) = trackers.publish.in_flight_mut(pkid) {
+                                    request.sent_at = Some(Instant::now());
+                                }
+                                // QoS 0 写出即完成
+                                if pkid == 0 {
+                                    let request = trackers.publish.on_ack(0);
+                                    drop(trackers);
+                                    if let Some(request) = request {
+                                        Self::finish_publish(&ctx, request, Ok(()));
+                                    }
+                                }
+                            }
+                        }
+                        Ok(LinkEvent::PubAck { pkid, result })
+                        | Ok(LinkEvent::PubComp { pkid, result }) => {
+                            let request = ctx.trackers.lock().publish.on_ack(pkid);
+                            if let Some(request) = request {
+                                Self::finish_publish(&ctx, request, result);
+                            }
                         }
-                        Ok(Event::Incoming(Packet::PingResp)) => {
+                        Ok(LinkEvent::PubRec { pkid, result: Err(e) }) => {
+                            let request = ctx.trackers.lock().publish.on_ack(pkid);
+                            if let Some(request) = request {
+                                Self::finish_publish(&ctx, request, Err(e));
+                            }
+                        }
+                        Ok(LinkEvent::OutgoingSubscribe(pkid)) => {
+                            ctx.trackers.lock().subscribe.on_outgoing(pkid);
+                        }
+                        Ok(LinkEvent::SubAck { pkid, codes }) => {
+                            let request = ctx.trackers.lock().subscribe.on_ack(pkid);
+                            if let Some(request) = request {
+                                let grants = request.grants(codes);
+                                Self::finish_subscribe(&ctx, request, grants);
+                            }
+                        }
+                        Ok(LinkEvent::OutgoingUnsubscribe(pkid)) => {
+                            ctx.trackers.lock().unsubscribe.on_outgoing(pkid);
+                        }
+                        Ok(LinkEvent::UnsubAck { pkid, codes }) => {
+                            let request = ctx.trackers.lock().unsubscribe.on_ack(pkid);
+                            if let Some(request) = request {
+                                let result = codes.into_iter().next().unwrap_or(Ok(()));
+                                let _ = request.reply.send(result);
+                            }
+                        }
+                        Ok(LinkEvent::PingResp) => {
                             // Ping 响应
                         }
                         Err(e) => {
-                            if connected {
-                                Self::emit_state_static(
-                                    &app_handle,
-                                    server_id,
-                                    "error",
-                                    Some(format!("Connection error: {}", e)),
-                                );
+                            let error = if connected {
+                                format!("Connection error: {}", e.message)
                             } else {
-                                Self::emit_state_static(
-                                    &app_handle,
-                                    server_id,
-                                    "error",
-                                    Some(format!("Failed to connect: {}", e)),
-                                );
+                                format!("Failed to connect: {}", e.message)
+                            };
+                            connected = false;
+
+                            // 未被 broker 拒绝的连接自动重连（包括首次连接失败）
+                            let can_retry = policy.enabled
+                                && !e.refused
+                                && (policy.max_attempts == 0 || attempt < policy.max_attempts);
+                            if !can_retry {
+                                Self::emit_state_static(app_handle, server_id, "error", Some(error));
+                                // 放弃重连后停止该 Server 的定时任务，重新连接时恢复
+                                let current =
+                                    ctx.clients.read().get(&server_id).map(|h| h.conn_id);
+                                if current == Some(ctx.conn_id) {
+                                    if let Some(scheduler) =
+                                        app_handle.try_state::<SchedulerManager>()
+                                    {
+                                        scheduler.suspend_server(server_id);
+                                    }
+                                }
+                                break;
+                            }
+
+                            attempt += 1;
+                            Self::emit_reconnecting(app_handle, server_id, attempt, error);
+
+                            // 等待退避时间，期间可被断开操作打断
+                            tokio::select! {
+                                _ = shutdown_rx.recv() => {
+                                    Self::emit_state_static(app_handle, server_id, "disconnected", None);
+                                    break;
+                                }
+                                _ = tokio::time::sleep(Self::reconnect_delay(policy, attempt)) => {}
                             }
-                            break;
                         }
                         _ => {}
                     }
@@ -181,12 +353,315 @@ impl MqttManager {
             }
         }
 
-        // 清理客户端
-        let mut clients = clients.write();
-        clients.remove(&server_id);
+        Self::fail_pending_requests(&ctx, "Connection closed before acknowledgement", true);
+
+        // 清理客户端（如果已被新连接替换则保留）
+        let mut clients = ctx.clients.write();
+        if clients.get(&server_id).map(|h| h.conn_id) == Some(ctx.conn_id) {
+            clients.remove(&server_id);
+        }
+    }
+
+    /// 连接建立后重新订阅所有启用的订阅（单个 SUBSCRIBE 报文）
+    fn resubscribe(ctx: &EventLoopContext) {
+        let Some(storage) = ctx.app_handle.try_state::<Storage>() else {
+            return;
+        };
+
+        let topics: Vec<(String, u8)> = storage
+            .get_subscriptions(ctx.server_id)
+            .into_iter()
+            .filter(|s| s.is_active)
+            .map(|s| (s.topic, s.qos.clamp(0, 2) as u8))
+            .collect();
+        if topics.is_empty() {
+            return;
+        }
+
+        let request = PendingSubscribe {
+            source: "resubscribe",
+            topics,
+            reply: None,
+        };
+        if let Err((request, e)) = Self::send_subscribe(&ctx.client, &ctx.trackers, request) {
+            let grants = request.failed_grants(&e);
+            Self::finish_subscribe(ctx, request, grants);
+        }
+    }
+
+    /// 登记并发送 SUBSCRIBE，保证登记顺序与发送顺序一致
+    fn send_subscribe(
+        client: &MqttLink,
+        trackers: &Mutex<Trackers>,
+        request: PendingSubscribe,
+    ) -> Result<(), (PendingSubscribe, String)> {
+        let mut trackers = trackers.lock();
+        match client.try_subscribe_many(&request.topics) {
+            Ok(()) => {
+                trackers.subscribe.enqueue(request);
+                Ok(())
+            }
+            Err(e) => Err((request, e)),
+        }
+    }
+
+    /// 依次处理收到的消息：执行接收后处理脚本、保存历史记录并通知前端
+    async fn process_incoming(
+        server_id: i64,
+        app_handle: AppHandle,
+        mut incoming_rx: mpsc::UnboundedReceiver<IncomingPublish>,
+    ) {
+        while let Some(mut publish) = incoming_rx.recv().await {
+            let text = String::from_utf8_lossy(&publish.payload).into_owned();
+
+            // 自动回复不受消息过滤影响
+            hooks::respond(&app_handle, server_id, &publish.topic, &text).await;
+
+            let mut script_errors = Vec::new();
+            if let Some(storage) = app_handle.try_state::<Storage>() {
+                match hooks::apply_filters(&storage, server_id, &publish.topic, &text).await {
+                    Ok(Some(topic)) => publish.topic = topic,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        script::log_error(
+                            &app_handle,
+                            &format!("Message filter script failed on topic {}", publish.topic),
+                            &e,
+                        );
+                        script_errors.push(e);
+                    }
+                }
+            }
+
+            let processed_payload =
+                match Self::run_receive_scripts(&app_handle, server_id, &publish, text).await {
+                    Ok(processed) => processed,
+                    Err(e) => {
+                        script::log_error(
+                            &app_handle,
+                            &format!("After-receive script failed on topic {}", publish.topic),
+                            &e,
+                        );
+                        script_errors.push(e);
+                        None
+                    }
+                };
+            let script_error = Some(script_errors.join("; ")).filter(|e| !e.is_empty());
+
+            let message_id = Self::save_received(
+                &app_handle,
+                server_id,
+                &publish,
+                processed_payload.as_deref(),
+            );
+            let msg = ReceivedMessage {
+                server_id,
+                topic: publish.topic,
+                payload: publish.payload,
+                qos: publish.qos,
+                retain: publish.retain,
+                properties: publish.properties,
+                processed_payload,
+                script_error,
+                message_id,
+                timestamp: chrono::Utc::now().to_rfc3339(),
+            };
+            let _ = app_handle.emit("mqtt-message", msg);
+        }
+    }
+
+    /// 执行接收后处理脚本，未配置脚本时返回 None
+    async fn run_receive_scripts(
+        app_handle: &AppHandle,
+        server_id: i64,
+        publish: &IncomingPublish,
+        payload: String,
+    ) -> Result<Option<String>, String> {
+        let Some(storage) = app_handle.try_state::<Storage>() else {
+            return Ok(None);
+        };
+        let scripts = storage.get_enabled_scripts(server_id, "after_receive");
+        if scripts.is_empty() {
+            return Ok(None);
+        }
+
+        let env = env::variables(&storage, server_id);
+        script::run_scripts(scripts, payload, publish.topic.clone(), env)
+            .await
+            .map(Some)
+    }
+
+    /// 按服务器设置将收到的消息写入历史记录
+    ///
+    /// 经过脚本处理的消息保存处理后的文本，原始字节保存在 raw_payload 中。
+    fn save_received(
+        app_handle: &AppHandle,
+        server_id: i64,
+        publish: &IncomingPublish,
+        processed_payload: Option<&str>,
+    ) -> Option<i64> {
+        let storage = app_handle.try_state::<Storage>()?;
+        if !storage.get_server(server_id)?.save_received_messages {
+            return None;
+        }
+
+        // 非 UTF-8 内容以 HEX 保存
+        let (payload, format) = match processed_payload {
+            Some(text) => (text.to_string(), "text"),
+            None => match std::str::from_utf8(&publish.payload) {
+                Ok(text) => (text.to_string(), "text"),
+                Err(_) => (hex::encode(&publish.payload), "hex"),
+            },
+        };
+
+        let history = MessageHistory {
+            id: None,
+            server_id,
+            direction: "receive".to_string(),
+            topic: publish.topic.clone(),
+            payload: Some(payload),
+            payload_format: Some(format.to_string()),
+            raw_payload: Some(publish.payload.clone()),
+            qos: publish.qos as i32,
+            retain: publish.retain,
+            properties: publish.properties.clone(),
+            delivery_state: None,
+            latency_ms: None,
+            delivery_error: None,
+            created_at: None,
+        };
+
+        storage.create_message(history).ok().and_then(|m| m.id)
+    }
+
+    /// 订阅请求完成：保存授权结果、通知前端并回复调用方
+    fn finish_subscribe(
+        ctx: &EventLoopContext,
+        request: PendingSubscribe,
+        grants: Vec<TopicGrant>,
+    ) {
+        if let Some(storage) = ctx.app_handle.try_state::<Storage>() {
+            let now = chrono::Utc::now().to_rfc3339();
+            let records = grants
+                .iter()
+                .map(|g| {
+                    (
+                        g.topic.clone(),
+                        SubscriptionGrant {
+                            granted_qos: g.granted_qos.map(|q| q as i32),
+                            error: g.error.clone(),
+                            updated_at: Some(now.clone()),
+                        },
+                    )
+                })
+                .collect();
+            let _ = storage.update_subscription_grants(ctx.server_id, records);
+        }
+
+        Self::emit_subscription_result(
+            &ctx.app_handle,
+            ctx.server_id,
+            request.source,
+            grants.clone(),
+        );
+
+        if let Some(reply) = request.reply {
+            let _ = reply.send(grants);
+        }
+    }
+
+    /// 发布请求完成：更新历史记录的投递状态、通知前端并回复调用方
+    fn finish_publish(ctx: &EventLoopContext, request: PendingPublish, result: Result<(), String>) {
+        let delivery = request.delivery(result);
+
+        if let Some(message_id) = request.message_id {
+            if let Some(storage) = ctx.app_handle.try_state::<Storage>() {
+                let _ = storage.update_message_delivery(
+                    message_id,
+                    &delivery.status,
+                    delivery.latency_ms,
+                    delivery.error.clone(),
+                );
+            }
+        }
+
+        Self::emit_delivery_status(
+            &ctx.app_handle,
+            DeliveryStatus {
+                server_id: ctx.server_id,
+                message_id: request.message_id,
+                topic: request.topic,
+                qos: request.qos,
+                status: delivery.status.clone(),
+                latency_ms: delivery.latency_ms,
+                error: delivery.error.clone(),
+            },
+        );
+
+        let _ = request.reply.send(delivery);
+    }
+
+    /// 连接断开时，已发出但未确认的请求全部视为失败
+    ///
+    /// 发布请求在重连后会由 rumqttc 重发，因此只有事件循环退出时才标记失败。
+    fn fail_pending_requests(ctx: &EventLoopContext, reason: &str, include_publishes: bool) {
+        let (publishes, subscribes, unsubscribes) = {
+            let mut trackers = ctx.trackers.lock();
+            let publishes = if include_publishes {
+                trackers.publish.drain_all()
+            } else {
+                Vec::new()
+            };
+            (
+                publishes,
+                trackers.subscribe.drain_in_flight(),
+                trackers.unsubscribe.drain_in_flight(),
+            )
+        };
+
+        for request in publishes {
+            Self::finish_publish(ctx, request, Err(reason.to_string()));
+        }
+        for request in subscribes {
+            let grants = request.failed_grants(reason);
+            Self::finish_subscribe(ctx, request, grants);
+        }
+        for request in unsubscribes {
+            let _ = request.reply.send(Err(reason.to_string()));
+        }
+    }
+
+    /// 计算第 attempt 次重连前的等待时间
+    fn reconnect_delay(policy: &ReconnectPolicy, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(31);
+        let base = policy
+            .initial_delay_ms
+            .saturating_mul(1u64 << exponent)
+            .min(policy.max_delay_ms.max(policy.initial_delay_ms));
+
+        let jitter = policy.jitter.clamp(0.0, 1.0);
+        let factor = if jitter > 0.0 {
+            rand::thread_rng().gen_range(1.0 - jitter..=1.0 + jitter)
+        } else {
+            1.0
+        };
+
+        Duration::from_millis((base as f64 * factor) as u64)
     }
 
     pub async fn disconnect(&self, server_id: i64) -> Result<(), String> {
+        // 断开前执行 on_disconnect 脚本，并等待其发布的消息发送完成
+        if self.is_connected(server_id) {
+            let receivers =
+                hooks::run_connection_hooks(&self.app_handle, server_id, "on_disconnect").await;
+            let _ = tokio::time::timeout(ACK_TIMEOUT, async {
+                for receiver in receivers {
+                    let _ = receiver.await;
+                }
+            })
+            .await;
+        }
+
         let handle = {
             let clients = self.clients.read();
             clients.get(&server_id).map(|h| h.shutdown_tx.clone())
@@ -207,56 +682,224 @@ impl MqttManager {
         qos: u8,
         retain: bool,
     ) -> Result<(), String> {
-        let client = {
+        let message = OutgoingMessage {
+            topic,
+            payload,
+            qos,
+            retain,
+            properties: None,
+        };
+        self.publish_tracked(server_id, message, None)
+            .await
+            .map(|_| ())
+    }
+
+    /// 发布消息并跟踪投递结果
+    ///
+    /// 返回的接收端在收到 PUBACK / PUBCOMP（QoS 0 为写出）或失败时完成；
+    /// 提供 message_id 时，事件循环会同步更新该历史记录的投递状态。
+    pub async fn publish_tracked(
+        &self,
+        server_id: i64,
+        message: OutgoingMessage,
+        message_id: Option<i64>,
+    ) -> Result<oneshot::Receiver<Delivery>, String> {
+        let (client, trackers, publish_lock) = {
             let clients = self.clients.read();
-            clients.get(&server_id).map(|h| h.client.clone())
+            clients
+                .get(&server_id)
+                .map(|h| (h.client.clone(), h.trackers.clone(), h.publish_lock.clone()))
+                .ok_or("Not connected")?
         };
 
-        let client = client.ok_or("Not connected")?;
+        let _guard = publish_lock.lock().await;
 
-        let qos = match qos {
-            0 => QoS::AtMostOnce,
-            1 => QoS::AtLeastOnce,
-            2 => QoS::ExactlyOnce,
-            _ => return Err("Invalid QoS".to_string()),
-        };
+        let (reply_tx, reply_rx) = oneshot::channel();
+        trackers.lock().publish.enqueue(PendingPublish {
+            topic: message.topic.clone(),
+            qos: message.qos,
+            message_id,
+            sent_at: None,
+            reply: reply_tx,
+        });
 
-        client
-            .publish(topic, qos, retain, payload)
+        let topic = message.topic.clone();
+        if let Err(e) = client
+            .publish(
+                message.topic,
+                message.qos,
+                message.retain,
+                message.payload,
+                message.properties,
+            )
             .await
-            .map_err(|e| e.to_string())
+        {
+            trackers.lock().publish.cancel_last();
+            return Err(e);
+        }
+
+        Self::emit_delivery_status(
+            &self.app_handle,
+            DeliveryStatus {
+                server_id,
+                message_id,
+                topic,
+                qos: message.qos,
+                status: "queued".to_string(),
+                latency_ms: None,
+                error: None,
+            },
+        );
+
+        Ok(reply_rx)
     }
 
-    pub async fn subscribe(&self, server_id: i64, topic: String, qos: u8) -> Result<(), String> {
-        let client = {
-            let clients = self.clients.read();
-            clients.get(&server_id).map(|h| h.client.clone())
+    /// 不再等待历史消息的确认，之后到达的确认不会更新这条记录
+    ///
+    /// 返回 false 表示请求已经结束（确认已到达或连接已关闭）。
+    pub fn abandon_publish(&self, server_id: i64, message_id: i64) -> bool {
+        let Some(trackers) = self
+            .clients
+            .read()
+            .get(&server_id)
+            .map(|h| h.trackers.clone())
+        else {
+            return false;
         };
+        let mut trackers = trackers.lock();
+        match trackers
+            .publish
+            .find_mut(|request| request.message_id == Some(message_id))
+        {
+            Some(request) => {
+                request.message_id = None;
+                true
+            }
+            None => false,
+        }
+    }
 
-        let client = client.ok_or("Not connected")?;
+    /// 发布一条消息并写入历史记录
+    ///
+    /// 发送前先执行发送前处理脚本，脚本失败时不发布；
+    /// 历史记录先以 "queued" 状态保存，投递状态随确认报文更新；
+    /// 提交失败时记录标记为 "failed" 并返回错误。
+    pub async fn publish_message(
+        &self,
+        storage: &Storage,
+        server_id: i64,
+        mut message: PublishPayload,
+    ) -> Result<(MessageHistory, oneshot::Receiver<Delivery>), String> {
+        let env = env::variables(storage, server_id);
+        message.topic = env::substitute(&message.topic, &env);
+        message.payload = env::substitute(&message.payload, &env);
 
-        let qos = match qos {
-            0 => QoS::AtMostOnce,
-            1 => QoS::AtLeastOnce,
-            2 => QoS::ExactlyOnce,
-            _ => return Err("Invalid QoS".to_string()),
+        let scripts = storage.get_enabled_scripts(server_id, "before_publish");
+        if !scripts.is_empty() {
+            message.payload =
+                script::run_scripts(scripts, message.payload, message.topic.clone(), env).await?;
+        }
+
+        // 转换消息内容
+        let payload_bytes = match message.format.as_str() {
+            "hex" => hex::decode(message.payload.replace(" ", ""))
+                .map_err(|e| format!("HEX decode failed: {}", e))?,
+            _ => message.payload.as_bytes().to_vec(),
         };
 
-        client
-            .subscribe(topic, qos)
+        let history = storage.create_message(MessageHistory {
+            id: None,
+            server_id,
+            topic: message.topic.clone(),
+            payload: Some(message.payload),
+            payload_format: Some(message.format),
+            raw_payload: Some(payload_bytes.clone()),
+            direction: "publish".to_string(),
+            qos: message.qos,
+            retain: message.retain,
+            properties: message.properties.clone().filter(|p| !p.is_empty()),
+            delivery_state: Some("queued".to_string()),
+            latency_ms: None,
+            delivery_error: None,
+            created_at: None,
+        })?;
+        let message_id = history.id.ok_or("Message ID missing")?;
+
+        let outgoing = OutgoingMessage {
+            topic: message.topic,
+            payload: payload_bytes,
+            qos: message.qos as u8,
+            retain: message.retain,
+            properties: message.properties,
+        };
+        match self
+            .publish_tracked(server_id, outgoing, Some(message_id))
             .await
-            .map_err(|e| e.to_string())
+        {
+            Ok(receiver) => Ok((history, receiver)),
+            Err(e) => {
+                storage.update_message_delivery(message_id, "failed", None, Some(e.clone()))?;
+                Err(e)
+            }
+        }
     }
 
-    pub async fn unsubscribe(&self, server_id: i64, topic: String) -> Result<(), String> {
-        let client = {
-            let clients = self.clients.read();
-            clients.get(&server_id).map(|h| h.client.clone())
+    /// 订阅主题并等待 SUBACK，返回 broker 的授权结果
+    pub async fn subscribe(
+        &self,
+        server_id: i64,
+        topic: String,
+        qos: u8,
+    ) -> Result<TopicGrant, String> {
+        let (client, trackers) = self.client_and_trackers(server_id)?;
+
+        let (reply_tx, reply_rx) = oneshot::channel();
+        let request = PendingSubscribe {
+            source: "subscribe",
+            topics: vec![(topic, qos)],
+            reply: Some(reply_tx),
         };
+        Self::send_subscribe(&client, &trackers, request).map_err(|(_, e)| e)?;
 
-        let client = client.ok_or("Not connected")?;
+        let grants = tokio::time::timeout(ACK_TIMEOUT, reply_rx)
+            .await
+            .map_err(|_| "Timed out waiting for SUBACK".to_string())?
+            .map_err(|_| "Connection closed before SUBACK".to_string())?;
 
-        client.unsubscribe(topic).await.map_err(|e| e.to_string())
+        grants
+            .into_iter()
+            .next()
+            .ok_or_else(|| "Missing return code in SUBACK".to_string())
+    }
+
+    /// 取消订阅并等待 UNSUBACK
+    pub async fn unsubscribe(&self, server_id: i64, topic: String) -> Result<(), String> {
+        let (client, trackers) = self.client_and_trackers(server_id)?;
+
+        let (reply_tx, reply_rx) = oneshot::channel();
+        {
+            let mut trackers = trackers.lock();
+            client.try_unsubscribe(topic)?;
+            trackers
+                .unsubscribe
+                .enqueue(PendingUnsubscribe { reply: reply_tx });
+        }
+
+        tokio::time::timeout(ACK_TIMEOUT, reply_rx)
+            .await
+            .map_err(|_| "Timed out waiting for UNSUBACK".to_string())?
+            .map_err(|_| "Connection closed before UNSUBACK".to_string())?
+    }
+
+    fn client_and_trackers(
+        &self,
+        server_id: i64,
+    ) -> Result<(MqttLink, Arc<Mutex<Trackers>>), String> {
+        let clients = self.clients.read();
+        clients
+            .get(&server_id)
+            .map(|h| (h.client.clone(), h.trackers.clone()))
+            .ok_or_else(|| "Not connected".to_string())
     }
 
     fn emit_state(&self, server_id: i64, status: &str, error: Option<String>) {
@@ -273,6 +916,35 @@ impl MqttManager {
             server_id,
             status: status.to_string(),
             error,
+            attempt: None,
+        };
+        let _ = app_handle.emit("mqtt-connection-state", state);
+    }
+
+    fn emit_subscription_result(
+        app_handle: &AppHandle,
+        server_id: i64,
+        source: &str,
+        grants: Vec<TopicGrant>,
+    ) {
+        let result = SubscriptionResult {
+            server_id,
+            source: source.to_string(),
+            grants,
+        };
+        let _ = app_handle.emit("mqtt-subscription-result", result);
+    }
+
+    fn emit_delivery_status(app_handle: &AppHandle, status: DeliveryStatus) {
+        let _ = app_handle.emit("mqtt-delivery-status", status);
+    }
+
+    fn emit_reconnecting(app_handle: &AppHandle, server_id: i64, attempt: u32, error: String) {
+        let state = ConnectionState {
+            server_id,
+            status: "reconnecting".to_string(),
+            error: Some(error),
+            attempt: Some(attempt),
         };
         let _ = app_handle.emit("mqtt-connection-state", state);
     }
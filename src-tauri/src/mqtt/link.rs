@@ -0,0 +1,497 @@
+use rumqttc::v5;
+use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, Packet, QoS, Transport};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::db::models::{MessageProperties, MqttServer, UserProperty};
+
+/// 协议版本
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtocolVersion {
+    V311,
+    V5,
+}
+
+impl ProtocolVersion {
+    pub fn parse(version: &str) -> Result<Self, String> {
+        match version.trim() {
+            "" | "3.1.1" => Ok(Self::V311),
+            "5.0" | "5" => Ok(Self::V5),
+            other => Err(format!("Unsupported MQTT protocol version: {}", other)),
+        }
+    }
+}
+
+/// 收到的 PUBLISH 报文（已与协议版本无关）
+pub struct IncomingPublish {
+    pub topic: String,
+    pub payload: Vec<u8>,
+    pub qos: u8,
+    pub retain: bool,
+    /// MQTT 5 属性（v3.1.1 连接始终为 None）
+    pub properties: Option<MessageProperties>,
+}
+
+/// 事件循环产生的事件（已与协议版本无关）
+pub enum LinkEvent {
+    ConnAck {
+        success: bool,
+        reason: String,
+    },
+    Publish(IncomingPublish),
+    /// 无法处理而丢弃的 PUBLISH（例如未登记的主题别名）
+    DroppedPublish(String),
+    /// PUBLISH 已写出，附带报文标识符（QoS 0 为 0）
+    OutgoingPublish(u16),
+    /// QoS 1 确认
+    PubAck {
+        pkid: u16,
+        result: Result<(), String>,
+    },
+    /// QoS 2 第一阶段确认，仅在失败时有意义
+    PubRec {
+        pkid: u16,
+        result: Result<(), String>,
+    },
+    /// QoS 2 完成
+    PubComp {
+        pkid: u16,
+        result: Result<(), String>,
+    },
+    /// SUBSCRIBE 已写出，附带分配的报文标识符
+    OutgoingSubscribe(u16),
+    /// 每个主题的结果：授予的 QoS 或失败原因
+    SubAck {
+        pkid: u16,
+        codes: Vec<Result<u8, String>>,
+    },
+    /// UNSUBSCRIBE 已写出，附带分配的报文标识符
+    OutgoingUnsubscribe(u16),
+    /// 每个主题的取消结果（v3.1.1 的 UNSUBACK 不含返回码）
+    UnsubAck {
+        pkid: u16,
+        codes: Vec<Result<(), String>>,
+    },
+    PingResp,
+    Other,
+}
+
+/// 事件循环错误
+pub struct LinkError {
+    pub message: String,
+    /// Broker 明确拒绝了连接（认证失败等），重连没有意义
+    pub refused: bool,
+}
+
+impl From<rumqttc::ConnectionError> for LinkError {
+    fn from(e: rumqttc::ConnectionError) -> Self {
+        Self {
+            refused: matches!(e, rumqttc::ConnectionError::ConnectionRefused(_)),
+            message: e.to_string(),
+        }
+    }
+}
+
+impl From<v5::ConnectionError> for LinkError {
+    fn from(e: v5::ConnectionError) -> Self {
+        Self {
+            refused: matches!(e, v5::ConnectionError::ConnectionRefused(_)),
+            message: e.to_string(),
+        }
+    }
+}
+
+/// 统一 v3.1.1 / v5 客户端的发送端
+#[derive(Clone)]
+pub enum MqttLink {
+    V4(AsyncClient),
+    V5(v5::AsyncClient),
+}
+
+/// 统一 v3.1.1 / v5 客户端的事件循环
+pub enum LinkEventLoop {
+    V4(Box<EventLoop>),
+    V5 {
+        eventloop: Box<v5::EventLoop>,
+        /// 当前连接内 broker 下发的主题别名
+        topic_aliases: HashMap<u16, String>,
+    },
+}
+
+/// 根据服务器配置创建客户端及事件循环
+pub fn open(
+    server: &MqttServer,
+    client_id: String,
+    transport: Option<Transport>,
+) -> Result<(MqttLink, LinkEventLoop), String> {
+    let credentials = match (server.username.as_ref(), server.password.as_ref()) {
+        (Some(username), Some(password)) if !username.is_empty() => {
+            Some((username.clone(), password.clone()))
+        }
+        _ => None,
+    };
+
+    match ProtocolVersion::parse(&server.protocol_version)? {
+        ProtocolVersion::V311 => {
+            let mut options = MqttOptions::new(client_id, &server.host, server.port as u16);
+            options.set_keep_alive(Duration::from_secs(server.keep_alive as u64));
+            options.set_clean_session(server.clean_session);
+            if let Some((username, password)) = credentials {
+                options.set_credentials(username, password);
+            }
+            if let Some(transport) = transport {
+                options.set_transport(transport);
+            }
+
+            let (client, eventloop) = AsyncClient::new(options, 100);
+            Ok((MqttLink::V4(client), LinkEventLoop::V4(Box::new(eventloop))))
+        }
+        ProtocolVersion::V5 => {
+            let mut options = v5::MqttOptions::new(client_id, &server.host, server.port as u16);
+            options.set_keep_alive(Duration::from_secs(server.keep_alive as u64));
+            options.set_clean_start(server.clean_session);
+            if let Some((username, password)) = credentials {
+                options.set_credentials(username, password);
+            }
+            if let Some(transport) = transport {
+                options.set_transport(transport);
+            }
+
+            let (client, eventloop) = v5::AsyncClient::new(options, 100);
+            Ok((
+                MqttLink::V5(client),
+                LinkEventLoop::V5 {
+                    eventloop: Box::new(eventloop),
+                    topic_aliases: HashMap::new(),
+                },
+            ))
+        }
+    }
+}
+
+fn qos_v4(qos: u8) -> Result<QoS, String> {
+    match qos {
+        0 => Ok(QoS::AtMostOnce),
+        1 => Ok(QoS::AtLeastOnce),
+        2 => Ok(QoS::ExactlyOnce),
+        _ => Err("Invalid QoS".to_string()),
+    }
+}
+
+fn qos_v5(qos: u8) -> Result<v5::mqttbytes::QoS, String> {
+    match qos {
+        0 => Ok(v5::mqttbytes::QoS::AtMostOnce),
+        1 => Ok(v5::mqttbytes::QoS::AtLeastOnce),
+        2 => Ok(v5::mqttbytes::QoS::ExactlyOnce),
+        _ => Err("Invalid QoS".to_string()),
+    }
+}
+
+/// 将消息属性转换为 rumqttc v5 发布属性
+fn publish_properties_v5(
+    properties: MessageProperties,
+) -> Result<v5::mqttbytes::v5::PublishProperties, String> {
+    if let Some(indicator) = properties.payload_format_indicator {
+        if indicator > 1 {
+            return Err("Payload format indicator must be 0 or 1".to_string());
+        }
+    }
+    if let Some(response_topic) = properties.response_topic.as_deref() {
+        if response_topic.is_empty() || response_topic.contains(['+', '#']) {
+            return Err(
+                "Response topic must be a non-empty topic name without wildcards".to_string(),
+            );
+        }
+    }
+
+    Ok(v5::mqttbytes::v5::PublishProperties {
+        payload_format_indicator: properties.payload_format_indicator,
+        message_expiry_interval: properties.message_expiry_interval,
+        topic_alias: None,
+        response_topic: properties.response_topic,
+        correlation_data: properties.correlation_data.map(Into::into),
+        user_properties: properties
+            .user_properties
+            .into_iter()
+            .map(|p| (p.key, p.value))
+            .collect(),
+        subscription_identifiers: Vec::new(),
+        content_type: properties.content_type,
+    })
+}
+
+/// 将 rumqttc v5 收到的发布属性转换为消息属性
+fn message_properties_v5(properties: v5::mqttbytes::v5::PublishProperties) -> MessageProperties {
+    MessageProperties {
+        user_properties: properties
+            .user_properties
+            .into_iter()
+            .map(|(key, value)| UserProperty { key, value })
+            .collect(),
+        content_type: properties.content_type,
+        response_topic: properties.response_topic,
+        correlation_data: properties.correlation_data.map(|d| d.to_vec()),
+        message_expiry_interval: properties.message_expiry_interval,
+        payload_format_indicator: properties.payload_format_indicator,
+        subscription_identifiers: properties
+            .subscription_identifiers
+            .into_iter()
+            .map(|id| id as u32)
+            .collect(),
+        topic_alias: properties.topic_alias,
+    }
+}
+
+impl MqttLink {
+    pub async fn publish(
+        &self,
+        topic: String,
+        qos: u8,
+        retain: bool,
+        payload: Vec<u8>,
+        properties: Option<MessageProperties>,
+    ) -> Result<(), String> {
+        let properties = properties.filter(|p| !p.is_empty());
+
+        match self {
+            MqttLink::V4(client) => {
+                if properties.is_some() {
+                    return Err("MQTT 5 properties require protocol version 5.0".to_string());
+                }
+                client
+                    .publish(topic, qos_v4(qos)?, retain, payload)
+                    .await
+                    .map_err(|e| e.to_string())
+            }
+            MqttLink::V5(client) => match properties {
+                Some(properties) => client
+                    .publish_with_properties(
+                        topic,
+                        qos_v5(qos)?,
+                        retain,
+                        payload,
+                        publish_properties_v5(properties)?,
+                    )
+                    .await
+                    .map_err(|e| e.to_string()),
+                None => client
+                    .publish(topic, qos_v5(qos)?, retain, payload)
+                    .await
+                    .map_err(|e| e.to_string()),
+            },
+        }
+    }
+
+    /// 以单个 SUBSCRIBE 报文订阅多个主题（不等待，队列满时立即失败）
+    pub fn try_subscribe_many(&self, topics: &[(String, u8)]) -> Result<(), String> {
+        if topics.is_empty() {
+            return Err("No topics to subscribe".to_string());
+        }
+
+        match self {
+            MqttLink::V4(client) => {
+                let filters = topics
+                    .iter()
+                    .map(|(topic, qos)| {
+                        Ok(rumqttc::SubscribeFilter::new(topic.clone(), qos_v4(*qos)?))
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                client
+                    .try_subscribe_many(filters)
+                    .map_err(|e| e.to_string())
+            }
+            MqttLink::V5(client) => {
+                let filters = topics
+                    .iter()
+                    .map(|(topic, qos)| {
+                        Ok(v5::mqttbytes::v5::Filter::new(topic.clone(), qos_v5(*qos)?))
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                client
+                    .try_subscribe_many(filters)
+                    .map_err(|e| e.to_string())
+            }
+        }
+    }
+
+    /// 发送 UNSUBSCRIBE（不等待，队列满时立即失败）
+    pub fn try_unsubscribe(&self, topic: String) -> Result<(), String> {
+        match self {
+            MqttLink::V4(client) => client.try_unsubscribe(topic).map_err(|e| e.to_string()),
+            MqttLink::V5(client) => client.try_unsubscribe(topic).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+impl LinkEventLoop {
+    /// 轮询下一个事件
+    pub async fn poll(&mut self) -> Result<LinkEvent, LinkError> {
+        match self {
+            LinkEventLoop::V4(eventloop) => {
+                let event = eventloop.poll().await?;
+                Ok(match event {
+                    Event::Incoming(Packet::ConnAck(ack)) => LinkEvent::ConnAck {
+                        success: ack.code == rumqttc::ConnectReturnCode::Success,
+                        reason: format!("{:?}", ack.code),
+                    },
+                    Event::Incoming(Packet::Publish(publish)) => {
+                        LinkEvent::Publish(IncomingPublish {
+                            topic: publish.topic,
+                            payload: publish.payload.to_vec(),
+                            qos: publish.qos as u8,
+                            retain: publish.retain,
+                            properties: None,
+                        })
+                    }
+                    Event::Outgoing(rumqttc::Outgoing::Publish(pkid)) => {
+                        LinkEvent::OutgoingPublish(pkid)
+                    }
+                    Event::Incoming(Packet::PubAck(ack)) => LinkEvent::PubAck {
+                        pkid: ack.pkid,
+                        result: Ok(()),
+                    },
+                    Event::Incoming(Packet::PubRec(rec)) => LinkEvent::PubRec {
+                        pkid: rec.pkid,
+                        result: Ok(()),
+                    },
+                    Event::Incoming(Packet::PubComp(comp)) => LinkEvent::PubComp {
+                        pkid: comp.pkid,
+                        result: Ok(()),
+                    },
+                    Event::Outgoing(rumqttc::Outgoing::Subscribe(pkid)) => {
+                        LinkEvent::OutgoingSubscribe(pkid)
+                    }
+                    Event::Incoming(Packet::SubAck(ack)) => LinkEvent::SubAck {
+                        pkid: ack.pkid,
+                        codes: ack
+                            .return_codes
+                            .into_iter()
+                            .map(|code| match code {
+                                rumqttc::SubscribeReasonCode::Success(qos) => Ok(qos as u8),
+                                rumqttc::SubscribeReasonCode::Failure => {
+                                    Err("Subscription rejected by broker".to_string())
+                                }
+                            })
+                            .collect(),
+                    },
+                    Event::Outgoing(rumqttc::Outgoing::Unsubscribe(pkid)) => {
+                        LinkEvent::OutgoingUnsubscribe(pkid)
+                    }
+                    Event::Incoming(Packet::UnsubAck(ack)) => LinkEvent::UnsubAck {
+                        pkid: ack.pkid,
+                        codes: Vec::new(),
+                    },
+                    Event::Incoming(Packet::PingResp) => LinkEvent::PingResp,
+                    _ => LinkEvent::Other,
+                })
+            }
+            LinkEventLoop::V5 {
+                eventloop,
+                topic_aliases,
+            } => {
+                use v5::mqttbytes::v5::{
+                    ConnectReturnCode, Packet as PacketV5, PubAckReason, PubCompReason,
+                    PubRecReason, SubscribeReasonCode, UnsubAckReason,
+                };
+
+                let event = eventloop.poll().await?;
+                Ok(match event {
+                    v5::Event::Incoming(PacketV5::ConnAck(ack)) => {
+                        // 主题别名只在单次连接内有效
+                        topic_aliases.clear();
+                        LinkEvent::ConnAck {
+                            success: ack.code == ConnectReturnCode::Success,
+                            reason: format!("{:?}", ack.code),
+                        }
+                    }
+                    v5::Event::Incoming(PacketV5::Publish(publish)) => {
+                        let mut topic = String::from_utf8_lossy(&publish.topic).to_string();
+                        let properties = publish.properties.map(message_properties_v5);
+
+                        // 解析主题别名：带主题时登记，空主题时查表还原
+                        if let Some(alias) = properties.as_ref().and_then(|p| p.topic_alias) {
+                            if !topic.is_empty() {
+                                topic_aliases.insert(alias, topic.clone());
+                            } else if let Some(known) = topic_aliases.get(&alias) {
+                                topic = known.clone();
+                            } else {
+                                return Ok(LinkEvent::DroppedPublish(format!(
+                                    "Unknown topic alias {}",
+                                    alias
+                                )));
+                            }
+                        }
+
+                        LinkEvent::Publish(IncomingPublish {
+                            topic,
+                            payload: publish.payload.to_vec(),
+                            qos: publish.qos as u8,
+                            retain: publish.retain,
+                            properties: properties.filter(|p| !p.is_empty()),
+                        })
+                    }
+                    v5::Event::Outgoing(rumqttc::Outgoing::Publish(pkid)) => {
+                        LinkEvent::OutgoingPublish(pkid)
+                    }
+                    v5::Event::Incoming(PacketV5::PubAck(ack)) => LinkEvent::PubAck {
+                        pkid: ack.pkid,
+                        result: match ack.reason {
+                            PubAckReason::Success | PubAckReason::NoMatchingSubscribers => Ok(()),
+                            other => Err(format!("Publish rejected by broker: {:?}", other)),
+                        },
+                    },
+                    v5::Event::Incoming(PacketV5::PubRec(rec)) => LinkEvent::PubRec {
+                        pkid: rec.pkid,
+                        result: match rec.reason {
+                            PubRecReason::Success | PubRecReason::NoMatchingSubscribers => Ok(()),
+                            other => Err(format!("Publish rejected by broker: {:?}", other)),
+                        },
+                    },
+                    v5::Event::Incoming(PacketV5::PubComp(comp)) => LinkEvent::PubComp {
+                        pkid: comp.pkid,
+                        result: match comp.reason {
+                            PubCompReason::Success => Ok(()),
+                            other => Err(format!("Publish not completed: {:?}", other)),
+                        },
+                    },
+                    v5::Event::Outgoing(rumqttc::Outgoing::Subscribe(pkid)) => {
+                        LinkEvent::OutgoingSubscribe(pkid)
+                    }
+                    v5::Event::Incoming(PacketV5::SubAck(ack)) => LinkEvent::SubAck {
+                        pkid: ack.pkid,
+                        codes: ack
+                            .return_codes
+                            .into_iter()
+                            .map(|code| match code {
+                                SubscribeReasonCode::Success(qos) => Ok(qos as u8),
+                                other => {
+                                    Err(format!("Subscription rejected by broker: {:?}", other))
+                                }
+                            })
+                            .collect(),
+                    },
+                    v5::Event::Outgoing(rumqttc::Outgoing::Unsubscribe(pkid)) => {
+                        LinkEvent::OutgoingUnsubscribe(pkid)
+                    }
+                    v5::Event::Incoming(PacketV5::UnsubAck(ack)) => LinkEvent::UnsubAck {
+                        pkid: ack.pkid,
+                        codes: ack
+                            .reasons
+                            .into_iter()
+                            .map(|reason| match reason {
+                                UnsubAckReason::Success | UnsubAckReason::NoSubscriptionExisted => {
+                                    Ok(())
+                                }
+                                other => {
+                                    Err(format!("Unsubscribe rejected by broker: {:?}", other))
+                                }
+                            })
+                            .collect(),
+                    },
+                    v5::Event::Incoming(PacketV5::PingResp(_)) => LinkEvent::PingResp,
+                    _ => LinkEvent::Other,
+                })
+            }
+        }
+    }
+}
@@ -1,3 +1,6 @@
 pub mod client;
+pub mod link;
+pub mod topic;
+pub mod tracker;
 
 pub use client::MqttManager;
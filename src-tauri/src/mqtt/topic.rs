@@ -0,0 +1,115 @@
+/// MQTT 主题过滤器匹配（支持 `+` 和 `#` 通配符）
+///
+/// 按规范，以 `$` 开头的主题不会被首层通配符匹配。
+pub fn matches_filter(filter: &str, topic: &str) -> bool {
+    if topic.starts_with('$') && (filter.starts_with('+') || filter.starts_with('#')) {
+        return false;
+    }
+
+    let mut filter_levels = filter.split('/');
+    let mut topic_levels = topic.split('/');
+
+    loop {
+        match (filter_levels.next(), topic_levels.next()) {
+            // # 匹配剩余所有层级（包括父级本身）
+            (Some("#"), _) => return true,
+            (Some("+"), Some(_)) => {}
+            (Some(f), Some(t)) if f == t => {}
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+/// 检查主题过滤器是否合法
+pub fn validate_filter(filter: &str) -> Result<(), String> {
+    if filter.is_empty() {
+        return Err("Topic filter must not be empty".to_string());
+    }
+    let levels: Vec<&str> = filter.split('/').collect();
+    for (i, level) in levels.iter().enumerate() {
+        if level.contains('#') && (*level != "#" || i != levels.len() - 1) {
+            return Err(format!("Invalid '#' wildcard in topic filter: {}", filter));
+        }
+        if level.contains('+') && *level != "+" {
+            return Err(format!("Invalid '+' wildcard in topic filter: {}", filter));
+        }
+    }
+    Ok(())
+}
+
+/// 检查发布主题是否合法（不能为空，不能包含通配符）
+pub fn validate_topic(topic: &str) -> Result<(), String> {
+    if topic.is_empty() {
+        return Err("Topic must not be empty".to_string());
+    }
+    if topic.contains(['+', '#']) {
+        return Err(format!(
+            "Wildcards are not allowed in publish topic: {}",
+            topic
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_exact_topic() {
+        assert!(matches_filter("sensors/room1/temp", "sensors/room1/temp"));
+        assert!(!matches_filter("sensors/room1/temp", "sensors/room1"));
+        assert!(!matches_filter("sensors/room1", "sensors/room1/temp"));
+        assert!(!matches_filter("Sensors/room1", "sensors/room1"));
+    }
+
+    #[test]
+    fn single_level_wildcard_matches_one_level() {
+        assert!(matches_filter("sensors/+/temp", "sensors/room1/temp"));
+        assert!(matches_filter("sensors/+", "sensors/room1"));
+        assert!(matches_filter("+/+", "/finance"));
+        assert!(matches_filter("sensors/+", "sensors/"));
+        assert!(!matches_filter("sensors/+", "sensors"));
+        assert!(!matches_filter("sensors/+", "sensors/room1/temp"));
+        assert!(!matches_filter("+", "/finance"));
+    }
+
+    #[test]
+    fn multi_level_wildcard_matches_rest_and_parent() {
+        assert!(matches_filter("#", "sensors/room1/temp"));
+        assert!(matches_filter("sensors/#", "sensors/room1/temp"));
+        assert!(matches_filter("sensors/#", "sensors"));
+        assert!(matches_filter("sensors/+/#", "sensors/room1"));
+        assert!(!matches_filter("sensors/#", "devices/room1"));
+    }
+
+    #[test]
+    fn wildcards_at_first_level_skip_dollar_topics() {
+        assert!(!matches_filter("#", "$SYS/broker/uptime"));
+        assert!(!matches_filter("+/broker/uptime", "$SYS/broker/uptime"));
+        assert!(matches_filter("$SYS/#", "$SYS/broker/uptime"));
+        assert!(matches_filter("$SYS/+/uptime", "$SYS/broker/uptime"));
+    }
+
+    #[test]
+    fn validates_filters() {
+        for filter in ["a/b", "#", "+", "a/+/c", "a/#", "+/+/#", "$SYS/#", "/"] {
+            assert!(validate_filter(filter).is_ok(), "{}", filter);
+        }
+        for filter in ["", "a/#/c", "a#", "a/b#", "#/a", "a+", "a/+b/c", "++"] {
+            assert!(validate_filter(filter).is_err(), "{}", filter);
+        }
+    }
+
+    #[test]
+    fn validates_publish_topics() {
+        assert!(validate_topic("sensors/room1/temp").is_ok());
+        assert!(validate_topic("$SYS/broker").is_ok());
+        assert!(validate_topic("/").is_ok());
+        assert!(validate_topic("").is_err());
+        assert!(validate_topic("sensors/+/temp").is_err());
+        assert!(validate_topic("sensors/#").is_err());
+        assert!(validate_topic("a#b").is_err());
+    }
+}
@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::time::Instant;
+use tokio::sync::oneshot;
+
+/// 单个主题的订阅结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopicGrant {
+    pub topic: String,
+    pub requested_qos: u8,
+    /// Broker 授予的 QoS，失败时为 None
+    pub granted_qos: Option<u8>,
+    pub error: Option<String>,
+}
+
+impl TopicGrant {
+    fn failed(topic: &str, requested_qos: u8, reason: &str) -> Self {
+        Self {
+            topic: topic.to_string(),
+            requested_qos,
+            granted_qos: None,
+            error: Some(reason.to_string()),
+        }
+    }
+}
+
+/// 一次 SUBSCRIBE 请求
+pub struct PendingSubscribe {
+    /// 请求来源："subscribe" | "resubscribe"
+    pub source: &'static str,
+    pub topics: Vec<(String, u8)>,
+    /// 等待结果的调用方
+    pub reply: Option<oneshot::Sender<Vec<TopicGrant>>>,
+}
+
+impl PendingSubscribe {
+    /// 按 SUBACK 返回码生成每个主题的结果
+    pub fn grants(&self, codes: Vec<Result<u8, String>>) -> Vec<TopicGrant> {
+        let mut codes = codes.into_iter();
+        self.topics
+            .iter()
+            .map(|(topic, qos)| {
+                match codes
+                    .next()
+                    .unwrap_or_else(|| Err("Missing return code in SUBACK".to_string()))
+                {
+                    Ok(granted) => TopicGrant {
+                        topic: topic.clone(),
+                        requested_qos: *qos,
+                        granted_qos: Some(granted),
+                        error: None,
+                    },
+                    Err(reason) => TopicGrant::failed(topic, *qos, &reason),
+                }
+            })
+            .collect()
+    }
+
+    /// 整个请求失败（连接断开等）
+    pub fn failed_grants(&self, reason: &str) -> Vec<TopicGrant> {
+        self.topics
+            .iter()
+            .map(|(topic, qos)| TopicGrant::failed(topic, *qos, reason))
+            .collect()
+    }
+}
+
+/// 一次 UNSUBSCRIBE 请求
+pub struct PendingUnsubscribe {
+    pub reply: oneshot::Sender<Result<(), String>>,
+}
+
+/// 发布消息的投递结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Delivery {
+    pub status: String, // "sent"（QoS 0）| "acked" | "failed"
+    /// 从报文写出到收到确认的耗时（毫秒）
+    pub latency_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// 一次 PUBLISH 请求
+pub struct PendingPublish {
+    pub topic: String,
+    pub qos: u8,
+    /// 对应的历史记录 ID
+    pub message_id: Option<i64>,
+    /// 报文写出的时间
+    pub sent_at: Option<Instant>,
+    pub reply: oneshot::Sender<Delivery>,
+}
+
+impl PendingPublish {
+    pub fn delivery(&self, result: Result<(), String>) -> Delivery {
+        let latency_ms = self.sent_at.map(|t| t.elapsed().as_millis() as u64);
+        match result {
+            Ok(()) if self.qos == 0 => Delivery {
+                status: "sent".to_string(),
+                latency_ms: None,
+                error: None,
+            },
+            Ok(()) => Delivery {
+                status: "acked".to_string(),
+                latency_ms,
+                error: None,
+            },
+            Err(e) => Delivery {
+                status: "failed".to_string(),
+                latency_ms,
+                error: Some(e),
+            },
+        }
+    }
+}
+
+/// 跟踪请求报文与其确认报文（SUBACK / UNSUBACK 等）的对应关系
+///
+/// rumqttc 按提交顺序发送请求，并在发送时通过 Outgoing 事件告知分配的报文标识符，
+/// 因此先按顺序排队，收到 Outgoing 事件时再与 pkid 绑定。
+pub struct AckTracker<T> {
+    queued: VecDeque<T>,
+    in_flight: HashMap<u16, T>,
+}
+
+impl<T> Default for AckTracker<T> {
+    fn default() -> Self {
+        Self {
+            queued: VecDeque::new(),
+            in_flight: HashMap::new(),
+        }
+    }
+}
+
+impl<T> AckTracker<T> {
+    /// 登记一个已提交给事件循环的请求
+    pub fn enqueue(&mut self, request: T) {
+        self.queued.push_back(request);
+    }
+
+    /// 请求已写出，绑定报文标识符
+    pub fn on_outgoing(&mut self, pkid: u16) {
+        if let Some(request) = self.queued.pop_front() {
+            self.in_flight.insert(pkid, request);
+        }
+    }
+
+    /// 收到确认报文，取出对应请求
+    pub fn on_ack(&mut self, pkid: u16) -> Option<T> {
+        self.in_flight.remove(&pkid)
+    }
+
+    pub fn in_flight_mut(&mut self, pkid: u16) -> Option<&mut T> {
+        self.in_flight.get_mut(&pkid)
+    }
+
+    /// 查找尚未完成的请求（包括尚未写出的）
+    pub fn find_mut(&mut self, mut predicate: impl FnMut(&T) -> bool) -> Option<&mut T> {
+        self.in_flight
+            .values_mut()
+            .chain(self.queued.iter_mut())
+            .find(|request| predicate(request))
+    }
+
+    /// 撤销最后登记的请求（提交失败时调用）
+    pub fn cancel_last(&mut self) -> Option<T> {
+        self.queued.pop_back()
+    }
+
+    /// 连接断开，取出所有已发出但未确认的请求
+    pub fn drain_in_flight(&mut self) -> Vec<T> {
+        self.in_flight.drain().map(|(_, request)| request).collect()
+    }
+
+    /// 取出所有未完成的请求（包括尚未写出的）
+    pub fn drain_all(&mut self) -> Vec<T> {
+        let mut requests = self.drain_in_flight();
+        requests.extend(self.queued.drain(..));
+        requests
+    }
+}
+
+/// 单个连接的所有确认跟踪器
+#[derive(Default)]
+pub struct Trackers {
+    pub publish: AckTracker<PendingPublish>,
+    pub subscribe: AckTracker<PendingSubscribe>,
+    pub unsubscribe: AckTracker<PendingUnsubscribe>,
+}
@@ -0,0 +1,320 @@
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+use crate::db::models::{MessageProperties, MessageQuery};
+use crate::db::Storage;
+use crate::export::replay::{ReplayReader, ReplayRecord};
+use crate::mqtt::client::OutgoingMessage;
+use crate::mqtt::link::ProtocolVersion;
+use crate::mqtt::MqttManager;
+
+/// 回放数据来源
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ReplaySource {
+    /// 指定的历史消息
+    Messages { message_ids: Vec<i64> },
+    /// 符合查询条件的历史消息
+    Query { query: MessageQuery },
+    /// 导出的回放文件（.mqrp）
+    File { path: String },
+}
+
+/// 回放选项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReplayOptions {
+    /// 速度倍率，2.0 表示两倍速
+    pub speed: f64,
+    /// 两条消息之间的最长等待时间（毫秒），用于跳过长时间空闲
+    pub max_gap_ms: Option<u64>,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            max_gap_ms: None,
+        }
+    }
+}
+
+/// 回放进度事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayProgress {
+    pub replay_id: String,
+    pub server_id: i64,
+    pub status: String, // "running" | "paused" | "completed" | "stopped" | "failed"
+    pub sent: usize,
+    pub total: usize,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReplayState {
+    Running,
+    Paused,
+    Stopped,
+}
+
+/// 管理所有进行中的回放任务
+pub struct ReplayManager {
+    sessions: Mutex<HashMap<String, watch::Sender<ReplayState>>>,
+    app_handle: AppHandle,
+}
+
+impl ReplayManager {
+    pub fn new(app_handle: AppHandle) -> Self {
+        Self {
+            sessions: Mutex::new(HashMap::new()),
+            app_handle,
+        }
+    }
+
+    /// 开始回放，返回回放任务 ID
+    pub fn start(
+        &self,
+        server_id: i64,
+        source: ReplaySource,
+        options: ReplayOptions,
+    ) -> Result<String, String> {
+        if !options.speed.is_finite() || options.speed <= 0.0 {
+            return Err("Replay speed must be greater than 0".to_string());
+        }
+
+        let storage = self
+            .app_handle
+            .try_state::<Storage>()
+            .ok_or("Database is not available")?;
+        let server = storage.get_server(server_id).ok_or("Server not found")?;
+        let keep_properties =
+            ProtocolVersion::parse(&server.protocol_version)? == ProtocolVersion::V5;
+
+        let records = load_records(&storage, source)?;
+        if records.is_empty() {
+            return Err("No messages to replay".to_string());
+        }
+
+        let replay_id = uuid::Uuid::new_v4().to_string();
+        let (state_tx, state_rx) = watch::channel(ReplayState::Running);
+        self.sessions.lock().insert(replay_id.clone(), state_tx);
+
+        let session = ReplaySession {
+            replay_id: replay_id.clone(),
+            server_id,
+            records,
+            options,
+            keep_properties,
+            app_handle: self.app_handle.clone(),
+        };
+        tokio::spawn(session.run(state_rx));
+
+        Ok(replay_id)
+    }
+
+    pub fn pause(&self, replay_id: &str) -> Result<(), String> {
+        self.set_state(replay_id, ReplayState::Paused)
+    }
+
+    pub fn resume(&self, replay_id: &str) -> Result<(), String> {
+        self.set_state(replay_id, ReplayState::Running)
+    }
+
+    pub fn stop(&self, replay_id: &str) -> Result<(), String> {
+        self.set_state(replay_id, ReplayState::Stopped)
+    }
+
+    fn set_state(&self, replay_id: &str, state: ReplayState) -> Result<(), String> {
+        let sessions = self.sessions.lock();
+        let state_tx = sessions.get(replay_id).ok_or("Replay not found")?;
+        state_tx.send_replace(state);
+        Ok(())
+    }
+
+    fn finish(&self, replay_id: &str) {
+        self.sessions.lock().remove(replay_id);
+    }
+}
+
+/// 加载待回放的消息（按时间正序）
+fn load_records(storage: &Storage, source: ReplaySource) -> Result<Vec<ReplayRecord>, String> {
+    match source {
+        ReplaySource::Messages { message_ids } => {
+            let mut messages = message_ids
+                .into_iter()
+                .filter_map(|id| storage.get_message(id))
+                .collect::<Vec<_>>();
+            messages.sort_by_key(|m| m.id);
+            Ok(messages.iter().map(ReplayRecord::from_message).collect())
+        }
+        ReplaySource::Query { query } => {
+            let mut query = MessageQuery {
+                cursor: None,
+                limit: Some(1000),
+                ascending: true,
+                ..query
+            };
+            let mut records = Vec::new();
+            loop {
+                let page = storage.search_messages(&query)?;
+                records.extend(page.messages.iter().map(ReplayRecord::from_message));
+                match page.next_cursor {
+                    Some(cursor) => query.cursor = Some(cursor),
+                    None => break,
+                }
+            }
+            Ok(records)
+        }
+        ReplaySource::File { path } => {
+            let file = File::open(&path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+            let mut reader = ReplayReader::new(BufReader::new(file))?;
+            let mut records = Vec::new();
+            while let Some(record) = reader.next_record()? {
+                records.push(record);
+            }
+            records.sort_by_key(|r| r.timestamp_us);
+            Ok(records)
+        }
+    }
+}
+
+struct ReplaySession {
+    replay_id: String,
+    server_id: i64,
+    records: Vec<ReplayRecord>,
+    options: ReplayOptions,
+    /// 目标服务器是否支持 MQTT 5 属性
+    keep_properties: bool,
+    app_handle: AppHandle,
+}
+
+impl ReplaySession {
+    async fn run(self, mut state_rx: watch::Receiver<ReplayState>) {
+        let total = self.records.len();
+        let mut sent = 0;
+        let mut error = None;
+        let mut previous_us = None;
+
+        self.emit_progress("running", sent, None);
+
+        for record in &self.records {
+            let delay = self.delay_between(previous_us, record.timestamp_us);
+            previous_us = Some(record.timestamp_us);
+
+            if !self.wait(delay, sent, &mut state_rx).await {
+                break;
+            }
+
+            let message = OutgoingMessage {
+                topic: record.topic.clone(),
+                payload: record.payload.clone(),
+                qos: record.qos,
+                retain: record.retain,
+                properties: record
+                    .properties
+                    .clone()
+                    .filter(|_| self.keep_properties)
+                    .map(MessageProperties::publishable),
+            };
+            let Some(mqtt_manager) = self.app_handle.try_state::<MqttManager>() else {
+                error = Some("Application is shutting down".to_string());
+                break;
+            };
+            if let Err(e) = mqtt_manager
+                .publish_tracked(self.server_id, message, None)
+                .await
+            {
+                error = Some(e);
+                break;
+            }
+
+            sent += 1;
+            self.emit_progress("running", sent, None);
+        }
+
+        let status = if error.is_some() {
+            "failed"
+        } else if sent == total {
+            "completed"
+        } else {
+            "stopped"
+        };
+        self.emit_progress(status, sent, error);
+
+        if let Some(manager) = self.app_handle.try_state::<ReplayManager>() {
+            manager.finish(&self.replay_id);
+        }
+    }
+
+    /// 按原始时间间隔和速度倍率计算等待时间
+    fn delay_between(&self, previous_us: Option<i64>, current_us: i64) -> Duration {
+        let Some(previous_us) = previous_us else {
+            return Duration::ZERO;
+        };
+        let gap_us = (current_us - previous_us).max(0) as f64 / self.options.speed;
+        let delay = Duration::from_micros(gap_us as u64);
+        match self.options.max_gap_ms {
+            Some(max) => delay.min(Duration::from_millis(max)),
+            None => delay,
+        }
+    }
+
+    /// 等待指定时间，期间处理暂停/继续；被停止时返回 false
+    async fn wait(
+        &self,
+        delay: Duration,
+        sent: usize,
+        state_rx: &mut watch::Receiver<ReplayState>,
+    ) -> bool {
+        let mut remaining = delay;
+        loop {
+            let state = *state_rx.borrow_and_update();
+            match state {
+                ReplayState::Stopped => return false,
+                ReplayState::Paused => {
+                    self.emit_progress("paused", sent, None);
+                    if state_rx.changed().await.is_err() {
+                        return false;
+                    }
+                    if *state_rx.borrow() == ReplayState::Running {
+                        self.emit_progress("running", sent, None);
+                    }
+                }
+                ReplayState::Running => {
+                    let started = Instant::now();
+                    tokio::select! {
+                        _ = tokio::time::sleep(remaining) => return true,
+                        changed = state_rx.changed() => {
+                            if changed.is_err() {
+                                return false;
+                            }
+                            // 暂停后恢复时只等待剩余的时间
+                            remaining = remaining.saturating_sub(started.elapsed());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn emit_progress(&self, status: &str, sent: usize, error: Option<String>) {
+        let _ = self.app_handle.emit(
+            "replay-progress",
+            ReplayProgress {
+                replay_id: self.replay_id.clone(),
+                server_id: self.server_id,
+                status: status.to_string(),
+                sent,
+                total: self.records.len(),
+                error,
+            },
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::AppHandle;
+use tauri::Manager;
+
+use crate::db::models::{MessageHistory, MqttServer};
+use crate::db::Storage;
+use crate::log::{self, LogEntry, LogManager};
+
+/// 后台清理的执行间隔
+const RETENTION_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 归档时每批处理的消息数
+const ARCHIVE_BATCH_SIZE: usize = 1000;
+
+/// 单个 Server 的清理结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionResult {
+    pub server_id: i64,
+    pub deleted: usize,
+    pub archived: usize,
+}
+
+/// 历史消息保留策略的执行者，负责清理和归档
+///
+/// 归档文件只轮转不删除，由用户自行管理归档目录。
+pub struct RetentionManager {
+    archive_dir: PathBuf,
+    max_file_size: u64, // bytes
+}
+
+impl RetentionManager {
+    pub fn new(app_handle: &AppHandle) -> Result<Self, String> {
+        let app_dir = app_handle
+            .path()
+            .app_data_dir()
+            .map_err(|e| e.to_string())?;
+
+        let archive_dir = app_dir.join("archive");
+        fs::create_dir_all(&archive_dir).map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            archive_dir,
+            max_file_size: 10_000_000, // 每个文件最大 10MB
+        })
+    }
+
+    /// 启动后台清理任务
+    pub fn start(app_handle: AppHandle) {
+        tauri::async_runtime::spawn(async move {
+            let mut interval = tokio::time::interval(RETENTION_INTERVAL);
+            loop {
+                interval.tick().await;
+                let handle = app_handle.clone();
+                let _ = tokio::task::spawn_blocking(move || Self::run_all(&handle)).await;
+            }
+        });
+    }
+
+    /// 对所有 Server 执行一次清理
+    fn run_all(app_handle: &AppHandle) {
+        let (Some(storage), Some(manager)) = (
+            app_handle.try_state::<Storage>(),
+            app_handle.try_state::<RetentionManager>(),
+        ) else {
+            return;
+        };
+
+        for server in storage.get_servers() {
+            if let Err(e) = manager.apply(&storage, &server) {
+                if let Some(log_manager) = app_handle.try_state::<LogManager>() {
+                    let _ = log_manager.write_log(&LogEntry {
+                        r#type: "error".to_string(),
+                        message: format!("Message retention failed for server {}", server.name),
+                        details: Some(e),
+                        timestamp: Local::now().to_rfc3339(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// 按 Server 的保留策略清理历史消息
+    pub fn apply(&self, storage: &Storage, server: &MqttServer) -> Result<RetentionResult, String> {
+        let server_id = server.id.ok_or("Server ID missing")?;
+        let policy = &server.retention;
+
+        let mut result = RetentionResult {
+            server_id,
+            deleted: 0,
+            archived: 0,
+        };
+
+        if !policy.archive {
+            result.deleted = storage.prune_messages(server_id, policy)?;
+            return Ok(result);
+        }
+
+        // 先写入归档，成功后再删除
+        loop {
+            let messages = storage.get_expired_messages(server_id, policy, ARCHIVE_BATCH_SIZE)?;
+            if messages.is_empty() {
+                break;
+            }
+            self.archive(&messages)?;
+            result.archived += messages.len();
+
+            let ids: Vec<i64> = messages.iter().filter_map(|m| m.id).collect();
+            result.deleted += storage.delete_messages(&ids)?;
+        }
+
+        Ok(result)
+    }
+
+    /// 获取归档目录路径
+    pub fn get_archive_dir(&self) -> &PathBuf {
+        &self.archive_dir
+    }
+
+    /// 获取当前归档文件路径
+    fn get_current_archive_file(&self) -> PathBuf {
+        let today = Local::now().format("%Y-%m-%d").to_string();
+        self.archive_dir.join(format!("messages-{}.ndjson", today))
+    }
+
+    /// 以 NDJSON 格式追加写入归档文件
+    fn archive(&self, messages: &[MessageHistory]) -> Result<(), String> {
+        let archive_file = self.get_current_archive_file();
+
+        // 检查文件大小，如果超过限制则轮转
+        if let Ok(metadata) = fs::metadata(&archive_file) {
+            if metadata.len() > self.max_file_size {
+                log::rotate_file(&archive_file, "ndjson")
+                    .map_err(|e| format!("Failed to rotate archive file: {}", e))?;
+            }
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&archive_file)
+            .map_err(|e| format!("Failed to open archive file: {}", e))?;
+        let mut writer = BufWriter::new(file);
+
+        for message in messages {
+            let line = serde_json::to_string(message).map_err(|e| e.to_string())?;
+            writeln!(writer, "{}", line).map_err(|e| format!("Failed to write archive: {}", e))?;
+        }
+        writer
+            .flush()
+            .map_err(|e| format!("Failed to write archive: {}", e))
+    }
+}
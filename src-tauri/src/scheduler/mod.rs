@@ -0,0 +1,473 @@
+use chrono::{DateTime, Local, Utc};
+use cron::Schedule;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::task::AbortHandle;
+
+use crate::db::models::{JobTrigger, MessageHistory, PublishPayload, ScheduledJob};
+use crate::db::Storage;
+use crate::mqtt::MqttManager;
+
+/// 定时任务状态事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobStatus {
+    pub job_id: i64,
+    pub server_id: i64,
+    pub status: String, // "running" | "completed" | "stopped" | "failed"
+    pub run_count: u64,
+    /// 下一次发送的时间（RFC 3339）
+    pub next_run_at: Option<String>,
+    /// 上一次发送的消息
+    pub message: Option<MessageHistory>,
+    /// 上一次发送的错误，或任务失败的原因
+    pub error: Option<String>,
+}
+
+struct RunningJob {
+    server_id: i64,
+    /// 启动序号，避免旧任务结束时移除新任务
+    token: u64,
+    abort: AbortHandle,
+}
+
+static NEXT_TOKEN: AtomicU64 = AtomicU64::new(1);
+
+/// 管理后台运行的定时发布任务
+pub struct SchedulerManager {
+    running: Mutex<HashMap<i64, RunningJob>>,
+    app_handle: AppHandle,
+}
+
+impl SchedulerManager {
+    pub fn new(app_handle: AppHandle) -> Self {
+        Self {
+            running: Mutex::new(HashMap::new()),
+            app_handle,
+        }
+    }
+
+    /// 启动任务，已在运行时先停止旧任务
+    pub fn start(&self, job: ScheduledJob) -> Result<(), String> {
+        let job_id = job.id.ok_or("Job ID missing")?;
+        validate_job(&job)?;
+
+        let token = NEXT_TOKEN.fetch_add(1, Ordering::Relaxed);
+        let server_id = job.server_id;
+        let runner = JobRunner {
+            job,
+            app_handle: self.app_handle.clone(),
+        };
+
+        let mut running = self.running.lock();
+        if let Some(previous) = running.remove(&job_id) {
+            previous.abort.abort();
+        }
+        let handle = tokio::spawn(runner.run(token));
+        running.insert(
+            job_id,
+            RunningJob {
+                server_id,
+                token,
+                abort: handle.abort_handle(),
+            },
+        );
+        Ok(())
+    }
+
+    /// 停止任务，不修改其启用状态
+    pub fn stop(&self, job_id: i64) {
+        let Some(job) = self.running.lock().remove(&job_id) else {
+            return;
+        };
+        job.abort.abort();
+
+        let run_count = self
+            .app_handle
+            .try_state::<Storage>()
+            .and_then(|storage| storage.get_scheduled_job(job_id))
+            .map(|job| job.run_count)
+            .unwrap_or_default();
+        emit_status(
+            &self.app_handle,
+            JobStatus {
+                job_id,
+                server_id: job.server_id,
+                status: "stopped".to_string(),
+                run_count,
+                next_run_at: None,
+                message: None,
+                error: None,
+            },
+        );
+    }
+
+    pub fn is_running(&self, job_id: i64) -> bool {
+        self.running.lock().contains_key(&job_id)
+    }
+
+    /// 连接建立后恢复该 Server 所有已启用的任务
+    pub fn resume_server(&self, storage: &Storage, server_id: i64) {
+        for job in storage.get_scheduled_jobs(server_id) {
+            let Some(job_id) = job.id else { continue };
+            if !job.enabled || self.is_running(job_id) {
+                continue;
+            }
+            if let Err(e) = self.start(job) {
+                emit_status(
+                    &self.app_handle,
+                    JobStatus {
+                        job_id,
+                        server_id,
+                        status: "failed".to_string(),
+                        run_count: 0,
+                        next_run_at: None,
+                        message: None,
+                        error: Some(e),
+                    },
+                );
+            }
+        }
+    }
+
+    /// 断开连接时停止该 Server 的任务，重新连接后恢复
+    pub fn suspend_server(&self, server_id: i64) {
+        let job_ids: Vec<i64> = self
+            .running
+            .lock()
+            .iter()
+            .filter(|(_, job)| job.server_id == server_id)
+            .map(|(job_id, _)| *job_id)
+            .collect();
+        for job_id in job_ids {
+            self.stop(job_id);
+        }
+    }
+
+    fn finish(&self, job_id: i64, token: u64) {
+        let mut running = self.running.lock();
+        if running.get(&job_id).is_some_and(|job| job.token == token) {
+            running.remove(&job_id);
+        }
+    }
+}
+
+/// 检查任务配置是否可以运行
+pub fn validate_job(job: &ScheduledJob) -> Result<(), String> {
+    Timing::parse(&job.trigger)?;
+    parse_time(job.start_at.as_deref())?;
+    parse_time(job.end_at.as_deref())?;
+    if job.template_ids.is_empty() && job.message.is_none() {
+        return Err("Scheduled job has no message to publish".to_string());
+    }
+    Ok(())
+}
+
+/// 解析后的触发方式
+enum Timing {
+    Interval {
+        interval: chrono::Duration,
+        round_interval: Option<chrono::Duration>,
+    },
+    Cron(Box<Schedule>),
+}
+
+impl Timing {
+    fn parse(trigger: &JobTrigger) -> Result<Self, String> {
+        match trigger {
+            JobTrigger::Interval {
+                interval_ms,
+                round_interval_ms,
+            } => {
+                if *interval_ms == 0 {
+                    return Err("Interval must be greater than 0".to_string());
+                }
+                Ok(Self::Interval {
+                    interval: chrono::Duration::milliseconds(*interval_ms as i64),
+                    round_interval: round_interval_ms
+                        .filter(|ms| *ms > 0)
+                        .map(|ms| chrono::Duration::milliseconds(ms as i64)),
+                })
+            }
+            JobTrigger::Cron { expression } => {
+                let expression = expression.trim();
+                // 标准 5 位表达式补上秒字段
+                let expression = if expression.split_whitespace().count() == 5 {
+                    format!("0 {}", expression)
+                } else {
+                    expression.to_string()
+                };
+                Schedule::from_str(&expression)
+                    .map(|schedule| Self::Cron(Box::new(schedule)))
+                    .map_err(|e| format!("Invalid cron expression: {}", e))
+            }
+        }
+    }
+
+    /// 计算下一次发送时间；first_in_round 表示即将发送新一轮的第一条消息
+    fn next_after(
+        &self,
+        last_run: Option<DateTime<Utc>>,
+        earliest: DateTime<Utc>,
+        first_in_round: bool,
+    ) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Interval {
+                interval,
+                round_interval,
+            } => {
+                let Some(last_run) = last_run else {
+                    return Some(earliest);
+                };
+                let gap = match round_interval {
+                    Some(round_interval) if first_in_round => *round_interval,
+                    _ => *interval,
+                };
+                Some((last_run + gap).max(earliest))
+            }
+            Self::Cron(schedule) => schedule
+                .after(&earliest.with_timezone(&Local))
+                .next()
+                .map(|time| time.with_timezone(&Utc)),
+        }
+    }
+}
+
+fn parse_time(value: Option<&str>) -> Result<Option<DateTime<Utc>>, String> {
+    value
+        .filter(|v| !v.is_empty())
+        .map(|v| {
+            DateTime::parse_from_rfc3339(v)
+                .map(|time| time.with_timezone(&Utc))
+                .map_err(|e| format!("Invalid time {}: {}", v, e))
+        })
+        .transpose()
+}
+
+struct JobRunner {
+    job: ScheduledJob,
+    app_handle: AppHandle,
+}
+
+impl JobRunner {
+    async fn run(mut self, token: u64) {
+        let job_id = self.job.id.unwrap_or_default();
+        let (status, message, error) = match self.run_loop().await {
+            Ok((message, error)) => ("completed", message, error),
+            Err(e) => ("failed", None, Some(e)),
+        };
+
+        if let Some(storage) = self.app_handle.try_state::<Storage>() {
+            let _ = storage.set_job_enabled(job_id, false);
+        }
+        self.emit(status, None, message, error);
+
+        if let Some(manager) = self.app_handle.try_state::<SchedulerManager>() {
+            manager.finish(job_id, token);
+        }
+    }
+
+    /// 按计划发送，直到达到次数上限或结束时间；返回最后一次发送的结果
+    async fn run_loop(&mut self) -> Result<(Option<MessageHistory>, Option<String>), String> {
+        let timing = Timing::parse(&self.job.trigger)?;
+        let start_at = parse_time(self.job.start_at.as_deref())?;
+        let end_at = parse_time(self.job.end_at.as_deref())?;
+        let message_count = self.job.template_ids.len().max(1) as u64;
+
+        // 恢复的任务从上次发送时间开始计算间隔
+        let mut last_run = parse_time(self.job.last_run_at.as_deref()).unwrap_or_default();
+        let mut last_result = (None, None);
+        loop {
+            if self
+                .job
+                .max_runs
+                .is_some_and(|max| self.job.run_count >= max)
+            {
+                return Ok(last_result);
+            }
+
+            let now = Utc::now();
+            let earliest = start_at.map_or(now, |start| start.max(now));
+            let first_in_round = self.job.run_count.is_multiple_of(message_count);
+            let Some(next) = timing.next_after(last_run, earliest, first_in_round) else {
+                return Ok(last_result);
+            };
+            if end_at.is_some_and(|end| next > end) {
+                return Ok(last_result);
+            }
+
+            let (message, error) = last_result;
+            self.emit("running", Some(next.to_rfc3339()), message, error);
+
+            let delay = (next - Utc::now()).to_std().unwrap_or_default();
+            tokio::time::sleep(delay).await;
+
+            last_run = Some(Utc::now());
+            last_result = self.fire().await;
+        }
+    }
+
+    /// 发送下一条消息，只有发送成功时才计入发送次数
+    async fn fire(&mut self) -> (Option<MessageHistory>, Option<String>) {
+        let (Some(storage), Some(mqtt_manager)) = (
+            self.app_handle.try_state::<Storage>(),
+            self.app_handle.try_state::<MqttManager>(),
+        ) else {
+            return (None, Some("Application is shutting down".to_string()));
+        };
+
+        let result = match self.next_message(&storage) {
+            Ok(message) => mqtt_manager
+                .publish_message(&storage, self.job.server_id, message)
+                .await
+                .map(|(history, _)| history),
+            Err(e) => Err(e),
+        };
+
+        if result.is_ok() {
+            self.job.run_count += 1;
+        }
+        let now = Utc::now().to_rfc3339();
+        if let Some(job_id) = self.job.id {
+            let _ = storage.record_job_run(job_id, self.job.run_count, &now);
+        }
+        self.job.last_run_at = Some(now);
+
+        match result {
+            Ok(history) => (Some(history), None),
+            Err(e) => (None, Some(e)),
+        }
+    }
+
+    /// 按顺序取出本次要发送的消息
+    fn next_message(&self, storage: &Storage) -> Result<PublishPayload, String> {
+        if self.job.template_ids.is_empty() {
+            return self
+                .job
+                .message
+                .clone()
+                .ok_or_else(|| "Scheduled job has no message to publish".to_string());
+        }
+
+        let index = (self.job.run_count % self.job.template_ids.len() as u64) as usize;
+        let template_id = self.job.template_ids[index];
+        let template = storage
+            .get_template(template_id)
+            .ok_or_else(|| format!("Template {} not found", template_id))?;
+        Ok(PublishPayload {
+            topic: template.topic,
+            payload: template.payload,
+            qos: template.qos,
+            retain: template.retain,
+            format: template.payload_type,
+            properties: None,
+            await_ack: false,
+            ack_timeout_ms: None,
+        })
+    }
+
+    fn emit(
+        &self,
+        status: &str,
+        next_run_at: Option<String>,
+        message: Option<MessageHistory>,
+        error: Option<String>,
+    ) {
+        emit_status(
+            &self.app_handle,
+            JobStatus {
+                job_id: self.job.id.unwrap_or_default(),
+                server_id: self.job.server_id,
+                status: status.to_string(),
+                run_count: self.job.run_count,
+                next_run_at,
+                message,
+                error,
+            },
+        );
+    }
+}
+
+fn emit_status(app_handle: &AppHandle, status: JobStatus) {
+    let _ = app_handle.emit("scheduled-job-status", status);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Timelike};
+
+    fn interval(interval_ms: u64, round_interval_ms: Option<u64>) -> Result<Timing, String> {
+        Timing::parse(&JobTrigger::Interval {
+            interval_ms,
+            round_interval_ms,
+        })
+    }
+
+    fn cron(expression: &str) -> Result<Timing, String> {
+        Timing::parse(&JobTrigger::Cron {
+            expression: expression.to_string(),
+        })
+    }
+
+    #[test]
+    fn parses_interval_triggers() {
+        assert!(interval(0, None).is_err());
+        assert!(matches!(
+            interval(1000, Some(0)),
+            Ok(Timing::Interval {
+                round_interval: None,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn interval_uses_round_interval_before_new_round() {
+        let timing = interval(1000, Some(60_000)).unwrap();
+        let now = Utc::now();
+
+        assert_eq!(timing.next_after(None, now, true), Some(now));
+        assert_eq!(
+            timing.next_after(Some(now), now, false),
+            Some(now + Duration::seconds(1))
+        );
+        assert_eq!(
+            timing.next_after(Some(now), now, true),
+            Some(now + Duration::seconds(60))
+        );
+        // 错过的发送时间不会补发
+        let late = now + Duration::seconds(10);
+        assert_eq!(timing.next_after(Some(now), late, false), Some(late));
+    }
+
+    #[test]
+    fn parses_cron_expressions() {
+        assert!(cron("30 8 * * 1-5").is_ok());
+        assert!(cron("  */5 * * * *  ").is_ok());
+        assert!(cron("*/10 * * * * *").is_ok());
+        assert!(cron("0 0 12 * * * 2030").is_ok());
+        assert!(cron("61 * * * *").is_err());
+        assert!(cron("* * *").is_err());
+        assert!(cron("").is_err());
+    }
+
+    #[test]
+    fn five_field_cron_fires_on_the_minute() {
+        let timing = cron("*/15 * * * *").unwrap();
+        let now = Utc::now();
+        let next = timing.next_after(None, now, true).unwrap();
+        let local = next.with_timezone(&Local);
+
+        assert!(next > now && next - now <= Duration::minutes(15));
+        assert_eq!(local.second(), 0);
+        assert_eq!(local.minute() % 15, 0);
+        assert_eq!(
+            timing.next_after(Some(now), next, false).unwrap() - next,
+            Duration::minutes(15)
+        );
+    }
+}
@@ -0,0 +1,448 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use boa_engine::object::builtins::JsUint8Array;
+use boa_engine::object::ObjectInitializer;
+use boa_engine::property::Attribute;
+use boa_engine::{
+    js_string, Context, JsArgs, JsNativeError, JsResult, JsString, JsValue, NativeFunction,
+};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+type NativeFn = fn(&JsValue, &[JsValue], &mut Context) -> JsResult<JsValue>;
+
+/// 注册脚本可用的 crypto 对象以及 btoa / atob
+pub fn register(context: &mut Context) -> JsResult<()> {
+    let functions: &[(&str, NativeFn, usize)] = &[
+        // 编码转换
+        ("stringToBytes", string_to_bytes, 1),
+        ("bytesToString", bytes_to_string, 1),
+        ("bytesToBase64", bytes_to_base64, 1),
+        ("base64ToBytes", base64_to_bytes, 1),
+        ("bytesToHex", bytes_to_hex, 1),
+        ("hexToBytes", hex_to_bytes, 1),
+        // 随机数
+        ("randomBytes", random_bytes, 1),
+        ("generateKey", generate_key, 1),
+        ("generateIv", generate_iv, 1),
+        // 哈希
+        ("sha256", sha256, 1),
+        ("sha1", sha1, 1),
+        ("md5", md5, 1),
+        ("hmacSha256", hmac_sha256, 2),
+        // AES 加解密（Base64 格式）
+        ("aesGcmEncrypt", aes_gcm_encrypt_base64, 3),
+        ("aesGcmDecrypt", aes_gcm_decrypt_base64, 2),
+        ("aesCbcEncrypt", aes_cbc_encrypt_base64, 3),
+        ("aesCbcDecrypt", aes_cbc_decrypt_base64, 2),
+        // AES 加解密（Hex 格式）
+        ("aesGcmEncryptHex", aes_gcm_encrypt_hex, 3),
+        ("aesGcmDecryptHex", aes_gcm_decrypt_hex, 2),
+        ("aesCbcEncryptHex", aes_cbc_encrypt_hex, 3),
+        ("aesCbcDecryptHex", aes_cbc_decrypt_hex, 2),
+        // 其他
+        ("xor", xor, 2),
+        ("crc32", crc32, 1),
+    ];
+
+    let mut crypto = ObjectInitializer::new(context);
+    for (name, function, length) in functions {
+        crypto.function(
+            NativeFunction::from_fn_ptr(*function),
+            JsString::from(*name),
+            *length,
+        );
+    }
+    let crypto = crypto.build();
+    context.register_global_property(js_string!("crypto"), crypto, Attribute::all())?;
+
+    context.register_global_callable(js_string!("btoa"), 1, NativeFunction::from_fn_ptr(btoa))?;
+    context.register_global_callable(js_string!("atob"), 1, NativeFunction::from_fn_ptr(atob))?;
+    Ok(())
+}
+
+fn arg_string(args: &[JsValue], index: usize, context: &mut Context) -> JsResult<String> {
+    Ok(args
+        .get_or_undefined(index)
+        .to_string(context)?
+        .to_std_string_escaped())
+}
+
+/// 可选的字符串参数，undefined / null 视为未提供
+fn arg_optional_string(
+    args: &[JsValue],
+    index: usize,
+    context: &mut Context,
+) -> JsResult<Option<String>> {
+    match args.get_or_undefined(index) {
+        value if value.is_null_or_undefined() => Ok(None),
+        _ => arg_string(args, index, context).map(Some),
+    }
+}
+
+/// 读取 Uint8Array 或普通数组参数
+fn arg_bytes(args: &[JsValue], index: usize, context: &mut Context) -> JsResult<Vec<u8>> {
+    let Some(object) = args.get_or_undefined(index).as_object() else {
+        return Err(JsNativeError::typ()
+            .with_message("Expected a Uint8Array or an array of bytes")
+            .into());
+    };
+    let length = object
+        .get(js_string!("length"), context)?
+        .to_length(context)?;
+    (0..length)
+        .map(|i| object.get(i as usize, context)?.to_uint8(context))
+        .collect()
+}
+
+fn arg_usize(
+    args: &[JsValue],
+    index: usize,
+    default: usize,
+    context: &mut Context,
+) -> JsResult<usize> {
+    match args.get_or_undefined(index) {
+        value if value.is_undefined() => Ok(default),
+        value => Ok(value.to_length(context)? as usize),
+    }
+}
+
+fn bytes_value(bytes: Vec<u8>, context: &mut Context) -> JsResult<JsValue> {
+    Ok(JsUint8Array::from_iter(bytes, context)?.into())
+}
+
+fn string_value(value: String) -> JsValue {
+    JsString::from(value).into()
+}
+
+fn js_error(message: impl Into<String>) -> boa_engine::JsError {
+    JsNativeError::error().with_message(message.into()).into()
+}
+
+fn decode_base64(value: &str) -> JsResult<Vec<u8>> {
+    STANDARD
+        .decode(value.trim())
+        .map_err(|e| js_error(format!("Invalid Base64: {}", e)))
+}
+
+fn decode_hex(value: &str) -> JsResult<Vec<u8>> {
+    hex::decode(value.trim()).map_err(|e| js_error(format!("Invalid hex: {}", e)))
+}
+
+fn random(length: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; length];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes
+}
+
+fn string_to_bytes(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let text = arg_string(args, 0, context)?;
+    bytes_value(text.into_bytes(), context)
+}
+
+fn bytes_to_string(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let bytes = arg_bytes(args, 0, context)?;
+    Ok(string_value(String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+fn bytes_to_base64(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let bytes = arg_bytes(args, 0, context)?;
+    Ok(string_value(STANDARD.encode(bytes)))
+}
+
+fn base64_to_bytes(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let bytes = decode_base64(&arg_string(args, 0, context)?)?;
+    bytes_value(bytes, context)
+}
+
+fn bytes_to_hex(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let bytes = arg_bytes(args, 0, context)?;
+    Ok(string_value(hex::encode(bytes)))
+}
+
+fn hex_to_bytes(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let bytes = decode_hex(&arg_string(args, 0, context)?)?;
+    bytes_value(bytes, context)
+}
+
+fn random_bytes(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let length = arg_usize(args, 0, 16, context)?.min(1024 * 1024);
+    bytes_value(random(length), context)
+}
+
+fn generate_key(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let bits = arg_usize(args, 0, 128, context)?;
+    if !matches!(bits, 128 | 192 | 256) {
+        return Err(js_error(format!("Unsupported key size: {} bits", bits)));
+    }
+    Ok(string_value(STANDARD.encode(random(bits / 8))))
+}
+
+fn generate_iv(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let length = arg_usize(args, 0, 12, context)?.min(64);
+    Ok(string_value(STANDARD.encode(random(length))))
+}
+
+fn sha256(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let data = arg_string(args, 0, context)?;
+    Ok(string_value(hex::encode(Sha256::digest(data.as_bytes()))))
+}
+
+fn sha1(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let data = arg_string(args, 0, context)?;
+    Ok(string_value(hex::encode(Sha1::digest(data.as_bytes()))))
+}
+
+fn md5(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let data = arg_string(args, 0, context)?;
+    Ok(string_value(hex::encode(md5::Md5::digest(data.as_bytes()))))
+}
+
+fn hmac_sha256(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let key = arg_string(args, 0, context)?;
+    let data = arg_string(args, 1, context)?;
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(key.as_bytes()).map_err(|e| js_error(e.to_string()))?;
+    mac.update(data.as_bytes());
+    Ok(string_value(hex::encode(mac.finalize().into_bytes())))
+}
+
+/// 加密结果为 IV + 密文
+fn aes_gcm_encrypt(key: &[u8], iv: Option<Vec<u8>>, plaintext: &[u8]) -> JsResult<Vec<u8>> {
+    use aes_gcm::aead::consts::U12;
+    use aes_gcm::aead::{Aead, KeyInit};
+    use aes_gcm::{Aes128Gcm, Aes256Gcm, AesGcm, Nonce};
+
+    let iv = iv.unwrap_or_else(|| random(12));
+    if iv.len() != 12 {
+        return Err(js_error("AES-GCM IV must be 12 bytes"));
+    }
+    let nonce = Nonce::from_slice(&iv);
+    let ciphertext = match key.len() {
+        16 => Aes128Gcm::new_from_slice(key).map(|c| c.encrypt(nonce, plaintext)),
+        24 => AesGcm::<aes::Aes192, U12>::new_from_slice(key).map(|c| c.encrypt(nonce, plaintext)),
+        32 => Aes256Gcm::new_from_slice(key).map(|c| c.encrypt(nonce, plaintext)),
+        n => return Err(js_error(format!("Invalid AES key length: {} bytes", n))),
+    }
+    .map_err(|e| js_error(e.to_string()))?
+    .map_err(|_| js_error("AES-GCM encryption failed"))?;
+
+    Ok([iv, ciphertext].concat())
+}
+
+fn aes_gcm_decrypt(key: &[u8], combined: &[u8]) -> JsResult<Vec<u8>> {
+    use aes_gcm::aead::consts::U12;
+    use aes_gcm::aead::{Aead, KeyInit};
+    use aes_gcm::{Aes128Gcm, Aes256Gcm, AesGcm, Nonce};
+
+    if combined.len() < 12 {
+        return Err(js_error("Ciphertext is too short"));
+    }
+    let (iv, ciphertext) = combined.split_at(12);
+    let nonce = Nonce::from_slice(iv);
+    match key.len() {
+        16 => Aes128Gcm::new_from_slice(key).map(|c| c.decrypt(nonce, ciphertext)),
+        24 => AesGcm::<aes::Aes192, U12>::new_from_slice(key).map(|c| c.decrypt(nonce, ciphertext)),
+        32 => Aes256Gcm::new_from_slice(key).map(|c| c.decrypt(nonce, ciphertext)),
+        n => return Err(js_error(format!("Invalid AES key length: {} bytes", n))),
+    }
+    .map_err(|e| js_error(e.to_string()))?
+    .map_err(|_| js_error("AES-GCM decryption failed"))
+}
+
+/// 加密结果为 IV + 密文（PKCS7 填充）
+fn aes_cbc_encrypt(key: &[u8], iv: Option<Vec<u8>>, plaintext: &[u8]) -> JsResult<Vec<u8>> {
+    use cbc::cipher::block_padding::Pkcs7;
+    use cbc::cipher::{BlockEncryptMut, KeyIvInit};
+
+    let iv = iv.unwrap_or_else(|| random(16));
+    let ciphertext = match key.len() {
+        16 => cbc::Encryptor::<aes::Aes128>::new_from_slices(key, &iv)
+            .map(|c| c.encrypt_padded_vec_mut::<Pkcs7>(plaintext)),
+        24 => cbc::Encryptor::<aes::Aes192>::new_from_slices(key, &iv)
+            .map(|c| c.encrypt_padded_vec_mut::<Pkcs7>(plaintext)),
+        32 => cbc::Encryptor::<aes::Aes256>::new_from_slices(key, &iv)
+            .map(|c| c.encrypt_padded_vec_mut::<Pkcs7>(plaintext)),
+        n => return Err(js_error(format!("Invalid AES key length: {} bytes", n))),
+    }
+    .map_err(|_| js_error("AES-CBC IV must be 16 bytes"))?;
+
+    Ok([iv, ciphertext].concat())
+}
+
+fn aes_cbc_decrypt(key: &[u8], combined: &[u8]) -> JsResult<Vec<u8>> {
+    use cbc::cipher::block_padding::Pkcs7;
+    use cbc::cipher::{BlockDecryptMut, KeyIvInit};
+
+    if combined.len() < 16 {
+        return Err(js_error("Ciphertext is too short"));
+    }
+    let (iv, ciphertext) = combined.split_at(16);
+    match key.len() {
+        16 => cbc::Decryptor::<aes::Aes128>::new_from_slices(key, iv)
+            .map(|c| c.decrypt_padded_vec_mut::<Pkcs7>(ciphertext)),
+        24 => cbc::Decryptor::<aes::Aes192>::new_from_slices(key, iv)
+            .map(|c| c.decrypt_padded_vec_mut::<Pkcs7>(ciphertext)),
+        32 => cbc::Decryptor::<aes::Aes256>::new_from_slices(key, iv)
+            .map(|c| c.decrypt_padded_vec_mut::<Pkcs7>(ciphertext)),
+        n => return Err(js_error(format!("Invalid AES key length: {} bytes", n))),
+    }
+    .map_err(|e| js_error(e.to_string()))?
+    .map_err(|_| js_error("AES-CBC decryption failed"))
+}
+
+fn aes_gcm_encrypt_base64(
+    _: &JsValue,
+    args: &[JsValue],
+    context: &mut Context,
+) -> JsResult<JsValue> {
+    let plaintext = arg_string(args, 0, context)?;
+    let key = decode_base64(&arg_string(args, 1, context)?)?;
+    let iv = arg_optional_string(args, 2, context)?
+        .map(|iv| decode_base64(&iv))
+        .transpose()?;
+    let combined = aes_gcm_encrypt(&key, iv, plaintext.as_bytes())?;
+    Ok(string_value(STANDARD.encode(combined)))
+}
+
+fn aes_gcm_decrypt_base64(
+    _: &JsValue,
+    args: &[JsValue],
+    context: &mut Context,
+) -> JsResult<JsValue> {
+    let combined = decode_base64(&arg_string(args, 0, context)?)?;
+    let key = decode_base64(&arg_string(args, 1, context)?)?;
+    let plaintext = aes_gcm_decrypt(&key, &combined)?;
+    Ok(string_value(
+        String::from_utf8_lossy(&plaintext).into_owned(),
+    ))
+}
+
+fn aes_cbc_encrypt_base64(
+    _: &JsValue,
+    args: &[JsValue],
+    context: &mut Context,
+) -> JsResult<JsValue> {
+    let plaintext = arg_string(args, 0, context)?;
+    let key = decode_base64(&arg_string(args, 1, context)?)?;
+    let iv = arg_optional_string(args, 2, context)?
+        .map(|iv| decode_base64(&iv))
+        .transpose()?;
+    let combined = aes_cbc_encrypt(&key, iv, plaintext.as_bytes())?;
+    Ok(string_value(STANDARD.encode(combined)))
+}
+
+fn aes_cbc_decrypt_base64(
+    _: &JsValue,
+    args: &[JsValue],
+    context: &mut Context,
+) -> JsResult<JsValue> {
+    let combined = decode_base64(&arg_string(args, 0, context)?)?;
+    let key = decode_base64(&arg_string(args, 1, context)?)?;
+    let plaintext = aes_cbc_decrypt(&key, &combined)?;
+    Ok(string_value(
+        String::from_utf8_lossy(&plaintext).into_owned(),
+    ))
+}
+
+fn aes_gcm_encrypt_hex(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let plaintext = arg_string(args, 0, context)?;
+    let key = decode_hex(&arg_string(args, 1, context)?)?;
+    let iv = arg_optional_string(args, 2, context)?
+        .map(|iv| decode_hex(&iv))
+        .transpose()?;
+    let combined = aes_gcm_encrypt(&key, iv, plaintext.as_bytes())?;
+    Ok(string_value(hex::encode(combined)))
+}
+
+fn aes_gcm_decrypt_hex(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let combined = decode_hex(&arg_string(args, 0, context)?)?;
+    let key = decode_hex(&arg_string(args, 1, context)?)?;
+    let plaintext = aes_gcm_decrypt(&key, &combined)?;
+    Ok(string_value(
+        String::from_utf8_lossy(&plaintext).into_owned(),
+    ))
+}
+
+fn aes_cbc_encrypt_hex(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let plaintext = arg_string(args, 0, context)?;
+    let key = decode_hex(&arg_string(args, 1, context)?)?;
+    let iv = arg_optional_string(args, 2, context)?
+        .map(|iv| decode_hex(&iv))
+        .transpose()?;
+    let combined = aes_cbc_encrypt(&key, iv, plaintext.as_bytes())?;
+    Ok(string_value(hex::encode(combined)))
+}
+
+fn aes_cbc_decrypt_hex(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let combined = decode_hex(&arg_string(args, 0, context)?)?;
+    let key = decode_hex(&arg_string(args, 1, context)?)?;
+    let plaintext = aes_cbc_decrypt(&key, &combined)?;
+    Ok(string_value(
+        String::from_utf8_lossy(&plaintext).into_owned(),
+    ))
+}
+
+/// 按 UTF-16 编码单元异或，与浏览器中的实现保持一致
+fn xor(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let data = arg_string(args, 0, context)?;
+    let key: Vec<u16> = arg_string(args, 1, context)?.encode_utf16().collect();
+    if key.is_empty() {
+        return Ok(string_value(data));
+    }
+    let units: Vec<u16> = data
+        .encode_utf16()
+        .zip(key.iter().cycle())
+        .map(|(unit, k)| unit ^ k)
+        .collect();
+    Ok(JsString::from(units.as_slice()).into())
+}
+
+/// 按 UTF-16 编码单元的低 8 位计算 CRC32，与浏览器中的实现保持一致
+fn crc32(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let bytes: Vec<u8> = arg_string(args, 0, context)?
+        .encode_utf16()
+        .map(|unit| unit as u8)
+        .collect();
+    Ok(string_value(format!("{:08x}", crc32fast::hash(&bytes))))
+}
+
+/// Latin-1 字符串编码为 Base64
+fn btoa(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let text = arg_string(args, 0, context)?;
+    let bytes = text
+        .chars()
+        .map(|c| u8::try_from(u32::from(c)))
+        .collect::<Result<Vec<u8>, _>>()
+        .map_err(|_| js_error("btoa: string contains characters outside of the Latin1 range"))?;
+    Ok(string_value(STANDARD.encode(bytes)))
+}
+
+/// Base64 解码为 Latin-1 字符串
+fn atob(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let bytes = decode_base64(&arg_string(args, 0, context)?)?;
+    Ok(string_value(bytes.into_iter().map(char::from).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::script::{execute, ScriptLimits};
+
+    fn run(code: &str) -> String {
+        execute(code, "", "t", &HashMap::new(), &ScriptLimits::default())
+            .unwrap()
+            .payload
+    }
+
+    #[test]
+    fn crc32_hashes_utf16_code_units() {
+        assert_eq!(run("return crypto.crc32('hello')"), "3610a686");
+        assert_eq!(run("return crypto.crc32('héllo')"), "919faf05");
+        assert_eq!(run("return crypto.crc32('中文')"), "122f5e93");
+        assert_eq!(run("return crypto.crc32('a😀')"), "dcbc30bb");
+    }
+}
@@ -0,0 +1,239 @@
+//! 限制内置函数一次调用能生成的数据量
+//!
+//! 引擎无法限制单次原生调用内的内存分配和耗时，`'x'.repeat(1e9)`、`Array(1e9).fill(0)`
+//! 这类调用不消耗指令数。这里在脚本运行前用带长度检查的版本替换这些内置函数：
+//! 结果超出长度上限时抛出 RangeError，不会进入原生实现。
+
+use boa_engine::{Context, JsNativeError, JsResult, JsValue, Source};
+
+use super::ScriptLimits;
+
+/// 替换内置函数的脚本，参数为字符串和数组的长度上限
+const GUARD: &str = r#"
+(function (maxString, maxArray) {
+  'use strict';
+  const OriginalArray = Array;
+  const ArrayProto = OriginalArray.prototype;
+  const StringProto = String.prototype;
+  const apply = Reflect.apply;
+  const construct = Reflect.construct;
+  const isArray = OriginalArray.isArray;
+
+  const define = (target, name, value) =>
+    Object.defineProperty(target, name, { value, writable: true, configurable: true });
+  const count = (value) => {
+    const n = Math.trunc(Number(value));
+    return n > 0 ? n : 0;
+  };
+  const lengthOf = (value) => (value == null ? 0 : count(Object(value).length));
+  const checkString = (length) => {
+    if (length > maxString) {
+      throw new RangeError('String length ' + length + ' exceeds the script limit of ' + maxString);
+    }
+  };
+  const checkArray = (length) => {
+    if (length > maxArray) {
+      throw new RangeError('Array length ' + length + ' exceeds the script limit of ' + maxArray);
+    }
+  };
+
+  // 字符串：按结果长度检查
+  const repeat = StringProto.repeat;
+  define(StringProto, 'repeat', function (...args) {
+    checkString(String(this).length * count(args[0]));
+    return apply(repeat, this, args);
+  });
+  for (const name of ['padStart', 'padEnd']) {
+    const pad = StringProto[name];
+    define(StringProto, name, function (...args) {
+      checkString(count(args[0]));
+      return apply(pad, this, args);
+    });
+  }
+  const concat = StringProto.concat;
+  define(StringProto, 'concat', function (...args) {
+    let length = String(this).length;
+    const parts = [];
+    for (let i = 0; i < args.length; i++) {
+      parts[i] = String(args[i]);
+      length += parts[i].length;
+    }
+    checkString(length);
+    return apply(concat, this, parts);
+  });
+
+  // 数组方法：调用前检查数组（或类数组）长度和调用后的长度
+  const growth = Object.assign(Object.create(null), {
+    push: (length, args) => length + args.length,
+    unshift: (length, args) => length + args.length,
+    splice: (length, args) => length + Math.max(args.length - 2, 0),
+    concat: (length, args) => {
+      let total = length;
+      for (let i = 0; i < args.length; i++) {
+        total += isArray(args[i]) ? lengthOf(args[i]) : 1;
+      }
+      return total;
+    },
+  });
+  for (const name of Object.getOwnPropertyNames(ArrayProto)) {
+    const method = ArrayProto[name];
+    if (name === 'constructor' || typeof method !== 'function') {
+      continue;
+    }
+    const grow = growth[name];
+    define(ArrayProto, name, function (...args) {
+      const length = lengthOf(this);
+      checkArray(grow ? grow(length, args) : length);
+      if (name === 'join') {
+        const separator = args[0] === undefined ? 1 : String(args[0]).length;
+        checkString(Math.max(length - 1, 0) * separator);
+      }
+      return apply(method, this, args);
+    });
+  }
+
+  // Array 构造函数：Array(n) 检查长度，其余行为不变（包括 instanceof 和子类）
+  const GuardedArray = function Array(...args) {
+    if (args.length === 1 && typeof args[0] === 'number') {
+      checkArray(args[0]);
+    }
+    return construct(OriginalArray, args, new.target || GuardedArray);
+  };
+  GuardedArray.prototype = ArrayProto;
+  define(GuardedArray, 'isArray', isArray);
+  define(GuardedArray, 'of', OriginalArray.of);
+  const from = OriginalArray.from;
+  define(GuardedArray, 'from', function (...args) {
+    checkArray(lengthOf(args[0]));
+    return apply(from, this, args);
+  });
+  Object.defineProperty(GuardedArray, Symbol.species, {
+    get() { return this; },
+    configurable: true,
+  });
+  define(ArrayProto, 'constructor', GuardedArray);
+  define(globalThis, 'Array', GuardedArray);
+
+  // 把类数组展开为参数列表的函数
+  const checkArguments = (target, name, index) => {
+    const original = target[name];
+    define(target, name, function (...args) {
+      checkArray(lengthOf(args[index]));
+      return apply(original, this, args);
+    });
+  };
+  checkArguments(Function.prototype, 'apply', 1);
+  checkArguments(Reflect, 'apply', 2);
+  checkArguments(Reflect, 'construct', 1);
+
+  // JSON.stringify 会遍历到数组的 length，逐个检查序列化的数组
+  const stringify = JSON.stringify;
+  define(JSON, 'stringify', function (value, replacer, space) {
+    if (isArray(replacer)) {
+      // 属性白名单无法和检查函数同时使用，只检查顶层的数组
+      checkArray(lengthOf(value));
+      return apply(stringify, this, [value, replacer, space]);
+    }
+    const check = function (key, item) {
+      const result = typeof replacer === 'function' ? apply(replacer, this, [key, item]) : item;
+      if (isArray(result)) {
+        checkArray(lengthOf(result));
+      }
+      return result;
+    };
+    return apply(stringify, this, [value, check, space]);
+  });
+})
+"#;
+
+/// 在脚本运行前替换内置函数
+pub(super) fn install(context: &mut Context, limits: &ScriptLimits) -> JsResult<()> {
+    let guard = context.eval(Source::from_bytes(GUARD))?;
+    let guard = guard
+        .as_callable()
+        .cloned()
+        .ok_or_else(|| JsNativeError::typ().with_message("Script guard is not callable"))?;
+    guard.call(
+        &JsValue::undefined(),
+        &[
+            (limits.max_string_length as f64).into(),
+            (limits.max_array_length as f64).into(),
+        ],
+        context,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::script::{execute, ScriptLimits};
+
+    fn run(code: &str) -> Result<String, String> {
+        execute(code, "", "t", &HashMap::new(), &ScriptLimits::default())
+            .map(|output| output.payload)
+            .map_err(|e| e.message)
+    }
+
+    fn assert_limited(code: &str, message: &str) {
+        let error = run(code).unwrap_err();
+        assert!(error.starts_with("RangeError: "), "{}: {}", code, error);
+        assert!(error.contains(message), "{}: {}", code, error);
+    }
+
+    #[test]
+    fn rejects_oversized_strings() {
+        for code in [
+            "return 'x'.repeat(1e9)",
+            "return 'x'.padStart(1e9)",
+            "return 'x'.padEnd(1e9, 'ab')",
+            "const a = 'x'.repeat(4e6); return a.concat(a, a)",
+            "return ['a', 'b'].join('x'.repeat(8.5e6))",
+        ] {
+            assert_limited(code, "String length");
+        }
+    }
+
+    #[test]
+    fn rejects_oversized_arrays() {
+        for code in [
+            "return new Array(1e9)",
+            "return Array(1e9).fill(0)",
+            "return Array.from({ length: 1e9 })",
+            "const a = [1]; a.length = 1e9; return a.fill(0)",
+            "const a = [1]; a.length = 1e9; return a.join(',')",
+            "const a = [1]; a.length = 1e9; return String(a)",
+            "const a = [1]; a.length = 1e9; return JSON.stringify({ a })",
+            "const a = [1]; a.length = 1e9; return Math.max.apply(null, a)",
+            "return Array.prototype.join.call({ length: 1e9 })",
+        ] {
+            assert_limited(code, "Array length");
+        }
+    }
+
+    #[test]
+    fn keeps_builtin_behaviour() {
+        let code = r#"
+            class List extends Array {
+              sum() { return this.reduce((a, b) => a + b, 0); }
+            }
+            const list = List.from([1, 2, 3]);
+            return [
+              list instanceof List, list instanceof Array, [] instanceof Array,
+              Array.isArray(list), list.map((v) => v).constructor === List, list.sum(),
+              Array(3).length, new Array(1, 2).length, Array.of(7).length,
+              [1, [2, [3]]].flat(Infinity).join('-'), [3, 1, 2].sort().join(''),
+              JSON.stringify({ a: [1, 2] }), JSON.stringify({ a: 1, b: 2 }, ['a']),
+              JSON.stringify({ a: 1 }, (k, v) => typeof v === 'number' ? v + 1 : v),
+              'ab'.concat('c', 1), 'a'.padStart(3, '-'), 'ab'.repeat(2),
+              Math.max.apply(null, [1, 5, 2]), Array.from('abc').join('+'),
+            ].join('|');
+        "#;
+        assert_eq!(
+            run(code).unwrap(),
+            "true|true|true|true|true|6|3|2|1|1-2-3|123|{\"a\":[1,2]}|{\"a\":1}|{\"a\":2}\
+             |abc1|--a|abab|5|a+b+c"
+        );
+    }
+}
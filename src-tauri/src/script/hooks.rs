@@ -0,0 +1,332 @@
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::oneshot;
+
+use super::{execute, log_error, run_blocking, ScriptOutput};
+use crate::db::models::{MessageHistory, PublishPayload, Script};
+use crate::db::Storage;
+use crate::env;
+use crate::mqtt::topic::matches_filter;
+use crate::mqtt::tracker::Delivery;
+use crate::mqtt::MqttManager;
+
+/// 单个自动回复脚本在 RESPONDER_WINDOW 内最多触发的次数，防止两端的自动回复互相触发
+const RESPONDER_MAX_RUNS: usize = 10;
+const RESPONDER_WINDOW: Duration = Duration::from_secs(1);
+
+/// 各自动回复脚本最近的触发记录
+static RESPONDER_RUNS: LazyLock<Mutex<HashMap<i64, ResponderRuns>>> =
+    LazyLock::new(Default::default);
+
+#[derive(Default)]
+struct ResponderRuns {
+    /// 窗口内的触发时间
+    times: VecDeque<Instant>,
+    /// 是否已提示超出频率限制，恢复触发后重置
+    limited: bool,
+}
+
+/// 脚本发出消息的事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptPublished {
+    pub server_id: i64,
+    pub script_id: Option<i64>,
+    pub script_name: String,
+    pub script_type: String,
+    pub message: Option<MessageHistory>,
+    pub error: Option<String>,
+}
+
+/// 脚本返回的待发布消息
+#[derive(Debug, Deserialize)]
+struct ScriptMessage {
+    topic: String,
+    #[serde(default)]
+    payload: Value,
+    #[serde(default)]
+    qos: i32,
+    #[serde(default)]
+    retain: bool,
+}
+
+/// 依次执行消息过滤脚本，返回消息的新主题；返回 None 表示丢弃
+///
+/// 脚本返回 false 或 `{ drop: true }` 丢弃消息，返回 `{ topic }` 将消息归入新主题，
+/// 其他返回值保留消息。
+pub async fn apply_filters(
+    storage: &Storage,
+    server_id: i64,
+    topic: &str,
+    payload: &str,
+) -> Result<Option<String>, String> {
+    let scripts = storage.get_enabled_scripts(server_id, "message_filter");
+    if scripts.is_empty() {
+        return Ok(Some(topic.to_string()));
+    }
+
+    let env = env::variables(storage, server_id);
+    let topic = topic.to_string();
+    let payload = payload.to_string();
+    run_blocking(scripts.len(), move |limits| {
+        let mut topic = topic;
+        for script in &scripts {
+            if !applies_to(script, &topic) {
+                continue;
+            }
+            let output = execute(&script.code, &payload, &topic, &env, limits)
+                .map_err(|e| format!("Script \"{}\" failed: {}", script.name, e))?;
+            match output.value {
+                Value::Bool(false) => return Ok(None),
+                Value::Object(result) => {
+                    if result.get("drop") == Some(&Value::Bool(true)) {
+                        return Ok(None);
+                    }
+                    if let Some(routed) = result.get("topic").and_then(Value::as_str) {
+                        topic = routed.to_string();
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(Some(topic))
+    })
+    .await?
+}
+
+/// 执行主题匹配的自动回复脚本并发布回复消息
+pub async fn respond(app_handle: &AppHandle, server_id: i64, topic: &str, payload: &str) {
+    let Some(storage) = app_handle.try_state::<Storage>() else {
+        return;
+    };
+    let scripts: Vec<Script> = storage
+        .get_enabled_scripts(server_id, "auto_responder")
+        .into_iter()
+        .filter(|s| applies_to(s, topic))
+        .filter(|s| match s.id.map_or(Ok(()), throttle) {
+            Ok(()) => true,
+            Err(notify) => {
+                if notify {
+                    let error = format!(
+                        "Auto responder triggered more than {} times within {} ms, messages are ignored",
+                        RESPONDER_MAX_RUNS,
+                        RESPONDER_WINDOW.as_millis()
+                    );
+                    emit(app_handle, server_id, s, None, Some(error));
+                }
+                false
+            }
+        })
+        .collect();
+    if scripts.is_empty() {
+        return;
+    }
+
+    let env = env::variables(&storage, server_id);
+    run_and_publish(app_handle, server_id, scripts, topic, payload, env).await;
+}
+
+/// 记录自动回复脚本的一次触发；超出频率限制时返回 Err，首次超限时为 Err(true)
+fn throttle(script_id: i64) -> Result<(), bool> {
+    let now = Instant::now();
+    let mut runs = RESPONDER_RUNS.lock();
+    let runs = runs.entry(script_id).or_default();
+    while runs
+        .times
+        .front()
+        .is_some_and(|t| now.duration_since(*t) >= RESPONDER_WINDOW)
+    {
+        runs.times.pop_front();
+    }
+
+    if runs.times.len() >= RESPONDER_MAX_RUNS {
+        let notify = !runs.limited;
+        runs.limited = true;
+        return Err(notify);
+    }
+    runs.times.push_back(now);
+    runs.limited = false;
+    Ok(())
+}
+
+/// 执行连接建立（on_connect）或断开前（on_disconnect）的脚本，返回所发布消息的投递结果
+pub async fn run_connection_hooks(
+    app_handle: &AppHandle,
+    server_id: i64,
+    script_type: &str,
+) -> Vec<oneshot::Receiver<Delivery>> {
+    let Some(storage) = app_handle.try_state::<Storage>() else {
+        return Vec::new();
+    };
+    let scripts = storage.get_enabled_scripts(server_id, script_type);
+    if scripts.is_empty() {
+        return Vec::new();
+    }
+
+    let env = env::variables(&storage, server_id);
+    run_and_publish(app_handle, server_id, scripts, "", "", env).await
+}
+
+/// 执行脚本并发布每个脚本返回的消息
+async fn run_and_publish(
+    app_handle: &AppHandle,
+    server_id: i64,
+    scripts: Vec<Script>,
+    topic: &str,
+    payload: &str,
+    env: std::collections::HashMap<String, String>,
+) -> Vec<oneshot::Receiver<Delivery>> {
+    let count = scripts.len();
+    let (topic, payload) = (topic.to_string(), payload.to_string());
+    let results = run_blocking(count, move |limits| {
+        scripts
+            .into_iter()
+            .map(|script| {
+                let output = execute(&script.code, &payload, &topic, &env, limits)
+                    .map_err(|e| format!("Script \"{}\" failed: {}", script.name, e));
+                (script, output)
+            })
+            .collect::<Vec<_>>()
+    })
+    .await;
+
+    let results = match results {
+        Ok(results) => results,
+        Err(e) => {
+            log_error(app_handle, "Connection or responder scripts failed", &e);
+            return Vec::new();
+        }
+    };
+
+    let mut receivers = Vec::new();
+    for (script, output) in results {
+        let messages = output.and_then(|output| script_messages(&script, &output));
+        match messages {
+            Ok(messages) => {
+                for message in messages {
+                    if let Some(receiver) = publish(app_handle, server_id, &script, message).await {
+                        receivers.push(receiver);
+                    }
+                }
+            }
+            Err(e) => {
+                log_error(
+                    app_handle,
+                    &format!("Script \"{}\" ({}) failed", script.name, script.script_type),
+                    &e,
+                );
+                emit(app_handle, server_id, &script, None, Some(e));
+            }
+        }
+    }
+    receivers
+}
+
+/// 通过 MqttManager 发布脚本返回的消息并通知前端
+async fn publish(
+    app_handle: &AppHandle,
+    server_id: i64,
+    script: &Script,
+    message: PublishPayload,
+) -> Option<oneshot::Receiver<Delivery>> {
+    let (Some(storage), Some(mqtt_manager)) = (
+        app_handle.try_state::<Storage>(),
+        app_handle.try_state::<MqttManager>(),
+    ) else {
+        return None;
+    };
+
+    match mqtt_manager
+        .publish_message(&storage, server_id, message)
+        .await
+    {
+        Ok((history, receiver)) => {
+            emit(app_handle, server_id, script, Some(history), None);
+            Some(receiver)
+        }
+        Err(e) => {
+            emit(app_handle, server_id, script, None, Some(e));
+            None
+        }
+    }
+}
+
+/// 将脚本返回值解析为待发布的消息：对象表示一条消息，数组表示多条，其他值表示不发布
+fn script_messages(script: &Script, output: &ScriptOutput) -> Result<Vec<PublishPayload>, String> {
+    let values = match &output.value {
+        Value::Object(_) => vec![output.value.clone()],
+        Value::Array(values) => values.clone(),
+        _ => return Ok(Vec::new()),
+    };
+
+    values
+        .into_iter()
+        .map(|value| {
+            let message: ScriptMessage = serde_json::from_value(value)
+                .map_err(|e| format!("Invalid message returned by script: {}", e))?;
+            if !(0..=2).contains(&message.qos) {
+                return Err(format!("Invalid QoS {} returned by script", message.qos));
+            }
+            // 回复主题再次命中自身会无限循环
+            if script.script_type == "auto_responder"
+                && script
+                    .topic_filter
+                    .as_deref()
+                    .is_some_and(|filter| matches_filter(filter, &message.topic))
+            {
+                return Err(format!(
+                    "Reply topic {} matches the responder's own topic filter",
+                    message.topic
+                ));
+            }
+
+            let payload = match message.payload {
+                Value::Null => String::new(),
+                Value::String(text) => text,
+                other => other.to_string(),
+            };
+            Ok(PublishPayload {
+                topic: message.topic,
+                payload,
+                qos: message.qos,
+                retain: message.retain,
+                format: "text".to_string(),
+                properties: None,
+                await_ack: false,
+                ack_timeout_ms: None,
+            })
+        })
+        .collect()
+}
+
+/// 脚本的主题过滤器是否匹配，未设置时匹配所有主题
+fn applies_to(script: &Script, topic: &str) -> bool {
+    script
+        .topic_filter
+        .as_deref()
+        .is_none_or(|filter| matches_filter(filter, topic))
+}
+
+fn emit(
+    app_handle: &AppHandle,
+    server_id: i64,
+    script: &Script,
+    message: Option<MessageHistory>,
+    error: Option<String>,
+) {
+    let _ = app_handle.emit(
+        "script-published",
+        ScriptPublished {
+            server_id,
+            script_id: script.id,
+            script_name: script.name.clone(),
+            script_type: script.script_type.clone(),
+            message,
+            error,
+        },
+    );
+}
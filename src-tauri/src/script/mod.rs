@@ -0,0 +1,586 @@
+pub mod crypto;
+mod guard;
+pub mod hooks;
+
+use boa_engine::builtins::promise::PromiseState;
+use boa_engine::context::HostHooks;
+use boa_engine::object::builtins::JsPromise;
+use boa_engine::property::Attribute;
+use boa_engine::{js_string, Context, JsError, JsString, JsValue, Source};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+use crate::db::models::Script;
+use crate::log::{LogEntry, LogManager};
+use crate::mqtt::topic;
+
+/// 支持的脚本类型
+pub const SCRIPT_TYPES: &[&str] = &[
+    "before_publish",
+    "after_receive",
+    "message_filter",
+    "auto_responder",
+    "on_connect",
+    "on_disconnect",
+];
+
+/// 脚本运行限制
+///
+/// 指令数、循环、调用深度和 ArrayBuffer 大小由引擎在执行时检查，超出后脚本立即终止；
+/// 字符串和数组长度由替换后的内置函数（guard 模块）在调用前检查。
+/// 引擎不支持限制总内存：用 `+` 反复拼接字符串或在循环中逐个添加元素仍可能占用较多内存，
+/// 这类增长由指令数间接限制。
+#[derive(Debug, Clone, Copy)]
+pub struct ScriptLimits {
+    /// 单个脚本的最长执行时间
+    pub timeout: Duration,
+    /// 单个脚本最多执行的虚拟机指令数（包括被调用的函数），不可被 try/catch 捕获
+    pub instruction_limit: usize,
+    /// 同一函数内循环的最大迭代次数
+    pub loop_iteration_limit: u64,
+    /// 最大调用深度
+    pub recursion_limit: usize,
+    /// 虚拟机栈的最大长度
+    pub stack_size_limit: usize,
+    /// 输入和输出 payload 的最大字节数
+    pub max_payload_bytes: usize,
+    /// 内置函数一次生成的字符串的最大长度（UTF-16 单元）
+    pub max_string_length: usize,
+    /// 内置函数处理和生成的数组的最大长度
+    pub max_array_length: usize,
+}
+
+impl Default for ScriptLimits {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(1),
+            instruction_limit: 5_000_000,
+            loop_iteration_limit: 1_000_000,
+            recursion_limit: 256,
+            stack_size_limit: 64 * 1024,
+            max_payload_bytes: 1024 * 1024,
+            max_string_length: 8 * 1024 * 1024,
+            max_array_length: 1024 * 1024,
+        }
+    }
+}
+
+/// 单个 ArrayBuffer 的最大字节数，与 payload 上限相同
+const MAX_BUFFER_BYTES: u64 = 1024 * 1024;
+/// 超时后仍未结束的脚本线程数
+static STALLED_SCRIPTS: AtomicUsize = AtomicUsize::new(0);
+/// 超时线程达到该数量后拒绝执行新的脚本，避免失控的脚本占满阻塞线程池
+const MAX_STALLED_SCRIPTS: usize = 2;
+
+/// 脚本引擎的宿主钩子：限制 ArrayBuffer 大小
+struct ScriptHooks;
+
+impl HostHooks for ScriptHooks {
+    fn max_buffer_size(&self, _context: &mut Context) -> u64 {
+        MAX_BUFFER_BYTES
+    }
+}
+
+/// 包装函数在脚本代码之前占用的行数
+const WRAPPER_LINES: u32 = 1;
+
+/// 脚本运行环境：env 对象和捕获输出的 console
+const PRELUDE: &str = r#"
+const env = (function (data) {
+  return Object.assign({}, data, {
+    get: (name) => data[name],
+    replace: (text) => {
+      if (!text) return text;
+      let result = String(text);
+      for (const name of Object.keys(data)) {
+        result = result.split('{{' + name + '}}').join(data[name]);
+      }
+      return result;
+    },
+    all: () => Object.keys(data).map((name) => ({ name, value: data[name] })),
+  });
+})(__env);
+
+const __logs = [];
+const console = (function () {
+  const format = (args) => args
+    .map((arg) => typeof arg === 'string' ? arg : JSON.stringify(arg))
+    .join(' ');
+  return {
+    log: (...args) => { __logs.push(format(args)); },
+    info: (...args) => { __logs.push(format(args)); },
+    warn: (...args) => { __logs.push('[warn] ' + format(args)); },
+    error: (...args) => { __logs.push('[error] ' + format(args)); },
+  };
+})();
+"#;
+
+/// 脚本执行结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptOutput {
+    pub payload: String,
+    /// 脚本的原始返回值，undefined 时为 null
+    pub value: serde_json::Value,
+    /// 脚本通过 console 输出的日志
+    pub logs: Vec<String>,
+}
+
+/// 脚本错误，语法错误带有在脚本代码中的行列号
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptError {
+    pub message: String,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    /// 出错前脚本输出的日志
+    #[serde(skip)]
+    pub logs: Vec<String>,
+}
+
+impl ScriptError {
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            line: None,
+            column: None,
+            logs: Vec::new(),
+        }
+    }
+}
+
+impl std::fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => {
+                write!(f, "{} (line {}, column {})", self.message, line, column)
+            }
+            _ => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// 执行脚本并返回处理后的 payload
+///
+/// 脚本可以定义 `process(payload, topic)`（支持 async），其返回值作为新的 payload；
+/// 对象会序列化为 JSON，返回 undefined / null 时保持原样。
+pub fn execute(
+    code: &str,
+    payload: &str,
+    topic: &str,
+    env: &HashMap<String, String>,
+    limits: &ScriptLimits,
+) -> Result<ScriptOutput, ScriptError> {
+    if payload.len() > limits.max_payload_bytes {
+        return Err(ScriptError::new(format!(
+            "Payload exceeds the script limit of {} bytes",
+            limits.max_payload_bytes
+        )));
+    }
+
+    let mut context = match Context::builder()
+        .host_hooks(&ScriptHooks)
+        .instructions_remaining(limits.instruction_limit)
+        .build()
+    {
+        Ok(context) => context,
+        Err(e) => return Err(ScriptError::new(e.to_string())),
+    };
+    let runtime_limits = context.runtime_limits_mut();
+    runtime_limits.set_loop_iteration_limit(limits.loop_iteration_limit);
+    runtime_limits.set_recursion_limit(limits.recursion_limit);
+    runtime_limits.set_stack_size_limit(limits.stack_size_limit);
+
+    let result = guard::install(&mut context, limits)
+        .and_then(|_| run(&mut context, code, payload, topic, env));
+    let logs = captured_logs(&mut context);
+    let (payload, value) = result.map_err(|e| ScriptError {
+        logs: logs.clone(),
+        ..script_error(e, &mut context)
+    })?;
+    if payload.len() > limits.max_payload_bytes {
+        return Err(ScriptError {
+            logs,
+            ..ScriptError::new(format!(
+                "Script output exceeds the limit of {} bytes",
+                limits.max_payload_bytes
+            ))
+        });
+    }
+    Ok(ScriptOutput {
+        payload,
+        value,
+        logs,
+    })
+}
+
+fn run(
+    context: &mut Context,
+    code: &str,
+    payload: &str,
+    topic: &str,
+    env: &HashMap<String, String>,
+) -> Result<(String, serde_json::Value), JsError> {
+    crypto::register(context)?;
+    let env = serde_json::to_value(env).unwrap_or_default();
+    let env = JsValue::from_json(&env, context)?;
+    context.register_global_property(js_string!("__env"), env, Attribute::all())?;
+    context.eval(Source::from_bytes(PRELUDE))?;
+
+    // 脚本代码作为 async 函数体执行
+    let wrapped = format!(
+        "(async function (payload, topic) {{ \"use strict\";\n{}\n\
+         ;if (typeof process === 'function') {{ return await process(payload, topic); }}\n\
+         return payload;\n}})",
+        code
+    );
+
+    let function = context.eval(Source::from_bytes(&wrapped))?;
+    let function = function
+        .as_callable()
+        .ok_or_else(|| JsError::from_opaque(js_string!("Script wrapper is not callable").into()))?
+        .clone();
+    let promise = function.call(
+        &JsValue::undefined(),
+        &[JsString::from(payload).into(), JsString::from(topic).into()],
+        context,
+    )?;
+    context.run_jobs();
+
+    let value = match promise.as_object().cloned().map(JsPromise::from_object) {
+        Some(Ok(promise)) => match promise.state() {
+            PromiseState::Fulfilled(value) => value,
+            PromiseState::Rejected(reason) => return Err(JsError::from_opaque(reason)),
+            PromiseState::Pending => {
+                return Err(JsError::from_opaque(
+                    js_string!("Script did not finish (pending promise)").into(),
+                ))
+            }
+        },
+        _ => promise,
+    };
+
+    if value.is_null_or_undefined() {
+        return Ok((payload.to_string(), serde_json::Value::Null));
+    }
+    if let Some(text) = value.as_string() {
+        let text = text.to_std_string_escaped();
+        return Ok((text.clone(), serde_json::Value::String(text)));
+    }
+    let json = value.to_json(context)?;
+    if value.is_object() {
+        return Ok((json.to_string(), json));
+    }
+    Ok((value.to_string(context)?.to_std_string_escaped(), json))
+}
+
+/// 读取脚本通过 console 输出的日志
+fn captured_logs(context: &mut Context) -> Vec<String> {
+    context
+        .eval(Source::from_bytes("__logs"))
+        .and_then(|logs| logs.to_json(context))
+        .ok()
+        .and_then(|logs| serde_json::from_value(logs).ok())
+        .unwrap_or_default()
+}
+
+/// 取出 JS 异常的可读信息和位置
+fn script_error(error: JsError, context: &mut Context) -> ScriptError {
+    let message = match error.try_native(context) {
+        Ok(native) if native.is_no_instructions_remain() => {
+            "Script exceeded the instruction limit".to_string()
+        }
+        Ok(native) => native.to_string(),
+        Err(_) => error
+            .as_opaque()
+            .and_then(|v| v.to_string(context).ok())
+            .map(|s| s.to_std_string_escaped())
+            .unwrap_or_else(|| error.to_string()),
+    };
+    match error_position(&message) {
+        Some((message, line, column)) => ScriptError {
+            line: Some(line),
+            column: Some(column),
+            ..ScriptError::new(message)
+        },
+        None => ScriptError::new(message),
+    }
+}
+
+/// 拆分语法错误信息末尾的 "at line X, col Y"，并换算为脚本代码中的位置
+fn error_position(message: &str) -> Option<(&str, u32, u32)> {
+    let (message, position) = message.rsplit_once(" at line ")?;
+    let (line, column) = position.split_once(", col ")?;
+    let line: u32 = line.trim().parse().ok()?;
+    let column: u32 = column
+        .trim()
+        .trim_end_matches(|c: char| !c.is_ascii_digit())
+        .parse()
+        .ok()?;
+    // 包装函数占用第一行
+    Some((message, line.saturating_sub(WRAPPER_LINES).max(1), column))
+}
+
+/// 在阻塞线程中依次执行脚本，前一个脚本的输出作为下一个脚本的输入
+pub async fn run_scripts(
+    scripts: Vec<Script>,
+    payload: String,
+    topic: String,
+    env: HashMap<String, String>,
+) -> Result<String, String> {
+    if scripts.is_empty() {
+        return Ok(payload);
+    }
+
+    run_blocking(scripts.len(), move |limits| {
+        scripts.iter().try_fold(payload, |payload, script| {
+            execute(&script.code, &payload, &topic, &env, limits)
+                .map(|output| output.payload)
+                .map_err(|e| format!("Script \"{}\" failed: {}", script.name, e))
+        })
+    })
+    .await?
+}
+
+/// 在阻塞线程中执行 count 个脚本，总时长超过各脚本时限之和时返回错误
+///
+/// 超时后不再等待结果，但线程会继续运行到脚本触发引擎限制（通常是指令数）为止；
+/// 这段时间内线程计入 STALLED_SCRIPTS，数量过多时拒绝执行新的脚本。
+async fn run_blocking<T, F>(count: usize, f: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(&ScriptLimits) -> T + Send + 'static,
+{
+    run_blocking_with(ScriptLimits::default(), count, f).await
+}
+
+async fn run_blocking_with<T, F>(limits: ScriptLimits, count: usize, f: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(&ScriptLimits) -> T + Send + 'static,
+{
+    let stalled = STALLED_SCRIPTS.load(Ordering::SeqCst);
+    if stalled >= MAX_STALLED_SCRIPTS {
+        return Err(format!(
+            "{} timed-out scripts are still running, try again later",
+            stalled
+        ));
+    }
+
+    let timeout = limits.timeout * count.max(1) as u32;
+    // 线程结束和等待超时都会置位，后置位的一方负责更新计数
+    let done = Arc::new(AtomicBool::new(false));
+    let task = tokio::task::spawn_blocking({
+        let done = done.clone();
+        move || {
+            let result = f(&limits);
+            if done.swap(true, Ordering::SeqCst) {
+                STALLED_SCRIPTS.fetch_sub(1, Ordering::SeqCst);
+            }
+            result
+        }
+    });
+
+    match tokio::time::timeout(timeout, task).await {
+        Ok(Ok(result)) => Ok(result),
+        Ok(Err(e)) => Err(format!("Script execution aborted: {}", e)),
+        Err(_) => {
+            STALLED_SCRIPTS.fetch_add(1, Ordering::SeqCst);
+            if done.swap(true, Ordering::SeqCst) {
+                // 线程恰好在超时后结束
+                STALLED_SCRIPTS.fetch_sub(1, Ordering::SeqCst);
+            }
+            Err(format!(
+                "Script execution timed out after {} ms",
+                timeout.as_millis()
+            ))
+        }
+    }
+}
+
+/// 脚本测试请求：测试已保存的脚本，或未保存的代码（code 优先）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptTestRequest {
+    pub script_id: Option<i64>,
+    pub code: Option<String>,
+    /// 提供环境变量的 Server，默认使用脚本所属的 Server
+    pub server_id: Option<i64>,
+    pub topic: String,
+    pub payload: String,
+}
+
+/// 脚本测试结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptTestResult {
+    pub success: bool,
+    pub payload: Option<String>,
+    pub logs: Vec<String>,
+    /// 执行耗时（毫秒）
+    pub duration_ms: f64,
+    pub error: Option<ScriptError>,
+}
+
+/// 在独立的引擎实例中试运行脚本，不影响任何连接
+pub async fn test(
+    code: String,
+    payload: String,
+    topic: String,
+    env: HashMap<String, String>,
+) -> ScriptTestResult {
+    let started = Instant::now();
+    let (result, elapsed) = run_blocking(1, move |limits| {
+        let result = execute(&code, &payload, &topic, &env, limits);
+        (result, started.elapsed())
+    })
+    .await
+    .unwrap_or_else(|e| (Err(ScriptError::new(e)), started.elapsed()));
+
+    let duration_ms = elapsed.as_secs_f64() * 1000.0;
+    match result {
+        Ok(output) => ScriptTestResult {
+            success: true,
+            payload: Some(output.payload),
+            logs: output.logs,
+            duration_ms,
+            error: None,
+        },
+        Err(mut error) => ScriptTestResult {
+            success: false,
+            payload: None,
+            logs: std::mem::take(&mut error.logs),
+            duration_ms,
+            error: Some(error),
+        },
+    }
+}
+
+/// 检查脚本类型和主题过滤器
+pub fn validate_script(script_type: &str, topic_filter: Option<&str>) -> Result<(), String> {
+    if !SCRIPT_TYPES.contains(&script_type) {
+        return Err(format!("Unsupported script type: {}", script_type));
+    }
+    match topic_filter.filter(|f| !f.is_empty()) {
+        Some(filter) => topic::validate_filter(filter),
+        // 未限定主题的自动回复会响应自己发出的消息
+        None if script_type == "auto_responder" => {
+            Err("Auto-responder scripts require a topic filter".to_string())
+        }
+        None => Ok(()),
+    }
+}
+
+/// 写入脚本错误日志
+pub fn log_error(app_handle: &AppHandle, message: &str, details: &str) {
+    if let Some(log_manager) = app_handle.try_state::<LogManager>() {
+        let _ = log_manager.write_log(&LogEntry {
+            r#type: "script".to_string(),
+            message: message.to_string(),
+            details: Some(details.to_string()),
+            timestamp: chrono::Local::now().to_rfc3339(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 不断调用函数的脚本，只能由指令数限制终止
+    const RUNAWAY: &str = "function f() { for (let i = 0; i < 1000; i++) {} }\nfor (;;) { f(); }";
+
+    fn run(code: &str, limits: &ScriptLimits) -> Result<String, String> {
+        execute(code, "payload", "t", &HashMap::new(), limits)
+            .map(|output| output.payload)
+            .map_err(|e| e.message)
+    }
+
+    #[test]
+    fn runs_process_function() {
+        let code = "function process(payload, topic) { return { payload, topic }; }";
+        assert_eq!(
+            run(code, &ScriptLimits::default()).unwrap(),
+            r#"{"payload":"payload","topic":"t"}"#
+        );
+    }
+
+    #[test]
+    fn instruction_limit_stops_runaway_script() {
+        let limits = ScriptLimits {
+            instruction_limit: 100_000,
+            ..Default::default()
+        };
+        assert_eq!(
+            run(RUNAWAY, &limits).unwrap_err(),
+            "Script exceeded the instruction limit"
+        );
+
+        // 指令数限制不能被脚本捕获
+        let code = format!("try {{ {} }} catch (e) {{ return 'caught'; }}", RUNAWAY);
+        assert_eq!(
+            run(&code, &limits).unwrap_err(),
+            "Script exceeded the instruction limit"
+        );
+    }
+
+    #[test]
+    fn runtime_limits_stop_loops_and_recursion() {
+        let limits = ScriptLimits {
+            instruction_limit: usize::MAX,
+            ..Default::default()
+        };
+        let error = run("for (;;) {}", &limits).unwrap_err();
+        assert!(error.contains("loop"), "{}", error);
+        let error = run("function f() { return f(); } return f();", &limits).unwrap_err();
+        assert!(error.contains("recursive calls"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_oversized_payload() {
+        let limits = ScriptLimits {
+            max_payload_bytes: 4,
+            ..Default::default()
+        };
+        assert!(run("return 'ok'", &limits).is_err());
+    }
+
+    #[test]
+    fn timed_out_scripts_are_tracked_until_they_finish() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let limits = ScriptLimits {
+                timeout: Duration::from_millis(1),
+                instruction_limit: 2_000_000,
+                ..Default::default()
+            };
+            let runaway = |limits: &ScriptLimits| run(RUNAWAY, limits);
+
+            for _ in 0..MAX_STALLED_SCRIPTS {
+                let error = run_blocking_with(limits, 1, runaway).await.unwrap_err();
+                assert!(error.starts_with("Script execution timed out"), "{}", error);
+            }
+            // 超时的线程仍在运行时拒绝新的脚本
+            let error = run_blocking_with(limits, 1, |_| ()).await.unwrap_err();
+            assert!(
+                error.contains("timed-out scripts are still running"),
+                "{}",
+                error
+            );
+
+            // 线程触发指令数限制结束后恢复
+            let deadline = Instant::now() + Duration::from_secs(120);
+            while STALLED_SCRIPTS.load(Ordering::SeqCst) > 0 {
+                assert!(
+                    Instant::now() < deadline,
+                    "timed-out scripts did not finish"
+                );
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            let result =
+                run_blocking_with(ScriptLimits::default(), 1, |limits| run("return 1", limits))
+                    .await;
+            assert_eq!(result, Ok(Ok("1".to_string())));
+        });
+    }
+}
@@ -0,0 +1,605 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use super::{check_vault_for_import, unique_name, ImportIssue, ImportReport};
+use crate::db::models::{MqttServer, ReconnectPolicy, RetentionPolicy, Subscription};
+use crate::db::Storage;
+use crate::mqtt::topic;
+
+/// 支持导入连接配置的其他 MQTT 工具
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExternalSource {
+    /// MQTTX 导出的 JSON 数据
+    Mqttx,
+    /// MQTT Explorer 的 settings.json
+    MqttExplorer,
+}
+
+/// MQTT Explorer 保存连接列表的设置项
+const EXPLORER_CONNECTIONS_KEY: &str = "ConnectionManager_connections";
+/// MQTTX 的默认连接超时（秒），与默认值相同时不提示
+const MQTTX_DEFAULT_CONNECT_TIMEOUT: u64 = 10;
+
+/// 从外部工具解析出的连接
+struct ExternalConnection {
+    server: MqttServer,
+    subscriptions: Vec<Subscription>,
+    /// 无法导入的字段：(类型, 订阅主题, 说明)，主题为空表示连接本身
+    unsupported: Vec<(&'static str, Option<String>, String)>,
+}
+
+/// 从其他工具的导出文件导入连接配置，每个连接创建为新的 Server
+pub fn import_profiles(
+    storage: &Storage,
+    path: &Path,
+    source: ExternalSource,
+) -> Result<ImportReport, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let value: Value =
+        serde_json::from_str(&content).map_err(|e| format!("Invalid JSON file: {}", e))?;
+    let connections = match source {
+        ExternalSource::Mqttx => parse_mqttx(value)?,
+        ExternalSource::MqttExplorer => parse_mqtt_explorer(value)?,
+    };
+    if connections.is_empty() {
+        return Err("No connections found in file".to_string());
+    }
+
+    let has_secrets = connections.iter().flatten().any(|c| {
+        [&c.server.password, &c.server.client_key_password]
+            .into_iter()
+            .flatten()
+            .any(|value| !value.is_empty())
+    });
+    check_vault_for_import(storage, has_secrets)?;
+
+    let mut names: HashSet<String> = storage.get_servers().into_iter().map(|s| s.name).collect();
+    let mut report = ImportReport::default();
+    for connection in connections {
+        let connection = match connection {
+            Ok(connection) => connection,
+            Err(issue) => {
+                report.failed.push(issue);
+                continue;
+            }
+        };
+        import_connection(storage, connection, &mut names, &mut report);
+    }
+    Ok(report)
+}
+
+fn import_connection(
+    storage: &Storage,
+    connection: ExternalConnection,
+    names: &mut HashSet<String>,
+    report: &mut ImportReport,
+) {
+    let name = unique_name(&connection.server.name, names);
+    names.insert(name.clone());
+
+    let server = MqttServer {
+        name: name.clone(),
+        ..connection.server
+    };
+    let server_id = match storage.create_server(server) {
+        Ok(id) => id,
+        Err(e) => {
+            report.failed.push(issue("server", &name, None, &e));
+            return;
+        }
+    };
+    report.created.servers += 1;
+
+    for (kind, topic, reason) in connection.unsupported {
+        let issue = match topic {
+            Some(topic) => issue(kind, &topic, Some(&name), &reason),
+            None => issue(kind, &name, None, &reason),
+        };
+        report.warnings.push(issue);
+    }
+
+    for sub in connection.subscriptions {
+        let topic = sub.topic.clone();
+        let result = topic::validate_filter(&sub.topic)
+            .and_then(|_| match sub.qos {
+                0..=2 => Ok(()),
+                qos => Err(format!("Invalid QoS: {}", qos)),
+            })
+            .and_then(|_| storage.create_subscription(Subscription { server_id, ..sub }));
+        match result {
+            Ok(_) => report.created.subscriptions += 1,
+            Err(e) => report
+                .failed
+                .push(issue("subscription", &topic, Some(&name), &e)),
+        }
+    }
+}
+
+fn issue(kind: &str, name: &str, server: Option<&str>, reason: &str) -> ImportIssue {
+    ImportIssue {
+        kind: kind.to_string(),
+        name: name.to_string(),
+        server: server.map(str::to_string),
+        reason: reason.to_string(),
+    }
+}
+
+/// 使用默认设置的 Server 配置
+fn new_server(name: String, host: String, port: i32) -> MqttServer {
+    MqttServer {
+        id: None,
+        name,
+        host,
+        port,
+        protocol_version: "3.1.1".to_string(),
+        username: None,
+        password: None,
+        client_id: None,
+        keep_alive: 60,
+        clean_session: true,
+        use_tls: false,
+        ca_cert: None,
+        client_cert: None,
+        client_key: None,
+        client_key_password: None,
+        reconnect: ReconnectPolicy::default(),
+        save_received_messages: true,
+        retention: RetentionPolicy::default(),
+        created_at: None,
+        updated_at: None,
+    }
+}
+
+fn new_subscription(
+    topic: String,
+    qos: i32,
+    color: Option<String>,
+    is_active: bool,
+) -> Subscription {
+    Subscription {
+        id: None,
+        server_id: 0,
+        topic,
+        qos,
+        is_active,
+        color,
+        last_grant: None,
+        created_at: None,
+    }
+}
+
+/// 检查主机名不为空且端口在 1~65535 之间
+fn validate_address(host: &str, port: i32) -> Result<(), String> {
+    if host.trim().is_empty() {
+        return Err("Host must not be empty".to_string());
+    }
+    if !(1..=65535).contains(&port) {
+        return Err(format!("Invalid port: {}", port));
+    }
+    Ok(())
+}
+
+/// 去掉主机名中的协议前缀（如 `mqtt://`）
+fn strip_scheme(host: &str) -> String {
+    let host = host.split_once("://").map_or(host, |(_, rest)| rest);
+    host.trim_end_matches('/').to_string()
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|v| !v.trim().is_empty())
+}
+
+/// 无法导入的连接（名称取自原始数据）
+fn parse_error(value: &Value, reason: String) -> ImportIssue {
+    let name = value
+        .get("name")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    issue("server", name, None, &reason)
+}
+
+// ===== MQTTX =====
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MqttxConnection {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    host: String,
+    port: Option<i32>,
+    /// "mqtt" | "mqtts" | "ws" | "wss"
+    #[serde(default)]
+    protocol: Option<String>,
+    #[serde(default)]
+    client_id: Option<String>,
+    #[serde(default)]
+    client_id_with_time: bool,
+    #[serde(default)]
+    username: Option<String>,
+    #[serde(default)]
+    password: Option<String>,
+    #[serde(default)]
+    mqtt_version: Option<String>,
+    keepalive: Option<i32>,
+    clean: Option<bool>,
+    #[serde(default)]
+    ssl: bool,
+    #[serde(default)]
+    ca: Option<String>,
+    #[serde(default)]
+    cert: Option<String>,
+    #[serde(default)]
+    key: Option<String>,
+    reject_unauthorized: Option<bool>,
+    reconnect: Option<bool>,
+    reconnect_period: Option<u64>,
+    connect_timeout: Option<u64>,
+    #[serde(default)]
+    will: Option<MqttxWill>,
+    #[serde(default)]
+    properties: Option<Value>,
+    #[serde(default)]
+    subscriptions: Vec<MqttxSubscription>,
+    #[serde(default)]
+    messages: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MqttxWill {
+    #[serde(default)]
+    last_will_topic: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MqttxSubscription {
+    topic: String,
+    #[serde(default)]
+    qos: i32,
+    #[serde(default)]
+    color: Option<String>,
+    #[serde(default)]
+    disabled: bool,
+    #[serde(default)]
+    alias: Option<String>,
+    #[serde(default)]
+    nl: bool,
+    #[serde(default)]
+    rap: bool,
+    #[serde(default)]
+    rh: u8,
+    #[serde(default)]
+    subscription_identifier: Option<u32>,
+}
+
+/// 解析 MQTTX 导出的 JSON：连接数组、`{ "connections": [...] }` 或单个连接
+fn parse_mqttx(value: Value) -> Result<Vec<Result<ExternalConnection, ImportIssue>>, String> {
+    let items = match value {
+        Value::Array(items) => items,
+        Value::Object(mut map) => match map.remove("connections") {
+            Some(Value::Array(items)) => items,
+            _ if map.contains_key("host") => vec![Value::Object(map)],
+            _ => return Err("Not an MQTTX export file".to_string()),
+        },
+        _ => return Err("Not an MQTTX export file".to_string()),
+    };
+
+    let mut connections = Vec::new();
+    collect_mqttx(items, &mut connections);
+    Ok(connections)
+}
+
+/// 展开分组（isCollection）并解析其中的连接
+fn collect_mqttx(
+    items: Vec<Value>,
+    connections: &mut Vec<Result<ExternalConnection, ImportIssue>>,
+) {
+    for item in items {
+        if item.get("isCollection").and_then(Value::as_bool) == Some(true) {
+            if let Some(Value::Array(children)) = item.get("children") {
+                collect_mqttx(children.clone(), connections);
+            }
+            continue;
+        }
+        let connection = serde_json::from_value::<MqttxConnection>(item.clone())
+            .map_err(|e| format!("Invalid connection: {}", e))
+            .and_then(mqttx_connection)
+            .map_err(|e| parse_error(&item, e));
+        connections.push(connection);
+    }
+}
+
+fn mqttx_connection(c: MqttxConnection) -> Result<ExternalConnection, String> {
+    let protocol = c.protocol.as_deref().unwrap_or("mqtt");
+    if matches!(protocol, "ws" | "wss") {
+        return Err("WebSocket connections are not supported".to_string());
+    }
+    let mut unsupported = Vec::new();
+    let mut note = |field: &str, reason: &str| {
+        unsupported.push(("server", None, format!("{}: {}", field, reason)));
+    };
+
+    let use_tls = protocol == "mqtts" || c.ssl;
+    let default_port = if use_tls { 8883 } else { 1883 };
+    let host = strip_scheme(&c.host);
+    let port = c.port.unwrap_or(default_port);
+    validate_address(&host, port)?;
+    let mut server = new_server(c.name, host, port);
+    server.use_tls = use_tls;
+    server.client_id = non_empty(c.client_id);
+    server.username = non_empty(c.username);
+    server.password = non_empty(c.password);
+    server.keep_alive = c.keepalive.unwrap_or(60);
+    server.clean_session = c.clean.unwrap_or(true);
+    server.protocol_version = match c.mqtt_version.as_deref() {
+        Some("5.0") | Some("5") => "5.0".to_string(),
+        Some("3.1") => {
+            note("mqttVersion", "MQTT 3.1 is imported as 3.1.1");
+            "3.1.1".to_string()
+        }
+        _ => "3.1.1".to_string(),
+    };
+    if let Some(enabled) = c.reconnect {
+        server.reconnect.enabled = enabled;
+    }
+    if let Some(period) = c.reconnect_period.filter(|p| *p > 0) {
+        server.reconnect.initial_delay_ms = period;
+        server.reconnect.max_delay_ms = server.reconnect.max_delay_ms.max(period);
+    }
+
+    server.ca_cert = load_pem("ca", c.ca, &mut note);
+    server.client_cert = load_pem("cert", c.cert, &mut note);
+    server.client_key = load_pem("key", c.key, &mut note);
+
+    if c.client_id_with_time {
+        note(
+            "clientIdWithTime",
+            "appending a timestamp to the client ID is not supported",
+        );
+    }
+    if c.reject_unauthorized == Some(false) {
+        note(
+            "rejectUnauthorized",
+            "disabling certificate verification is not supported",
+        );
+    }
+    if c.connect_timeout
+        .is_some_and(|t| t != MQTTX_DEFAULT_CONNECT_TIMEOUT)
+    {
+        note("connectTimeout", "custom connect timeout is not supported");
+    }
+    if c.will
+        .and_then(|w| w.last_will_topic)
+        .is_some_and(|t| !t.is_empty())
+    {
+        note("will", "last will message is not supported");
+    }
+    if c.properties
+        .as_ref()
+        .and_then(Value::as_object)
+        .is_some_and(|p| p.values().any(|v| !v.is_null()))
+    {
+        note(
+            "properties",
+            "MQTT 5 connection properties are not supported",
+        );
+    }
+    if !c.messages.is_empty() {
+        note(
+            "messages",
+            &format!("{} messages are not imported", c.messages.len()),
+        );
+    }
+
+    let mut subscriptions = Vec::new();
+    for sub in c.subscriptions {
+        let mut options = Vec::new();
+        if sub.nl || sub.rap || sub.rh != 0 {
+            options.push("nl/rap/rh");
+        }
+        if sub.subscription_identifier.is_some() {
+            options.push("subscriptionIdentifier");
+        }
+        if sub.alias.as_deref().is_some_and(|a| !a.is_empty()) {
+            options.push("alias");
+        }
+        if !options.is_empty() {
+            unsupported.push((
+                "subscription",
+                Some(sub.topic.clone()),
+                format!(
+                    "{}: subscription options are not supported",
+                    options.join(", ")
+                ),
+            ));
+        }
+        subscriptions.push(new_subscription(
+            sub.topic,
+            sub.qos,
+            non_empty(sub.color),
+            !sub.disabled,
+        ));
+    }
+
+    Ok(ExternalConnection {
+        server,
+        subscriptions,
+        unsupported,
+    })
+}
+
+/// 证书内容：只接受内嵌的 PEM 文本，文件路径不读取，提示用户手动添加
+fn load_pem(
+    field: &str,
+    value: Option<String>,
+    note: &mut impl FnMut(&str, &str),
+) -> Option<String> {
+    let value = non_empty(value)?;
+    if value.contains("-----BEGIN") {
+        return Some(value);
+    }
+    note(
+        field,
+        &format!(
+            "certificate file {} was not imported, add it to the server manually",
+            value.trim()
+        ),
+    );
+    None
+}
+
+// ===== MQTT Explorer =====
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExplorerConnection {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    host: String,
+    port: Option<i32>,
+    /// "mqtt" | "ws"
+    #[serde(default)]
+    protocol: Option<String>,
+    #[serde(default)]
+    client_id: Option<String>,
+    #[serde(default)]
+    username: Option<String>,
+    #[serde(default)]
+    password: Option<String>,
+    #[serde(default)]
+    encryption: bool,
+    cert_validation: Option<bool>,
+    #[serde(default)]
+    subscriptions: Vec<ExplorerSubscription>,
+    #[serde(default)]
+    self_signed_certificate: Option<ExplorerCertificate>,
+    #[serde(default)]
+    client_certificate: Option<ExplorerCertificate>,
+    #[serde(default)]
+    client_key: Option<ExplorerCertificate>,
+}
+
+/// 旧版本的订阅只保存主题
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ExplorerSubscription {
+    Topic(String),
+    Entry {
+        topic: String,
+        #[serde(default)]
+        qos: i32,
+    },
+}
+
+/// 证书文件，data 为 base64 编码的内容
+#[derive(Deserialize)]
+struct ExplorerCertificate {
+    #[serde(default)]
+    name: String,
+    data: String,
+}
+
+/// 解析 MQTT Explorer 的 settings.json
+fn parse_mqtt_explorer(
+    value: Value,
+) -> Result<Vec<Result<ExternalConnection, ImportIssue>>, String> {
+    let Some(Value::Object(map)) = value.get(EXPLORER_CONNECTIONS_KEY) else {
+        return Err(format!(
+            "Not an MQTT Explorer settings file (missing {})",
+            EXPLORER_CONNECTIONS_KEY
+        ));
+    };
+
+    Ok(map
+        .values()
+        .map(|item| {
+            serde_json::from_value::<ExplorerConnection>(item.clone())
+                .map_err(|e| format!("Invalid connection: {}", e))
+                .and_then(explorer_connection)
+                .map_err(|e| parse_error(item, e))
+        })
+        .collect())
+}
+
+fn explorer_connection(c: ExplorerConnection) -> Result<ExternalConnection, String> {
+    if c.protocol.as_deref() == Some("ws") {
+        return Err("WebSocket connections are not supported".to_string());
+    }
+    let mut unsupported = Vec::new();
+    let mut note = |field: &str, reason: &str| {
+        unsupported.push(("server", None, format!("{}: {}", field, reason)));
+    };
+
+    let default_port = if c.encryption { 8883 } else { 1883 };
+    let name = match c.name.is_empty() {
+        true => c.host.clone(),
+        false => c.name,
+    };
+    let host = strip_scheme(&c.host);
+    let port = c.port.unwrap_or(default_port);
+    validate_address(&host, port)?;
+    let mut server = new_server(name, host, port);
+    server.use_tls = c.encryption;
+    server.client_id = non_empty(c.client_id);
+    server.username = non_empty(c.username);
+    server.password = non_empty(c.password);
+    server.ca_cert = decode_certificate(
+        "selfSignedCertificate",
+        c.self_signed_certificate,
+        &mut note,
+    );
+    server.client_cert = decode_certificate("clientCertificate", c.client_certificate, &mut note);
+    server.client_key = decode_certificate("clientKey", c.client_key, &mut note);
+    if c.cert_validation == Some(false) {
+        note(
+            "certValidation",
+            "disabling certificate verification is not supported",
+        );
+    }
+
+    let subscriptions = c
+        .subscriptions
+        .into_iter()
+        .map(|sub| match sub {
+            ExplorerSubscription::Topic(topic) => new_subscription(topic, 0, None, true),
+            ExplorerSubscription::Entry { topic, qos } => new_subscription(topic, qos, None, true),
+        })
+        .collect();
+
+    Ok(ExternalConnection {
+        server,
+        subscriptions,
+        unsupported,
+    })
+}
+
+/// 解码 base64 保存的 PEM 证书
+fn decode_certificate(
+    field: &str,
+    certificate: Option<ExplorerCertificate>,
+    note: &mut impl FnMut(&str, &str),
+) -> Option<String> {
+    let certificate = certificate?;
+    let content = STANDARD
+        .decode(certificate.data.trim())
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .filter(|content| content.contains("-----BEGIN"));
+    if content.is_none() {
+        note(
+            field,
+            &format!("{} is not a PEM certificate", certificate.name),
+        );
+    }
+    content
+}
@@ -0,0 +1,966 @@
+pub mod external;
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::db::backup;
+use crate::db::models::{
+    CommandTemplate, CreateEnvVariableRequest, CreateScriptRequest, CreateTemplateRequest,
+    EnvVariable, MessageHistory, MessageQuery, MqttServer, ScheduledJob, Script, Subscription,
+};
+use crate::db::Storage;
+use crate::export::Redactor;
+
+/// 工作区归档的格式标识
+pub const WORKSPACE_FORMAT: &str = "mqtt-workspace";
+/// 单个服务器配置归档的格式标识
+pub const SERVER_FORMAT: &str = "mqtt-server";
+/// 当前归档版本，导入时拒绝更高版本的文件
+pub const ARCHIVE_VERSION: u32 = 1;
+/// 导出历史消息时每次读取的条数
+const HISTORY_BATCH_SIZE: usize = 1000;
+
+/// 工作区归档：服务器及其订阅、模板、脚本、定时任务和环境变量，可选包含历史消息
+///
+/// 记录保留原始 ID，导入时按 server_id / 模板 ID 重新映射。
+/// 单个服务器的配置归档使用相同结构，只包含一个服务器。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceArchive {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: String,
+    /// 是否已去除凭据和私钥
+    #[serde(default)]
+    pub credentials_stripped: bool,
+    #[serde(default)]
+    pub servers: Vec<MqttServer>,
+    #[serde(default)]
+    pub subscriptions: Vec<Subscription>,
+    #[serde(default)]
+    pub templates: Vec<CommandTemplate>,
+    #[serde(default)]
+    pub scripts: Vec<Script>,
+    #[serde(default)]
+    pub scheduled_jobs: Vec<ScheduledJob>,
+    /// 全局变量（server_id 为空）和 Server 变量
+    #[serde(default)]
+    pub env_variables: Vec<EnvVariable>,
+    #[serde(default)]
+    pub messages: Vec<MessageHistory>,
+    /// 导出过程中的提示（例如历史消息未能脱敏），不写入归档
+    #[serde(skip)]
+    pub warnings: Vec<String>,
+}
+
+/// 导出选项
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportOptions {
+    pub include_history: bool,
+    /// 去除用户名、密码、客户端私钥和敏感变量的值，历史消息中的敏感值替换为占位符
+    pub strip_credentials: bool,
+}
+
+/// 导入方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    /// 保留现有数据：同名服务器合并，与现有记录冲突的条目跳过
+    Merge,
+    /// 删除现有的服务器和全局变量后导入
+    Replace,
+}
+
+/// 导入时跳过或失败的条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportIssue {
+    /// "server" | "subscription" | "template" | "script" | "scheduled_job"
+    /// | "env_variable" | "message"
+    pub kind: String,
+    pub name: String,
+    /// 所属服务器名称，全局记录为空
+    pub server: Option<String>,
+    pub reason: String,
+}
+
+/// 各类记录的导入条数
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportCounts {
+    pub servers: usize,
+    pub subscriptions: usize,
+    pub templates: usize,
+    pub scripts: usize,
+    pub scheduled_jobs: usize,
+    pub env_variables: usize,
+    pub messages: usize,
+}
+
+/// 导入结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub created: ImportCounts,
+    /// 与现有记录冲突而跳过（或合并）的条目
+    pub conflicts: Vec<ImportIssue>,
+    pub failed: Vec<ImportIssue>,
+    /// 已导入但部分字段无法导入的条目（从其他工具导入时）
+    pub warnings: Vec<ImportIssue>,
+    /// 导入前自动创建的备份文件名
+    pub backup: Option<String>,
+}
+
+impl WorkspaceArchive {
+    fn new(format: &str, credentials_stripped: bool) -> Self {
+        Self {
+            format: format.to_string(),
+            version: ARCHIVE_VERSION,
+            exported_at: chrono::Utc::now().to_rfc3339(),
+            credentials_stripped,
+            servers: Vec::new(),
+            subscriptions: Vec::new(),
+            templates: Vec::new(),
+            scripts: Vec::new(),
+            scheduled_jobs: Vec::new(),
+            env_variables: Vec::new(),
+            messages: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// 添加一个服务器及其所有子记录
+    fn push_server(
+        &mut self,
+        storage: &Storage,
+        mut server: MqttServer,
+        options: &ExportOptions,
+    ) -> Result<(), String> {
+        let Some(server_id) = server.id else {
+            return Ok(());
+        };
+
+        let mut env_variables = storage.get_env_variables(server_id);
+        if options.strip_credentials {
+            strip_server(&mut server);
+            strip_env_variables(&mut env_variables);
+        }
+        if options.include_history {
+            let redactor = match options.strip_credentials {
+                true => Redactor::for_server(storage, server_id)
+                    .map_err(|e| self.warnings.push(format!("{}: {}", server.name, e)))
+                    .ok(),
+                false => None,
+            };
+            self.messages
+                .extend(export_history(storage, server_id, redactor.as_ref())?);
+        }
+
+        self.servers.push(server);
+        self.subscriptions
+            .extend(storage.get_subscriptions(server_id));
+        self.templates.extend(storage.get_templates(server_id));
+        self.scripts.extend(storage.get_scripts(server_id));
+        self.scheduled_jobs
+            .extend(storage.get_scheduled_jobs(server_id));
+        self.env_variables.extend(env_variables);
+        Ok(())
+    }
+
+    /// 是否包含需要保险库加密保存的内容
+    fn has_secrets(&self) -> bool {
+        let credentials = self
+            .servers
+            .iter()
+            .flat_map(|s| [&s.password, &s.client_key_password])
+            .flatten()
+            .any(|value| !value.is_empty());
+        let secrets = self
+            .env_variables
+            .iter()
+            .any(|v| v.secret && !v.value.is_empty());
+        credentials || secrets
+    }
+}
+
+/// 导出整个工作区
+pub fn export_workspace(
+    storage: &Storage,
+    options: &ExportOptions,
+) -> Result<WorkspaceArchive, String> {
+    check_vault_for_export(storage, options)?;
+
+    let mut archive = WorkspaceArchive::new(WORKSPACE_FORMAT, options.strip_credentials);
+    for server in storage.get_servers() {
+        archive.push_server(storage, server, options)?;
+    }
+
+    let mut globals = storage.get_global_env_variables();
+    if options.strip_credentials {
+        strip_env_variables(&mut globals);
+    }
+    archive.env_variables.extend(globals);
+    Ok(archive)
+}
+
+/// 导出单个服务器及其子记录，不包含全局变量和历史消息
+pub fn export_server(
+    storage: &Storage,
+    server_id: i64,
+    strip_credentials: bool,
+) -> Result<WorkspaceArchive, String> {
+    let options = ExportOptions {
+        include_history: false,
+        strip_credentials,
+    };
+    check_vault_for_export(storage, &options)?;
+
+    let server = storage.get_server(server_id).ok_or("Server not found")?;
+    let mut archive = WorkspaceArchive::new(SERVER_FORMAT, strip_credentials);
+    archive.push_server(storage, server, &options)?;
+    Ok(archive)
+}
+
+/// 写入归档文件
+pub fn write_archive(archive: &WorkspaceArchive, path: &Path) -> Result<(), String> {
+    let content = serde_json::to_vec_pretty(archive).map_err(|e| e.to_string())?;
+    backup::write_atomic(path, &content)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// 读取并校验归档文件，format 为期望的格式标识
+pub fn read_archive(path: &Path, format: &str) -> Result<WorkspaceArchive, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let archive: WorkspaceArchive =
+        serde_json::from_str(&content).map_err(|e| format!("Invalid workspace archive: {}", e))?;
+    if archive.format != format {
+        return Err(format!("Unsupported archive format: {}", archive.format));
+    }
+    if archive.version > ARCHIVE_VERSION {
+        return Err(format!(
+            "Archive version {} is newer than the supported version {}, please upgrade the application",
+            archive.version, ARCHIVE_VERSION
+        ));
+    }
+    Ok(archive)
+}
+
+/// 导入工作区归档
+///
+/// 导入前会强制备份数据文件；单条记录失败不会中断导入，记录在结果中。
+pub fn import_workspace(
+    storage: &Storage,
+    archive: WorkspaceArchive,
+    mode: ImportMode,
+) -> Result<ImportReport, String> {
+    check_vault_for_import(storage, archive.has_secrets())?;
+
+    let backup = backup::rotate(storage, true)?;
+
+    if mode == ImportMode::Replace {
+        for id in storage.get_servers().into_iter().filter_map(|s| s.id) {
+            storage.delete_server(id)?;
+        }
+        for id in storage
+            .get_global_env_variables()
+            .into_iter()
+            .filter_map(|v| v.id)
+        {
+            storage.delete_env_variable(id)?;
+        }
+    }
+
+    let mut importer = Importer::new(storage);
+    importer.report.backup = backup.map(|b| b.name);
+    importer.run(archive);
+    Ok(importer.report)
+}
+
+/// 导入单个服务器的配置归档，始终创建新的服务器，同名时自动加序号
+pub fn import_server(
+    storage: &Storage,
+    mut archive: WorkspaceArchive,
+) -> Result<ImportReport, String> {
+    if archive.servers.len() != 1 {
+        return Err("Server archive must contain exactly one server".to_string());
+    }
+    // 只导入该服务器自己的记录
+    archive.env_variables.retain(|v| v.server_id.is_some());
+    archive.messages.clear();
+    check_vault_for_import(storage, archive.has_secrets())?;
+
+    let names: HashSet<String> = storage.get_servers().into_iter().map(|s| s.name).collect();
+    let server = &mut archive.servers[0];
+    server.name = unique_name(&server.name, &names);
+
+    let mut importer = Importer::new(storage);
+    importer.run(archive);
+    Ok(importer.report)
+}
+
+/// 将归档中的记录写入存储，并维护 ID 映射
+struct Importer<'a> {
+    storage: &'a Storage,
+    report: ImportReport,
+    /// 归档中的 server_id -> 导入后的 server_id
+    server_ids: HashMap<i64, i64>,
+    /// 归档中的 server_id -> 服务器名称
+    server_names: HashMap<i64, String>,
+    /// 合并到已有服务器的归档 server_id
+    merged: HashSet<i64>,
+    /// 归档中的模板 ID -> 导入后的模板 ID
+    template_ids: HashMap<i64, i64>,
+}
+
+impl<'a> Importer<'a> {
+    fn new(storage: &'a Storage) -> Self {
+        Self {
+            storage,
+            report: ImportReport::default(),
+            server_ids: HashMap::new(),
+            server_names: HashMap::new(),
+            merged: HashSet::new(),
+            template_ids: HashMap::new(),
+        }
+    }
+
+    fn run(&mut self, archive: WorkspaceArchive) {
+        self.import_servers(archive.servers);
+        self.import_subscriptions(archive.subscriptions);
+        self.import_templates(archive.templates);
+        self.import_scripts(archive.scripts);
+        self.import_scheduled_jobs(archive.scheduled_jobs);
+        self.import_env_variables(archive.env_variables);
+        self.import_messages(archive.messages);
+    }
+
+    fn import_servers(&mut self, servers: Vec<MqttServer>) {
+        let existing: HashMap<String, i64> = self
+            .storage
+            .get_servers()
+            .into_iter()
+            .filter_map(|s| Some((s.name, s.id?)))
+            .collect();
+
+        for server in servers {
+            let Some(archive_id) = server.id else {
+                self.fail("server", &server.name, None, "Missing server id");
+                continue;
+            };
+            self.server_names.insert(archive_id, server.name.clone());
+
+            if let Some(&id) = existing.get(&server.name) {
+                self.conflict(
+                    "server",
+                    &server.name,
+                    None,
+                    "A server with the same name exists, records are merged into it",
+                );
+                self.server_ids.insert(archive_id, id);
+                self.merged.insert(archive_id);
+                continue;
+            }
+
+            let name = server.name.clone();
+            match self.storage.create_server(server) {
+                Ok(id) => {
+                    self.server_ids.insert(archive_id, id);
+                    self.report.created.servers += 1;
+                }
+                Err(e) => self.fail("server", &name, None, &e),
+            }
+        }
+    }
+
+    fn import_subscriptions(&mut self, subscriptions: Vec<Subscription>) {
+        let mut existing = self.existing_keys(|storage, id| {
+            storage
+                .get_subscriptions(id)
+                .into_iter()
+                .map(|s| s.topic)
+                .collect()
+        });
+
+        for sub in subscriptions {
+            let Some(server_id) = self.target_server("subscription", &sub.topic, sub.server_id)
+            else {
+                continue;
+            };
+            if !existing.insert((server_id, sub.topic.clone())) {
+                self.conflict_in(
+                    "subscription",
+                    &sub.topic,
+                    sub.server_id,
+                    "Topic is already subscribed",
+                );
+                continue;
+            }
+
+            let archive_server_id = sub.server_id;
+            let topic = sub.topic.clone();
+            let sub = Subscription {
+                id: None,
+                server_id,
+                last_grant: None,
+                ..sub
+            };
+            match self.storage.create_subscription(sub) {
+                Ok(_) => self.report.created.subscriptions += 1,
+                Err(e) => self.fail_in("subscription", &topic, archive_server_id, &e),
+            }
+        }
+    }
+
+    fn import_templates(&mut self, templates: Vec<CommandTemplate>) {
+        let mut existing: HashMap<(i64, String), i64> = self
+            .merged_targets()
+            .into_iter()
+            .flat_map(|id| {
+                self.storage
+                    .get_templates(id)
+                    .into_iter()
+                    .filter_map(move |t| Some(((id, t.name), t.id?)))
+            })
+            .collect();
+
+        for template in templates {
+            let Some(server_id) =
+                self.target_server("template", &template.name, template.server_id)
+            else {
+                continue;
+            };
+            let key = (server_id, template.name.clone());
+            if let Some(&id) = existing.get(&key) {
+                // 定时任务引用的模板指向已有的同名模板
+                if let Some(archive_id) = template.id {
+                    self.template_ids.insert(archive_id, id);
+                }
+                self.conflict_in(
+                    "template",
+                    &template.name,
+                    template.server_id,
+                    "A template with the same name exists",
+                );
+                continue;
+            }
+
+            let req = CreateTemplateRequest {
+                server_id,
+                name: template.name.clone(),
+                topic: template.topic,
+                payload: template.payload,
+                payload_type: template.payload_type,
+                qos: template.qos,
+                retain: template.retain,
+                description: template.description,
+                category: template.category,
+            };
+            match self.storage.create_template(req) {
+                Ok(id) => {
+                    if let Some(archive_id) = template.id {
+                        self.template_ids.insert(archive_id, id);
+                    }
+                    existing.insert(key, id);
+                    self.report.created.templates += 1;
+                }
+                Err(e) => self.fail_in("template", &template.name, template.server_id, &e),
+            }
+        }
+    }
+
+    fn import_scripts(&mut self, scripts: Vec<Script>) {
+        let mut existing = self.existing_keys(|storage, id| {
+            storage
+                .get_scripts(id)
+                .into_iter()
+                .map(|s| s.name)
+                .collect()
+        });
+
+        for script in scripts {
+            let Some(server_id) = self.target_server("script", &script.name, script.server_id)
+            else {
+                continue;
+            };
+            if !existing.insert((server_id, script.name.clone())) {
+                self.conflict_in(
+                    "script",
+                    &script.name,
+                    script.server_id,
+                    "A script with the same name exists",
+                );
+                continue;
+            }
+
+            let req = CreateScriptRequest {
+                server_id,
+                name: script.name.clone(),
+                script_type: script.script_type,
+                code: script.code,
+                enabled: script.enabled,
+                description: script.description,
+                topic_filter: script.topic_filter,
+            };
+            match self.storage.create_script(req) {
+                Ok(_) => self.report.created.scripts += 1,
+                Err(e) => self.fail_in("script", &script.name, script.server_id, &e),
+            }
+        }
+    }
+
+    fn import_scheduled_jobs(&mut self, jobs: Vec<ScheduledJob>) {
+        let mut existing = self.existing_keys(|storage, id| {
+            storage
+                .get_scheduled_jobs(id)
+                .into_iter()
+                .map(|j| j.name)
+                .collect()
+        });
+
+        for job in jobs {
+            let Some(server_id) = self.target_server("scheduled_job", &job.name, job.server_id)
+            else {
+                continue;
+            };
+            if !existing.insert((server_id, job.name.clone())) {
+                self.conflict_in(
+                    "scheduled_job",
+                    &job.name,
+                    job.server_id,
+                    "A scheduled job with the same name exists",
+                );
+                continue;
+            }
+
+            let template_ids: Vec<i64> = job
+                .template_ids
+                .iter()
+                .filter_map(|id| self.template_ids.get(id).copied())
+                .collect();
+            if !job.template_ids.is_empty() && template_ids.is_empty() && job.message.is_none() {
+                self.fail_in(
+                    "scheduled_job",
+                    &job.name,
+                    job.server_id,
+                    "None of the referenced templates were imported",
+                );
+                continue;
+            }
+
+            let archive_server_id = job.server_id;
+            let name = job.name.clone();
+            let job = ScheduledJob {
+                id: None,
+                server_id,
+                template_ids,
+                run_count: 0,
+                last_run_at: None,
+                ..job
+            };
+            match self.storage.create_scheduled_job(job) {
+                Ok(_) => self.report.created.scheduled_jobs += 1,
+                Err(e) => self.fail_in("scheduled_job", &name, archive_server_id, &e),
+            }
+        }
+    }
+
+    fn import_env_variables(&mut self, variables: Vec<EnvVariable>) {
+        // 全局变量使用 server_id = None 作为键
+        let mut existing: HashSet<(Option<i64>, String)> = self
+            .storage
+            .get_global_env_variables()
+            .into_iter()
+            .map(|v| (None, v.name))
+            .collect();
+        existing.extend(
+            self.existing_keys(|storage, id| {
+                storage
+                    .get_env_variables(id)
+                    .into_iter()
+                    .map(|v| v.name)
+                    .collect()
+            })
+            .into_iter()
+            .map(|(id, name)| (Some(id), name)),
+        );
+
+        for variable in variables {
+            let server_id = match variable.server_id {
+                Some(archive_id) => {
+                    match self.target_server("env_variable", &variable.name, archive_id) {
+                        Some(id) => Some(id),
+                        None => continue,
+                    }
+                }
+                None => None,
+            };
+            let server = variable
+                .server_id
+                .and_then(|id| self.server_names.get(&id).cloned());
+
+            if !existing.insert((server_id, variable.name.clone())) {
+                self.issue(
+                    true,
+                    "env_variable",
+                    &variable.name,
+                    server,
+                    "A variable with the same name exists",
+                );
+                continue;
+            }
+
+            let req = CreateEnvVariableRequest {
+                server_id,
+                name: variable.name.clone(),
+                value: variable.value,
+                description: variable.description,
+                secret: variable.secret,
+            };
+            match self.storage.create_env_variable(req) {
+                Ok(_) => self.report.created.env_variables += 1,
+                Err(e) => self.issue(false, "env_variable", &variable.name, server, &e),
+            }
+        }
+    }
+
+    fn import_messages(&mut self, messages: Vec<MessageHistory>) {
+        // 已有服务器的历史消息无法判断是否重复，不导入
+        let mut skipped: Vec<i64> = Vec::new();
+        let messages: Vec<MessageHistory> = messages
+            .into_iter()
+            .filter_map(|message| {
+                if self.merged.contains(&message.server_id) {
+                    if !skipped.contains(&message.server_id) {
+                        skipped.push(message.server_id);
+                    }
+                    return None;
+                }
+                let server_id = *self.server_ids.get(&message.server_id)?;
+                Some(MessageHistory {
+                    id: None,
+                    server_id,
+                    ..message
+                })
+            })
+            .collect();
+
+        for archive_id in skipped {
+            self.conflict_in(
+                "message",
+                "",
+                archive_id,
+                "History is not imported into an existing server",
+            );
+        }
+
+        if messages.is_empty() {
+            return;
+        }
+        match self.storage.import_messages(&messages) {
+            Ok(count) => self.report.created.messages += count,
+            Err(e) => self.issue(false, "message", "", None, &e),
+        }
+    }
+
+    /// 归档 server_id 对应的目标服务器，服务器未导入时记录失败
+    fn target_server(&mut self, kind: &str, name: &str, archive_id: i64) -> Option<i64> {
+        let target = self.server_ids.get(&archive_id).copied();
+        if target.is_none() {
+            let reason = match self.server_names.contains_key(&archive_id) {
+                true => "Server was not imported",
+                false => "Server not found in archive",
+            };
+            self.fail_in(kind, name, archive_id, reason);
+        }
+        target
+    }
+
+    /// 合并目标（已有服务器）的 ID
+    fn merged_targets(&self) -> Vec<i64> {
+        self.merged
+            .iter()
+            .filter_map(|id| self.server_ids.get(id).copied())
+            .collect()
+    }
+
+    /// 已有服务器中记录的 (server_id, 名称)，用于检测冲突
+    fn existing_keys<F>(&self, f: F) -> HashSet<(i64, String)>
+    where
+        F: Fn(&Storage, i64) -> Vec<String>,
+    {
+        self.merged_targets()
+            .into_iter()
+            .flat_map(|id| f(self.storage, id).into_iter().map(move |name| (id, name)))
+            .collect()
+    }
+
+    fn conflict(&mut self, kind: &str, name: &str, server: Option<String>, reason: &str) {
+        self.issue(true, kind, name, server, reason);
+    }
+
+    fn conflict_in(&mut self, kind: &str, name: &str, archive_id: i64, reason: &str) {
+        let server = self.server_names.get(&archive_id).cloned();
+        self.issue(true, kind, name, server, reason);
+    }
+
+    fn fail(&mut self, kind: &str, name: &str, server: Option<String>, reason: &str) {
+        self.issue(false, kind, name, server, reason);
+    }
+
+    fn fail_in(&mut self, kind: &str, name: &str, archive_id: i64, reason: &str) {
+        let server = self.server_names.get(&archive_id).cloned();
+        self.issue(false, kind, name, server, reason);
+    }
+
+    fn issue(
+        &mut self,
+        conflict: bool,
+        kind: &str,
+        name: &str,
+        server: Option<String>,
+        reason: &str,
+    ) {
+        let issue = ImportIssue {
+            kind: kind.to_string(),
+            name: name.to_string(),
+            server,
+            reason: reason.to_string(),
+        };
+        match conflict {
+            true => self.report.conflicts.push(issue),
+            false => self.report.failed.push(issue),
+        }
+    }
+}
+
+/// 导出凭据时需要解密，保险库锁定则无法导出
+fn check_vault_for_export(storage: &Storage, options: &ExportOptions) -> Result<(), String> {
+    let status = storage.vault_status();
+    if status.initialized && !status.unlocked && !options.strip_credentials {
+        return Err("Vault is locked, unlock it or strip credentials to export".to_string());
+    }
+    Ok(())
+}
+
+/// 导入的凭据需要加密保存，保险库锁定时在修改数据前拒绝导入
+fn check_vault_for_import(storage: &Storage, has_secrets: bool) -> Result<(), String> {
+    let status = storage.vault_status();
+    if status.initialized && !status.unlocked && has_secrets {
+        return Err("Vault is locked, unlock it before importing credentials".to_string());
+    }
+    Ok(())
+}
+
+/// 按时间正序读取服务器的全部历史消息，redactor 不为空时替换其中的敏感值
+fn export_history(
+    storage: &Storage,
+    server_id: i64,
+    redactor: Option<&Redactor>,
+) -> Result<Vec<MessageHistory>, String> {
+    let mut query = MessageQuery {
+        server_id,
+        ascending: true,
+        limit: Some(HISTORY_BATCH_SIZE),
+        ..Default::default()
+    };
+
+    let mut messages = Vec::new();
+    loop {
+        let page = storage.search_messages(&query)?;
+        messages.extend(page.messages.into_iter().map(|message| match redactor {
+            Some(redactor) => redactor.redacted(&message).into_owned(),
+            None => message,
+        }));
+        match page.next_cursor {
+            Some(cursor) => query.cursor = Some(cursor),
+            None => break,
+        }
+    }
+    Ok(messages)
+}
+
+/// 与已有名称重复时添加序号，如 `Broker (2)`
+fn unique_name(name: &str, existing: &HashSet<String>) -> String {
+    if !existing.contains(name) {
+        return name.to_string();
+    }
+    (2..)
+        .map(|n| format!("{} ({})", name, n))
+        .find(|candidate| !existing.contains(candidate))
+        .unwrap_or_else(|| name.to_string())
+}
+
+/// 去除服务器的用户名、密码和客户端私钥
+fn strip_server(server: &mut MqttServer) {
+    server.username = None;
+    server.password = None;
+    server.client_key = None;
+    server.client_key_password = None;
+}
+
+/// 清空敏感变量的值
+fn strip_env_variables(variables: &mut [EnvVariable]) {
+    for variable in variables.iter_mut().filter(|v| v.secret) {
+        variable.value.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::JobTrigger;
+
+    fn server(storage: &Storage, name: &str) -> i64 {
+        let server: MqttServer = serde_json::from_value(serde_json::json!({
+            "name": name,
+            "host": "localhost",
+            "port": 1883,
+            "protocol_version": "3.1.1",
+            "keep_alive": 60,
+            "clean_session": true,
+            "use_tls": false,
+        }))
+        .unwrap();
+        storage.create_server(server).unwrap()
+    }
+
+    fn template(storage: &Storage, server_id: i64, name: &str) -> i64 {
+        storage
+            .create_template(CreateTemplateRequest {
+                server_id,
+                name: name.to_string(),
+                topic: "t".to_string(),
+                payload: String::new(),
+                payload_type: "text".to_string(),
+                qos: 0,
+                retain: false,
+                description: None,
+                category: None,
+            })
+            .unwrap()
+    }
+
+    fn job(storage: &Storage, server_id: i64, name: &str, template_ids: Vec<i64>) {
+        storage
+            .create_scheduled_job(ScheduledJob {
+                id: None,
+                server_id,
+                name: name.to_string(),
+                template_ids,
+                message: None,
+                trigger: JobTrigger::Interval {
+                    interval_ms: 1000,
+                    round_interval_ms: None,
+                },
+                max_runs: None,
+                start_at: None,
+                end_at: None,
+                enabled: false,
+                run_count: 0,
+                last_run_at: None,
+                created_at: None,
+                updated_at: None,
+            })
+            .unwrap();
+    }
+
+    fn import(storage: &Storage, archive: WorkspaceArchive) -> ImportReport {
+        let mut importer = Importer::new(storage);
+        importer.run(archive);
+        importer.report
+    }
+
+    #[test]
+    fn remaps_server_and_template_ids() {
+        let source = Storage::in_memory();
+        let source_id = server(&source, "Broker");
+        let first = template(&source, source_id, "first");
+        let second = template(&source, source_id, "second");
+        job(&source, source_id, "job", vec![second, first]);
+        source
+            .create_subscription(Subscription {
+                id: None,
+                server_id: source_id,
+                topic: "a/#".to_string(),
+                qos: 1,
+                is_active: true,
+                color: None,
+                last_grant: None,
+                created_at: None,
+            })
+            .unwrap();
+        source
+            .create_env_variable(CreateEnvVariableRequest {
+                server_id: Some(source_id),
+                name: "room".to_string(),
+                value: "1".to_string(),
+                description: None,
+                secret: false,
+            })
+            .unwrap();
+        let archive = export_workspace(&source, &ExportOptions::default()).unwrap();
+
+        // 目标中已有记录，导入后的 ID 与归档中的不同
+        let target = Storage::in_memory();
+        let other = server(&target, "Other");
+        for name in ["a", "b"] {
+            template(&target, other, name);
+        }
+        let report = import(&target, archive);
+        assert!(report.failed.is_empty() && report.conflicts.is_empty());
+
+        let target_id = target
+            .get_servers()
+            .into_iter()
+            .find(|s| s.name == "Broker")
+            .and_then(|s| s.id)
+            .unwrap();
+        assert_ne!(target_id, source_id);
+        assert_eq!(target.get_subscriptions(target_id)[0].topic, "a/#");
+        assert_eq!(target.get_env_variables(target_id)[0].name, "room");
+
+        let ids: HashMap<String, i64> = target
+            .get_templates(target_id)
+            .into_iter()
+            .map(|t| (t.name, t.id.unwrap()))
+            .collect();
+        assert!(!ids.values().any(|id| [first, second].contains(id)));
+        let jobs = target.get_scheduled_jobs(target_id);
+        assert_eq!(jobs[0].template_ids, vec![ids["second"], ids["first"]]);
+    }
+
+    #[test]
+    fn merged_server_reuses_existing_templates() {
+        let source = Storage::in_memory();
+        let source_id = server(&source, "Broker");
+        let shared = template(&source, source_id, "shared");
+        job(&source, source_id, "job", vec![shared]);
+        let archive = export_workspace(&source, &ExportOptions::default()).unwrap();
+
+        let target = Storage::in_memory();
+        server(&target, "Other");
+        let target_id = server(&target, "Broker");
+        let existing = template(&target, target_id, "shared");
+        let report = import(&target, archive);
+
+        assert_eq!(report.created.servers, 0);
+        assert_eq!(report.created.templates, 0);
+        assert_eq!(report.conflicts.len(), 2);
+        let jobs = target.get_scheduled_jobs(target_id);
+        assert_eq!(jobs[0].template_ids, vec![existing]);
+    }
+
+    #[test]
+    fn skips_records_of_unknown_servers() {
+        let source = Storage::in_memory();
+        let source_id = server(&source, "Broker");
+        template(&source, source_id, "orphan");
+        let mut archive = export_workspace(&source, &ExportOptions::default()).unwrap();
+        archive.servers.clear();
+
+        let target = Storage::in_memory();
+        let report = import(&target, archive);
+        assert_eq!(report.created.templates, 0);
+        assert_eq!(report.failed[0].reason, "Server not found in archive");
+    }
+}
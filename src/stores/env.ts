@@ -5,11 +5,15 @@ import type { EnvVariable, CreateEnvVariableRequest, UpdateEnvVariableRequest }
 
 export type { EnvVariable, CreateEnvVariableRequest, UpdateEnvVariableRequest };
 
+// 变量作用域：当前 Server 或全局
+export type EnvScope = "server" | "global";
+
 export const useEnvStore = defineStore("env", () => {
   // 状态
   const variables = ref<EnvVariable[]>([]);
   const loading = ref(false);
   const searchKeyword = ref("");
+  const scope = ref<EnvScope>("server");
 
   // 过滤后的变量列表
   const filteredVariables = computed(() => {
@@ -33,13 +37,14 @@ export const useEnvStore = defineStore("env", () => {
     return map;
   });
 
-  // 加载环境变量
+  // 加载当前作用域的环境变量
   const loadVariables = async (serverId: number) => {
     loading.value = true;
     try {
-      variables.value = await invoke<EnvVariable[]>("list_env_variables", {
-        serverId,
-      });
+      variables.value =
+        scope.value === "global"
+          ? await invoke<EnvVariable[]>("list_global_env_variables")
+          : await invoke<EnvVariable[]>("list_env_variables", { serverId });
     } catch (error) {
       console.error("Failed to load env variables:", error);
       throw error;
@@ -55,10 +60,11 @@ export const useEnvStore = defineStore("env", () => {
     const now = new Date().toISOString();
     variables.value.push({
       id,
-      server_id: request.server_id,
+      server_id: request.server_id ?? null,
       name: request.name,
       value: request.value,
       description: request.description,
+      secret: request.secret ?? false,
       created_at: now,
       updated_at: now,
     });
@@ -77,6 +83,7 @@ export const useEnvStore = defineStore("env", () => {
         name: request.name ?? current.name,
         value: request.value ?? current.value,
         description: request.description ?? current.description,
+        secret: request.secret ?? current.secret,
         updated_at: new Date().toISOString(),
       };
     }
@@ -105,6 +112,12 @@ export const useEnvStore = defineStore("env", () => {
     });
   };
 
+  // 切换作用域并重新加载
+  const setScope = async (value: EnvScope, serverId: number) => {
+    scope.value = value;
+    await loadVariables(serverId);
+  };
+
   // 清空状态
   const clearVariables = () => {
     variables.value = [];
@@ -116,6 +129,7 @@ export const useEnvStore = defineStore("env", () => {
     variables,
     loading,
     searchKeyword,
+    scope,
     filteredVariables,
     variablesMap,
     // 方法
@@ -125,6 +139,7 @@ export const useEnvStore = defineStore("env", () => {
     deleteVariable,
     setSearchKeyword,
     replaceVariables,
+    setScope,
     clearVariables,
   };
 });
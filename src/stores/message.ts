@@ -1,7 +1,14 @@
 import { defineStore } from "pinia";
 import { ref } from "vue";
 import { invoke } from "@tauri-apps/api/core";
-import type { MessageHistory, PublishPayload } from "@/types/mqtt";
+import type {
+  ExportFormat,
+  ExportSummary,
+  MessageHistory,
+  MessagePage,
+  MessageQuery,
+  PublishPayload,
+} from "@/types/mqtt";
 
 export const useMessageStore = defineStore("message", () => {
   const messages = ref<Map<number, MessageHistory[]>>(new Map());
@@ -26,6 +33,24 @@ export const useMessageStore = defineStore("message", () => {
     }
   }
 
+  async function searchMessageHistory(query: MessageQuery) {
+    return await invoke<MessagePage>("search_message_history", { query });
+  }
+
+  async function exportMessageHistory(
+    query: MessageQuery,
+    format: ExportFormat,
+    path: string,
+    redact = false
+  ) {
+    return await invoke<ExportSummary>("export_message_history", {
+      query,
+      format,
+      path,
+      redact,
+    });
+  }
+
   async function publishMessage(serverId: number, message: PublishPayload) {
     const result = await invoke<MessageHistory>("publish_message", {
       serverId,
@@ -38,6 +63,14 @@ export const useMessageStore = defineStore("message", () => {
     return result;
   }
 
+  async function resendMessage(messageId: number) {
+    const result = await invoke<MessageHistory>("resend_message", {
+      messageId,
+    });
+    addMessage(result.server_id, result);
+    return result;
+  }
+
   function addMessage(serverId: number, message: MessageHistory) {
     const serverMessages = messages.value.get(serverId) || [];
     serverMessages.unshift(message);
@@ -77,7 +110,10 @@ export const useMessageStore = defineStore("message", () => {
     realtimeMessages,
     loading,
     fetchMessageHistory,
+    searchMessageHistory,
+    exportMessageHistory,
     publishMessage,
+    resendMessage,
     addMessage,
     clearHistory,
     getMessages,
@@ -3,9 +3,7 @@ import { ref, shallowRef } from "vue";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 import { ElMessage } from "element-plus";
-import type { ConnectionStatus, MqttMessage, EnvVariable } from "@/types/mqtt";
-import { ScriptEngine } from "@/utils/scriptEngine";
-import type { Script } from "@/stores/script";
+import type { ConnectionStatus, MqttMessage, MessageHistory, MessageProperties } from "@/types/mqtt";
 import { handleScriptError } from "@/utils/errorHandler";
 import i18n from "@/i18n";
 
@@ -13,6 +11,8 @@ interface ConnectionState {
   server_id: number;
   status: ConnectionStatus;
   error?: string;
+  /** 当前重连次数（仅 reconnecting 状态） */
+  attempt?: number;
 }
 
 interface ReceivedMessage {
@@ -21,24 +21,27 @@ interface ReceivedMessage {
   payload: number[];
   qos: number;
   retain: boolean;
+  /** MQTT 5 消息属性 */
+  properties?: MessageProperties;
+  /** 接收后处理脚本的输出 */
+  processed_payload?: string | null;
+  /** 接收后处理脚本的错误 */
+  script_error?: string | null;
+  /** 后端保存的历史记录 ID */
+  message_id?: number;
   timestamp: string;
 }
 
-  // 脚本缓存接口
-interface ScriptCache {
-  scripts: Script[];
-  timestamp: number;
-}
-
-// 环境变量缓存接口
-interface EnvCache {
-  variables: Record<string, string>;
-  timestamp: number;
+/** 脚本（自动回复、连接钩子）发出的消息 */
+interface ScriptPublished {
+  server_id: number;
+  script_id?: number | null;
+  script_name: string;
+  script_type: string;
+  message?: MessageHistory | null;
+  error?: string | null;
 }
 
-// 脚本缓存有效期（毫秒）
-const SCRIPT_CACHE_TTL = 5000;
-
 export const useMqttStore = defineStore("mqtt", () => {
   // 连接状态
   const connectionStates = ref<
@@ -51,80 +54,11 @@ export const useMqttStore = defineStore("mqtt", () => {
   // 订阅列表（按 server_id 分组）
   const subscriptions = ref<Map<number, Set<string>>>(new Map());
 
-  // 脚本缓存（避免高频调用 invoke）
-  const scriptCache = new Map<string, ScriptCache>();
-
-  // 环境变量缓存
-  const envCache = new Map<number, EnvCache>();
-
   // 消息批处理队列
   const messageQueue: MqttMessage[] = [];
   let batchTimeout: ReturnType<typeof setTimeout> | null = null;
   const BATCH_INTERVAL = 50; // 批处理间隔（毫秒）
 
-  // 获取缓存的脚本
-  async function getCachedScripts(serverId: number, scriptType: string): Promise<Script[]> {
-    const cacheKey = `${serverId}-${scriptType}`;
-    const cached = scriptCache.get(cacheKey);
-    const now = Date.now();
-
-    if (cached && now - cached.timestamp < SCRIPT_CACHE_TTL) {
-      return cached.scripts;
-    }
-
-    try {
-      const scripts = await invoke<Script[]>("get_enabled_scripts", {
-        serverId,
-        scriptType,
-      });
-      scriptCache.set(cacheKey, { scripts, timestamp: now });
-      return scripts;
-    } catch {
-      return [];
-    }
-  }
-
-  // 清除脚本缓存（当脚本更新时调用）
-  function clearScriptCache(serverId?: number) {
-    if (serverId) {
-      scriptCache.delete(`${serverId}-before_send`);
-      scriptCache.delete(`${serverId}-after_receive`);
-    } else {
-      scriptCache.clear();
-    }
-  }
-
-  // 获取缓存的环境变量
-  async function getCachedEnvVariables(serverId: number): Promise<Record<string, string>> {
-    const cached = envCache.get(serverId);
-    const now = Date.now();
-
-    if (cached && now - cached.timestamp < SCRIPT_CACHE_TTL) {
-      return cached.variables;
-    }
-
-    try {
-      const envList = await invoke<EnvVariable[]>("list_env_variables", { serverId });
-      const variables: Record<string, string> = {};
-      for (const env of envList) {
-        variables[env.name] = env.value;
-      }
-      envCache.set(serverId, { variables, timestamp: now });
-      return variables;
-    } catch {
-      return {};
-    }
-  }
-
-  // 清除环境变量缓存
-  function clearEnvCache(serverId?: number) {
-    if (serverId) {
-      envCache.delete(serverId);
-    } else {
-      envCache.clear();
-    }
-  }
-
   // 批量处理消息队列
   function flushMessageQueue() {
     if (messageQueue.length === 0) return;
@@ -182,32 +116,14 @@ export const useMqttStore = defineStore("mqtt", () => {
     });
 
     // 监听接收消息
-    await listen<ReceivedMessage>("mqtt-message", async (event) => {
+    await listen<ReceivedMessage>("mqtt-message", (event) => {
       const msg = event.payload;
-      let payloadBytes = new Uint8Array(msg.payload);
-      let scriptError: string | undefined = undefined;
-      
-      // 尝试应用接收后处理脚本（使用缓存）
-      try {
-        const scripts = await getCachedScripts(msg.server_id, "after_receive");
-        
-        if (scripts.length > 0) {
-          const originalPayload = new TextDecoder().decode(payloadBytes);
-          const envVariables = await getCachedEnvVariables(msg.server_id);
-          const processedPayload = await ScriptEngine.executeAfterReceive(
-            scripts,
-            originalPayload,
-            msg.topic,
-            envVariables
-          );
-          payloadBytes = new TextEncoder().encode(processedPayload);
-        }
-      } catch (error: any) {
-        // 记录脚本错误
-        scriptError = error?.message || String(error);
-        handleScriptError(error, true); // 静默处理，不显示通知（会写入日志）
-      }
-      
+      // 接收后处理脚本由后端执行
+      const payloadBytes =
+        msg.processed_payload != null
+          ? new TextEncoder().encode(msg.processed_payload)
+          : new Uint8Array(msg.payload);
+
       // 使用批处理队列
       queueMessage({
         server_id: msg.server_id,
@@ -217,9 +133,25 @@ export const useMqttStore = defineStore("mqtt", () => {
         qos: msg.qos as 0 | 1 | 2,
         retain: msg.retain,
         timestamp: msg.timestamp,
-        scriptError: scriptError,
+        scriptError: msg.script_error ?? undefined,
       });
     });
+
+    // 监听脚本发出的消息
+    await listen<ScriptPublished>("script-published", (event) => {
+      const { server_id, script_name, message, error } = event.payload;
+      if (message) {
+        addPublishMessage(server_id, {
+          topic: message.topic,
+          payload: message.payload ?? "",
+          qos: message.qos as 0 | 1 | 2,
+          retain: message.retain,
+          payload_type: message.payload_format ?? "text",
+        });
+      } else if (error) {
+        handleScriptError(`${script_name}: ${error}`, true);
+      }
+    });
   };
 
   // 连接
@@ -377,7 +309,5 @@ export const useMqttStore = defineStore("mqtt", () => {
     getServerMessages,
     clearMessages,
     addPublishMessage,
-    clearScriptCache,
-    clearEnvCache,
   };
 });
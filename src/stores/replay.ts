@@ -0,0 +1,47 @@
+import { defineStore } from "pinia";
+import { ref } from "vue";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import type { ReplayOptions, ReplayProgress, ReplaySource } from "@/types/mqtt";
+
+export const useReplayStore = defineStore("replay", () => {
+  // 回放任务进度，key 为 replay_id
+  const replays = ref<Map<string, ReplayProgress>>(new Map());
+
+  // 监听回放进度
+  const initListeners = async () => {
+    await listen<ReplayProgress>("replay-progress", (event) => {
+      replays.value.set(event.payload.replay_id, event.payload);
+    });
+  };
+
+  // 开始回放
+  const startReplay = async (
+    serverId: number,
+    source: ReplaySource,
+    options?: ReplayOptions
+  ) => {
+    return await invoke<string>("start_replay", { serverId, source, options });
+  };
+
+  const pauseReplay = async (replayId: string) => {
+    await invoke("pause_replay", { replayId });
+  };
+
+  const resumeReplay = async (replayId: string) => {
+    await invoke("resume_replay", { replayId });
+  };
+
+  const stopReplay = async (replayId: string) => {
+    await invoke("stop_replay", { replayId });
+  };
+
+  return {
+    replays,
+    initListeners,
+    startReplay,
+    pauseReplay,
+    resumeReplay,
+    stopReplay,
+  };
+});
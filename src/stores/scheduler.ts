@@ -0,0 +1,74 @@
+import { defineStore } from "pinia";
+import { ref } from "vue";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import type { JobStatus, ScheduledJob } from "@/types/mqtt";
+
+export const useSchedulerStore = defineStore("scheduler", () => {
+  // 定时任务列表，key 为 server_id
+  const jobs = ref<Map<number, ScheduledJob[]>>(new Map());
+  // 任务最新状态，key 为 job_id
+  const statuses = ref<Map<number, JobStatus>>(new Map());
+  // 状态回调，供对话框记录发送日志
+  const statusHandlers = new Set<(status: JobStatus) => void>();
+
+  // 监听任务状态
+  const initListeners = async () => {
+    await listen<JobStatus>("scheduled-job-status", (event) => {
+      statuses.value.set(event.payload.job_id, event.payload);
+      statusHandlers.forEach((handler) => handler(event.payload));
+    });
+  };
+
+  const onStatus = (handler: (status: JobStatus) => void) => {
+    statusHandlers.add(handler);
+    return () => statusHandlers.delete(handler);
+  };
+
+  const loadJobs = async (serverId: number) => {
+    const result = await invoke<ScheduledJob[]>("list_scheduled_jobs", {
+      serverId,
+    });
+    jobs.value.set(serverId, result);
+    return result;
+  };
+
+  const createJob = async (job: ScheduledJob) => {
+    const result = await invoke<ScheduledJob>("create_scheduled_job", { job });
+    await loadJobs(job.server_id);
+    return result;
+  };
+
+  const updateJob = async (job: ScheduledJob) => {
+    const result = await invoke<ScheduledJob>("update_scheduled_job", { job });
+    await loadJobs(job.server_id);
+    return result;
+  };
+
+  const deleteJob = async (serverId: number, id: number) => {
+    await invoke("delete_scheduled_job", { id });
+    statuses.value.delete(id);
+    await loadJobs(serverId);
+  };
+
+  const startJob = async (id: number) => {
+    return await invoke<ScheduledJob>("start_scheduled_job", { id });
+  };
+
+  const stopJob = async (id: number) => {
+    await invoke("stop_scheduled_job", { id });
+  };
+
+  return {
+    jobs,
+    statuses,
+    initListeners,
+    onStatus,
+    loadJobs,
+    createJob,
+    updateJob,
+    deleteJob,
+    startJob,
+    stopJob,
+  };
+});
@@ -2,7 +2,13 @@ import { defineStore } from "pinia";
 import { ref } from "vue";
 import { invoke } from "@tauri-apps/api/core";
 
-export type ScriptType = "before_publish" | "after_receive";
+export type ScriptType =
+  | "before_publish"
+  | "after_receive"
+  | "message_filter"
+  | "auto_responder"
+  | "on_connect"
+  | "on_disconnect";
 
 export interface Script {
   id?: number;
@@ -12,6 +18,8 @@ export interface Script {
   code: string;
   enabled: boolean;
   description?: string;
+  /** 消息过滤和自动回复脚本的主题过滤器 */
+  topic_filter?: string | null;
   created_at?: string;
   updated_at?: string;
 }
@@ -23,6 +31,7 @@ export interface CreateScriptRequest {
   code: string;
   enabled: boolean;
   description?: string;
+  topic_filter?: string;
 }
 
 export interface UpdateScriptRequest {
@@ -31,6 +40,34 @@ export interface UpdateScriptRequest {
   code?: string;
   enabled?: boolean;
   description?: string;
+  /** 空字符串表示清除 */
+  topic_filter?: string;
+}
+
+export interface ScriptTestRequest {
+  /** 已保存的脚本 ID */
+  script_id?: number;
+  /** 未保存的代码，优先于 script_id */
+  code?: string;
+  /** 提供环境变量的服务器 */
+  server_id?: number;
+  topic: string;
+  payload: string;
+}
+
+export interface ScriptError {
+  message: string;
+  line?: number | null;
+  column?: number | null;
+}
+
+export interface ScriptTestResult {
+  success: boolean;
+  payload?: string | null;
+  logs: string[];
+  /** 执行耗时（毫秒） */
+  duration_ms: number;
+  error?: ScriptError | null;
 }
 
 export const useScriptStore = defineStore("script", () => {
@@ -85,6 +122,11 @@ export const useScriptStore = defineStore("script", () => {
     await loadScripts(serverId);
   }
 
+  // 使用示例消息试运行脚本
+  async function testScript(request: ScriptTestRequest): Promise<ScriptTestResult> {
+    return await invoke<ScriptTestResult>("test_script", { request });
+  }
+
   return {
     scripts,
     loading,
@@ -94,5 +136,6 @@ export const useScriptStore = defineStore("script", () => {
     updateScript,
     deleteScript,
     toggleScript,
+    testScript,
   };
 });
@@ -41,6 +41,21 @@ export const useServerStore = defineStore("server", () => {
     }
   };
 
+  // 重新读取 Server 列表并保留连接状态（保险库解锁或锁定、导入工作区后）
+  const reloadServerConfigs = async () => {
+    const data = await invoke<MqttServer[]>("get_servers");
+    servers.value = data.map((server) => {
+      const item = servers.value.find((s) => s.server.id === server.id);
+      return item
+        ? { ...item, server }
+        : { server, status: "disconnected" as ConnectionStatus };
+    });
+    const activeId = activeServerId.value;
+    if (activeId !== null && !servers.value.some((s) => s.server.id === activeId)) {
+      activeServerId.value = servers.value[0]?.server.id ?? null;
+    }
+  };
+
   // 创建 Server
   const createServer = async (
     serverData: Omit<MqttServer, "id" | "created_at" | "updated_at">
@@ -134,6 +149,7 @@ export const useServerStore = defineStore("server", () => {
     activeServer,
     loading,
     fetchServers,
+    reloadServerConfigs,
     createServer,
     updateServer,
     removeServer,
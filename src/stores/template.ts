@@ -43,6 +43,22 @@ export interface UpdateTemplateRequest {
   category?: string
 }
 
+// 导入时同名模板的处理方式：跳过或覆盖
+export type TemplateImportStrategy = 'skip' | 'overwrite'
+
+export interface TemplateImportIssue {
+  index: number
+  name: string
+  reason: string
+}
+
+export interface TemplateImportReport {
+  created: string[]
+  updated: string[]
+  skipped: TemplateImportIssue[]
+  failed: TemplateImportIssue[]
+}
+
 export const useTemplateStore = defineStore('template', () => {
   const templates = ref<CommandTemplate[]>([])
   const categories = ref<string[]>([])
@@ -215,11 +231,19 @@ export const useTemplateStore = defineStore('template', () => {
   }
 
   // 导入模板
-  async function importTemplates(serverId: number, jsonData: string): Promise<number> {
+  async function importTemplates(
+    serverId: number,
+    jsonData: string,
+    strategy: TemplateImportStrategy
+  ): Promise<TemplateImportReport> {
     try {
-      const count = await invoke<number>('import_templates', { serverId, jsonData })
+      const report = await invoke<TemplateImportReport>('import_templates', {
+        serverId,
+        jsonData,
+        strategy
+      })
       await loadTemplates(serverId)
-      return count
+      return report
     } catch (error) {
       console.error('导入模板失败:', error)
       throw error
@@ -17,10 +17,45 @@ export interface MqttServer {
   client_cert?: string;
   client_key?: string;
   client_key_password?: string;
+  /** 自动重连策略 */
+  reconnect?: ReconnectPolicy;
+  /** 是否由后端保存收到的消息 */
+  save_received_messages?: boolean;
+  /** 历史消息保留策略 */
+  retention?: RetentionPolicy;
   created_at?: string;
   updated_at?: string;
 }
 
+/**
+ * 历史消息保留策略，超出任一限制的旧消息会在后台清理
+ */
+export interface RetentionPolicy {
+  /** 最多保留的消息条数 */
+  max_messages?: number | null;
+  /** 消息最长保留时间（秒） */
+  max_age_secs?: number | null;
+  /** 消息总大小上限（字节） */
+  max_bytes?: number | null;
+  /** 删除前写入归档文件 */
+  archive: boolean;
+}
+
+/**
+ * 自动重连策略（指数退避）
+ */
+export interface ReconnectPolicy {
+  enabled: boolean;
+  /** 首次重连等待时间（毫秒） */
+  initial_delay_ms: number;
+  /** 最大重连等待时间（毫秒） */
+  max_delay_ms: number;
+  /** 随机抖动比例（0 ~ 1） */
+  jitter: number;
+  /** 最大重连次数，0 表示不限制 */
+  max_attempts: number;
+}
+
 /**
  * 命令模板
  */
@@ -66,9 +101,21 @@ export interface Subscription {
   is_active: boolean;
   /** 订阅的颜色标记（用于消息列表中高亮显示） */
   color?: string;
+  /** 最近一次订阅时 broker 返回的结果 */
+  last_grant?: SubscriptionGrant;
   created_at?: string;
 }
 
+/**
+ * 订阅授权结果（来自 SUBACK）
+ */
+export interface SubscriptionGrant {
+  /** Broker 授予的 QoS，失败时为空 */
+  granted_qos?: number;
+  error?: string;
+  updated_at?: string;
+}
+
 /**
  * 更新订阅请求
  */
@@ -88,12 +135,170 @@ export interface MessageHistory {
   topic: string;
   payload?: string;
   payload_format?: "text" | "json" | "hex";
+  /** 原始内容字节（base64） */
+  raw_payload?: string;
   direction: "publish" | "receive";
   qos: number;
   retain: boolean;
+  /** MQTT 5 消息属性 */
+  properties?: MessageProperties;
+  /** 投递状态（仅发布消息） */
+  delivery_state?: DeliveryState;
+  /** 从写出到收到确认的耗时（毫秒） */
+  latency_ms?: number;
+  delivery_error?: string;
   created_at?: string;
 }
 
+/**
+ * 历史消息查询条件
+ */
+export interface MessageQuery {
+  server_id: number;
+  /** 主题过滤器，支持 + / # 通配符 */
+  topic?: string;
+  direction?: "publish" | "receive";
+  qos?: number;
+  retain?: boolean;
+  /** 起始时间（RFC3339，含） */
+  since?: string;
+  /** 结束时间（RFC3339，不含） */
+  until?: string;
+  payload_contains?: string;
+  payload_regex?: string;
+  /** 上一页返回的 next_cursor */
+  cursor?: number;
+  /** 按时间正序返回（默认倒序） */
+  ascending?: boolean;
+  /** 每页条数，默认 100，最大 1000 */
+  limit?: number;
+}
+
+/**
+ * 历史消息分页结果
+ */
+export interface MessagePage {
+  messages: MessageHistory[];
+  next_cursor?: number;
+}
+
+/**
+ * 历史消息导出格式
+ */
+export type ExportFormat = "csv" | "ndjson" | "replay";
+
+/**
+ * 历史消息导出结果
+ */
+export interface ExportSummary {
+  exported: number;
+  /** 要求脱敏但未能脱敏时的提示（例如保险库已锁定） */
+  warning: string | null;
+}
+
+/**
+ * 回放数据来源
+ */
+export type ReplaySource =
+  | { type: "messages"; message_ids: number[] }
+  | { type: "query"; query: MessageQuery }
+  | { type: "file"; path: string };
+
+/**
+ * 回放选项
+ */
+export interface ReplayOptions {
+  /** 速度倍率，2 表示两倍速 */
+  speed: number;
+  /** 两条消息之间的最长等待时间（毫秒） */
+  max_gap_ms?: number;
+}
+
+/**
+ * 回放进度事件
+ */
+export interface ReplayProgress {
+  replay_id: string;
+  server_id: number;
+  status: "running" | "paused" | "completed" | "stopped" | "failed";
+  sent: number;
+  total: number;
+  error?: string;
+}
+
+/**
+ * 定时发布任务的触发方式
+ */
+export type JobTrigger =
+  | { type: "interval"; interval_ms: number; round_interval_ms?: number }
+  | { type: "cron"; expression: string };
+
+/**
+ * 定时发布任务
+ */
+export interface ScheduledJob {
+  id?: number;
+  server_id: number;
+  name: string;
+  /** 按顺序循环发送的命令模板，为空时发送 message */
+  template_ids: number[];
+  message?: PublishPayload;
+  trigger: JobTrigger;
+  /** 最多发送的消息条数 */
+  max_runs?: number;
+  start_at?: string;
+  end_at?: string;
+  enabled: boolean;
+  run_count?: number;
+  last_run_at?: string;
+  created_at?: string;
+  updated_at?: string;
+}
+
+/**
+ * 定时任务状态事件
+ */
+export interface JobStatus {
+  job_id: number;
+  server_id: number;
+  status: "running" | "completed" | "stopped" | "failed";
+  run_count: number;
+  next_run_at?: string;
+  /** 上一次发送的消息 */
+  message?: MessageHistory;
+  error?: string;
+}
+
+/**
+ * 发布消息的投递状态
+ */
+export type DeliveryState = "queued" | "sent" | "acked" | "failed";
+
+/**
+ * MQTT 5 用户属性
+ */
+export interface UserProperty {
+  key: string;
+  value: string;
+}
+
+/**
+ * MQTT 5 消息属性
+ */
+export interface MessageProperties {
+  user_properties?: UserProperty[];
+  content_type?: string;
+  response_topic?: string;
+  correlation_data?: number[];
+  /** 消息过期时间（秒） */
+  message_expiry_interval?: number;
+  payload_format_indicator?: 0 | 1;
+  /** 匹配的订阅标识符（仅接收） */
+  subscription_identifiers?: number[];
+  /** 主题别名（仅接收） */
+  topic_alias?: number;
+}
+
 /**
  * 发布消息载荷
  */
@@ -103,6 +308,12 @@ export interface PublishPayload {
   qos: number;
   retain: boolean;
   format: "text" | "json" | "hex";
+  /** MQTT 5 发布属性（仅 5.0 协议） */
+  properties?: MessageProperties;
+  /** 等待 Broker 确认后再返回 */
+  await_ack?: boolean;
+  /** 等待确认的超时时间（毫秒），默认 10000 */
+  ack_timeout_ms?: number;
 }
 
 /**
@@ -112,6 +323,7 @@ export type ConnectionStatus =
   | "disconnected"
   | "connecting"
   | "connected"
+  | "reconnecting"
   | "error";
 
 /**
@@ -119,10 +331,14 @@ export type ConnectionStatus =
  */
 export interface EnvVariable {
   id?: number;
-  server_id: number;
+  /** 为空表示全局变量 */
+  server_id: number | null;
   name: string;
+  /** 敏感变量在保险库锁定时为空 */
   value: string;
   description?: string;
+  /** 是否为敏感变量（加密存储） */
+  secret?: boolean;
   created_at?: string;
   updated_at?: string;
 }
@@ -131,10 +347,12 @@ export interface EnvVariable {
  * 创建环境变量请求
  */
 export interface CreateEnvVariableRequest {
-  server_id: number;
+  /** 为空表示创建全局变量 */
+  server_id?: number | null;
   name: string;
   value: string;
   description?: string;
+  secret?: boolean;
 }
 
 /**
@@ -145,6 +363,49 @@ export interface UpdateEnvVariableRequest {
   name?: string;
   value?: string;
   description?: string;
+  secret?: boolean;
+}
+
+/**
+ * 工作区导出选项
+ */
+export interface WorkspaceExportOptions {
+  include_history: boolean;
+  /** 去除用户名、密码、客户端私钥和敏感变量的值 */
+  strip_credentials: boolean;
+}
+
+/**
+ * 工作区导入方式：合并到现有数据，或替换现有数据
+ */
+export type ImportMode = "merge" | "replace";
+
+/**
+ * 导入时跳过或失败的条目
+ */
+export interface ImportIssue {
+  kind: string;
+  name: string;
+  server?: string;
+  reason: string;
+}
+
+/**
+ * 可导入连接配置的其他 MQTT 工具
+ */
+export type ExternalSource = "mqttx" | "mqtt_explorer";
+
+/**
+ * 导入结果
+ */
+export interface ImportReport {
+  created: Record<string, number>;
+  conflicts: ImportIssue[];
+  failed: ImportIssue[];
+  /** 已导入但部分字段无法导入的条目（从其他工具导入时） */
+  warnings: ImportIssue[];
+  /** 导入前自动创建的备份文件名 */
+  backup?: string;
 }
 
 /**